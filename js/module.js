@@ -15,34 +15,53 @@ export async function convertArray(jsonData, config = {}) {
     await ensureInit();
 
     const getStyles = config.styles === undefined ? true : config.styles;
-    const xlsx = XLSX.new(jsonData);
-    const styles = getStyles ? xlsx.get_styles() : null;
+    const mode = 0 | (config.formulas ? XLSX.with_formulas() : 0)
+        | (config.types ? XLSX.with_typed_values() : 0)
+        | (config.dates ? XLSX.with_dates() : 0)
+        | (config.formatted ? XLSX.with_formatted_text() : 0)
+        | (config.rich ? XLSX.with_rich_text() : 0)
+        | (config.sparse ? XLSX.with_sparse_cells() : 0)
+        | (config.validationValues ? XLSX.with_validation_values() : 0);
+    const xlsx = config.csv ? XLSX.from_csv(jsonData, config.delimiter || "", !!config.inferTypes) : XLSX.try_new(jsonData);
+    const styleFlags = config.borderDetails ? XLSX.with_border_details() : 0;
+    const styles = getStyles ? xlsx.try_get_styles_with(styleFlags) : null;
+    const dxfs = getStyles ? xlsx.try_get_differential_styles_with(styleFlags) : null;
 
-    let data;
-    if (config.sheet) {
-        data = [xlsx.get_sheet_data(config.sheet)];
-    } else {
-        const sheets = xlsx.get_sheets();
-        const mode = 0 | (config.formulas ? XLSX.with_formulas() : 0);
-        data = sheets.map(name => xlsx.get_sheet_data(name, mode));
-    }
+    const sheets = xlsx.get_sheet_descriptors();
+    const list = config.sheet ? [config.sheet] : sheetNames(sheets, config);
+    const data = list.map(name => xlsx.try_get_sheet_data(name, mode));
+    const names = xlsx.get_defined_names();
+
+    return { data, styles, dxfs, names, sheets };
+}
 
-    return { data, styles };
+// names of sheets to convert, hidden sheets and sheets without cells can be skipped
+function sheetNames(sheets, config) {
+    return sheets
+        .filter(sheet => !config.skipHidden || sheet.state === "visible")
+        .filter(sheet => !config.skipCharts || (sheet.type !== "chartsheet" && sheet.type !== "dialogsheet"))
+        .map(sheet => sheet.name);
 }
 
 export async function convert(jsonData, config = {}) {
     await ensureInit();
 
     if (jsonData instanceof File) {
-        return new Promise((res) => {
+        return new Promise((res, rej) => {
             const reader = new FileReader();
             reader.readAsArrayBuffer(jsonData);
-            reader.onload = async (e) => {
-                const result = await convertArray(new Uint8Array(e.target.result), config);
-                res(result);
+            reader.onload = (e) => {
+                convertArray(new Uint8Array(e.target.result), config).then(res, rej);
             };
         });
     } else {
         return await convertArray(jsonData, config);
     }
 }
+
+// xlsx file from { data, styles } object returned by `convert`
+export async function exportXlsx(json) {
+    await ensureInit();
+    const data = XLSX.to_xlsx(json);
+    return new Blob([data], { type: "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet" });
+}
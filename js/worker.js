@@ -16,33 +16,105 @@ onmessage = async function (e) {
         } else {
             doConvert(file, config);
         }
+    } else if (config.type === "export") {
+        doExport(config);
     }
 };
 
+async function doExport(config) {
+    await init();
+
+    let data;
+    try {
+        data = XLSX.to_xlsx(config.data);
+    } catch (error) {
+        postMessage({
+            uid: config.uid || Date.now(),
+            type: "error",
+            error
+        });
+        return;
+    }
+
+    postMessage({
+        uid: config.uid || Date.now(),
+        type: "export",
+        data: new Blob([data], { type: "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet" })
+    });
+}
+
 async function doConvert(input, config) {
     await init();
 
     const getStyles = config.styles === undefined ? true : config.styles;
+    const mode = 0 | (config.formulas ? XLSX.with_formulas() : 0)
+        | (config.types ? XLSX.with_typed_values() : 0)
+        | (config.dates ? XLSX.with_dates() : 0)
+        | (config.formatted ? XLSX.with_formatted_text() : 0)
+        | (config.rich ? XLSX.with_rich_text() : 0)
+        | (config.sparse ? XLSX.with_sparse_cells() : 0)
+        | (config.validationValues ? XLSX.with_validation_values() : 0);
 
-    const xlsx = XLSX.new(input);
-    const styles = getStyles ? xlsx.get_styles() : null;
-
-    let sheetsData;
-    if (config.sheet) {
-        const data = xlsx.get_sheet_data(config.sheet);
-        sheetsData = [data];
-    } else {
-        const sheets = xlsx.get_sheets();
-        const mode = 0 | (config.formulas ? XLSX.with_formulas() : 0);
-        sheetsData = sheets.map(name => xlsx.get_sheet_data(name, mode));
+    let styles, dxfs, names, sheets, sheetsData;
+    try {
+        const xlsx = config.csv ? XLSX.from_csv(input, config.delimiter || "", !!config.inferTypes) : XLSX.try_new(input);
+        const styleFlags = config.borderDetails ? XLSX.with_border_details() : 0;
+        styles = getStyles ? xlsx.try_get_styles_with(styleFlags) : null;
+        dxfs = getStyles ? xlsx.try_get_differential_styles_with(styleFlags) : null;
+        names = xlsx.get_defined_names();
+
+        sheets = xlsx.get_sheet_descriptors();
+        const list = config.sheet ? [config.sheet] : sheetNames(sheets, config);
+        if (config.stream) {
+            sheetsData = list.map(name => streamRows(xlsx, name, mode, config));
+        } else {
+            sheetsData = list.map(name => xlsx.try_get_sheet_data(name, mode));
+        }
+    } catch (error) {
+        postMessage({
+            uid: config.uid || Date.now(),
+            type: "error",
+            error
+        });
+        return;
     }
 
     postMessage({
         uid: config.uid || Date.now(),
         type: "ready",
         data: sheetsData,
-        styles
+        styles,
+        dxfs,
+        names,
+        sheets
     });
 }
 
+// names of sheets to convert, hidden sheets and sheets without cells can be skipped
+function sheetNames(sheets, config) {
+    return sheets
+        .filter(sheet => !config.skipHidden || sheet.state === "visible")
+        .filter(sheet => !config.skipCharts || (sheet.type !== "chartsheet" && sheet.type !== "dialogsheet"))
+        .map(sheet => sheet.name);
+}
+
+// posts rows in chunks, returns sheet data without cells
+function streamRows(xlsx, name, mode, config) {
+    const chunkSize = config.chunkSize || 1000;
+    xlsx.open_rows(name, mode);
+    for (;;) {
+        const rows = xlsx.next_rows(chunkSize);
+        if (!rows.length) break;
+
+        postMessage({
+            uid: config.uid || Date.now(),
+            type: "rows",
+            sheet: name,
+            rows,
+            progress: xlsx.rows_progress()
+        });
+    }
+    return xlsx.rows_sheet_data();
+}
+
 postMessage({ type:"init" });
\ No newline at end of file
@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::{env, fs, io, process};
+
+use serde::Serialize;
+use serde_json::json;
+
+use excel2json_wasm::{CsvOptions, SheetState, Workbook, WITH_DATES, WITH_FORMATTED_TEXT, WITH_FORMULAS, WITH_RICH_TEXT, WITH_SPARSE_CELLS, WITH_TYPED_VALUES, WITH_VALIDATION_VALUES};
+
+const USAGE: &str = "Usage: excel2json [OPTIONS] <FILE>
+
+Converts xlsx, xlsb, xls or ods file to json, prints { data, styles, dxfs, names, sheets } to stdout.
+Files with .csv and .tsv extension are read as csv.
+Use \"-\" as FILE to read from stdin.
+
+Options:
+    --sheet <NAME>      convert only the sheet with this name
+    --skip-hidden       do not convert hidden sheets
+    --skip-charts       do not convert chart and dialog sheets
+    --formulas          export formulas instead of their values
+    --types             export numbers and booleans as json values
+    --dates             export dates as ISO-8601 strings
+    --formatted         add text formatted with number format
+    --rich              add rich text runs
+    --sparse            export only populated cells, as a list of { r, c, v, s }
+    --validation-values add values of validation lists given by a range
+    --no-styles         do not export styles
+    --pretty            pretty print json
+    --out-dir <DIR>     write each sheet to <DIR>/sheets/<sheet>.json and styles to <DIR>/styles.json and dxfs.json
+    --csv               read the file as csv, delimiter is detected by the content
+    --delimiter <CHAR>  delimiter of csv fields, implies --csv
+    --infer-types       convert csv numbers and ISO dates to numbers
+    -h, --help          print this help";
+
+struct Config {
+    file: String,
+    sheet: Option<String>,
+    flags: u32,
+    styles: bool,
+    pretty: bool,
+    out_dir: Option<PathBuf>,
+    skip_hidden: bool,
+    skip_charts: bool,
+    // set for csv input
+    csv: Option<CsvOptions>,
+}
+
+fn parse_args(args: &[String]) -> Result<Config, String> {
+    let mut config = Config { file: String::new(), sheet: None, flags: 0, styles: true, pretty: false, out_dir: None, skip_hidden: false, skip_charts: false, csv: None };
+    let mut file: Option<String> = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--sheet" => {
+                config.sheet = Some(args.next().ok_or("--sheet requires a sheet name")?.clone());
+            },
+            "--out-dir" => {
+                config.out_dir = Some(PathBuf::from(args.next().ok_or("--out-dir requires a directory")?));
+            },
+            "--delimiter" => {
+                let delimiter = args.next().ok_or("--delimiter requires a character")?;
+                let delimiter = match delimiter.as_str() {
+                    "\\t" | "tab" => '\t',
+                    _ if delimiter.chars().count() == 1 => delimiter.chars().next().unwrap_or(','),
+                    _ => return Err(format!("invalid delimiter {}", delimiter)),
+                };
+                config.csv.get_or_insert_with(CsvOptions::default).delimiter = Some(delimiter);
+            },
+            "--csv" => { config.csv.get_or_insert_with(CsvOptions::default); },
+            "--infer-types" => config.csv.get_or_insert_with(CsvOptions::default).infer_types = true,
+            "--formulas" => config.flags |= WITH_FORMULAS,
+            "--types" => config.flags |= WITH_TYPED_VALUES,
+            "--dates" => config.flags |= WITH_DATES,
+            "--formatted" => config.flags |= WITH_FORMATTED_TEXT,
+            "--rich" => config.flags |= WITH_RICH_TEXT,
+            "--sparse" => config.flags |= WITH_SPARSE_CELLS,
+            "--validation-values" => config.flags |= WITH_VALIDATION_VALUES,
+            "--skip-hidden" => config.skip_hidden = true,
+            "--skip-charts" => config.skip_charts = true,
+            "--no-styles" => config.styles = false,
+            "--pretty" => config.pretty = true,
+            "-" => file = Some(arg.clone()),
+            _ if arg.starts_with('-') => return Err(format!("unknown option {}", arg)),
+            _ if file.is_some() => return Err(format!("unexpected argument {}", arg)),
+            _ => file = Some(arg.clone()),
+        }
+    }
+    config.file = file.ok_or("missing input file")?;
+    let extension = Path::new(&config.file).extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase());
+    if matches!(extension.as_deref(), Some("csv") | Some("tsv")) {
+        config.csv.get_or_insert_with(CsvOptions::default);
+    }
+    // csv sheet is named by the file
+    if let (Some(options), false) = (config.csv.as_mut(), config.file == "-") {
+        options.sheet_name = Path::new(&config.file).file_stem().map(|stem| stem.to_string_lossy().into_owned());
+    }
+    Ok(config)
+}
+
+fn to_json<T: Serialize>(value: &T, pretty: bool) -> String {
+    let result = if pretty { serde_json::to_string_pretty(value) } else { serde_json::to_string(value) };
+    result.expect("data is always serializable")
+}
+
+// sheet names can contain characters not allowed in file names
+fn file_name(sheet: &str) -> String {
+    let name: String = sheet.chars()
+        .map(|c| if matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') { '_' } else { c })
+        .collect();
+    format!("{}.json", name)
+}
+
+// file of each sheet, names which become the same file are rejected instead of being overwritten
+// file names are compared ignoring case, as some file systems do
+fn file_names(sheets: &[String]) -> Result<Vec<String>, String> {
+    let mut used: HashMap<String, &str> = HashMap::new();
+    let mut result = vec!();
+    for sheet in sheets.iter() {
+        let name = file_name(sheet);
+        if let Some(other) = used.insert(name.to_lowercase(), sheet) {
+            return Err(format!("sheets \"{}\" and \"{}\" are both written to {}", other, sheet, name));
+        }
+        result.push(name);
+    }
+    Ok(result)
+}
+
+fn write_file(path: &Path, content: &str) -> Result<(), String> {
+    fs::write(path, content).map_err(|e| format!("can't write {}: {}", path.display(), e))
+}
+
+fn run(config: Config) -> Result<(), String> {
+    let mut data = vec!();
+    if config.file == "-" {
+        io::stdin().read_to_end(&mut data).map_err(|e| format!("can't read stdin: {}", e))?;
+    } else {
+        data = fs::read(&config.file).map_err(|e| format!("can't read {}: {}", config.file, e))?;
+    }
+
+    let mut workbook = match config.csv {
+        Some(options) => Workbook::from_csv(data, options),
+        None => Workbook::from_vec(data).map_err(|e| e.to_string())?,
+    };
+    let (skip_hidden, skip_charts) = (config.skip_hidden, config.skip_charts);
+    let names = match config.sheet {
+        Some(name) => vec!(name),
+        None => workbook.sheets().into_iter()
+            .filter(|sheet| !skip_hidden || sheet.state == SheetState::Visible)
+            .filter(|sheet| !skip_charts || sheet.has_cells())
+            .map(|sheet| sheet.name)
+            .collect(),
+    };
+    let (styles, dxfs) = if config.styles {
+        (Some(workbook.styles().map_err(|e| e.to_string())?), Some(workbook.differential_styles(0).map_err(|e| e.to_string())?))
+    } else {
+        (None, None)
+    };
+    let mut sheets = vec!();
+    for name in names.iter() {
+        sheets.push(workbook.sheet_with(name, config.flags).map_err(|e| e.to_string())?);
+    }
+
+    match config.out_dir {
+        Some(dir) => {
+            // sheets are in their own directory, so they can't replace styles.json
+            let sheets_dir = dir.join("sheets");
+            let files = file_names(&names)?;
+            fs::create_dir_all(&sheets_dir).map_err(|e| format!("can't create {}: {}", sheets_dir.display(), e))?;
+            for (sheet, file) in sheets.iter().zip(files.iter()) {
+                write_file(&sheets_dir.join(file), &to_json(sheet, config.pretty))?;
+            }
+            if let Some(styles) = styles {
+                write_file(&dir.join("styles.json"), &to_json(&styles, config.pretty))?;
+            }
+            if let Some(dxfs) = dxfs {
+                write_file(&dir.join("dxfs.json"), &to_json(&dxfs, config.pretty))?;
+            }
+        },
+        None => {
+            let output = to_json(&json!({ "data": sheets, "styles": styles, "dxfs": dxfs, "names": workbook.defined_names(), "sheets": workbook.sheets() }), config.pretty);
+            match writeln!(io::stdout(), "{}", output) {
+                // output is piped to a command which doesn't read it all, like `head`
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => (),
+                result => result.map_err(|e| e.to_string())?,
+            }
+        },
+    }
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.iter().any(|a| a == "-h" || a == "--help") {
+        println!("{}", USAGE);
+        return;
+    }
+
+    let config = match parse_args(&args) {
+        Ok(config) => config,
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            process::exit(2);
+        },
+    };
+    if let Err(message) = run(config) {
+        eprintln!("error: {}", message);
+        process::exit(1);
+    }
+}
+
+
+#[test]
+fn test_parse_args() {
+    let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+
+    let config = parse_args(&args(&["--sheet", "Sheet 1", "--formulas", "--dates", "--sparse", "--no-styles", "book.xlsx"])).unwrap();
+    assert_eq!(config.file, "book.xlsx");
+    assert_eq!(config.sheet.as_deref(), Some("Sheet 1"));
+    assert_eq!(config.flags, WITH_FORMULAS | WITH_DATES | WITH_SPARSE_CELLS);
+    assert!(!config.styles);
+    assert!(!config.pretty);
+    assert!(!config.skip_hidden && !config.skip_charts);
+
+    let config = parse_args(&args(&["--skip-hidden", "--skip-charts", "book.xlsx"])).unwrap();
+    assert!(config.skip_hidden && config.skip_charts);
+
+    assert!(parse_args(&args(&["--sheet"])).is_err());
+    assert!(parse_args(&args(&["--unknown", "book.xlsx"])).is_err());
+    assert!(parse_args(&args(&["a.xlsx", "b.xlsx"])).is_err());
+    assert!(parse_args(&args(&["--pretty"])).is_err());
+    assert!(config.csv.is_none());
+
+    let config = parse_args(&args(&["--delimiter", "\\t", "--infer-types", "-"])).unwrap();
+    let options = config.csv.unwrap();
+    assert_eq!(options.delimiter, Some('\t'));
+    assert!(options.infer_types);
+    assert_eq!(options.sheet_name, None);
+    assert_eq!(parse_args(&args(&["Orders.CSV"])).unwrap().csv.unwrap().sheet_name.as_deref(), Some("Orders"));
+    assert!(parse_args(&args(&["--delimiter", ";;", "a.csv"])).is_err());
+    assert_eq!(file_name("Q1/Q2: plan"), "Q1_Q2_ plan.json");
+
+    let names = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+    assert_eq!(file_names(&names(&["styles", "dxfs"])).unwrap(), vec!["styles.json", "dxfs.json"]);
+    assert!(file_names(&names(&["a/b", "a:b"])).is_err());
+    assert!(file_names(&names(&["Plan", "a_b", "A/B"])).is_err());
+}
@@ -1,13 +1,38 @@
+use serde::Serialize;
+
 const THIN_BORDER: &str = "0.5px";
 const MEDIUM_BORDER: &str = "1px";
 const THICK_BORDER: &str = "2px";
 
+#[derive(Clone, Copy)]
 pub enum BorderPosition {
     Left,
     Right,
     Top,
     Bottom,
+    // <diagonal> line, direction is set by diagonalUp and diagonalDown attributes of <border>
+    DiagonalUp,
+    DiagonalDown,
 }
+
+// css has no dash-dot lines, so they are shown as dashed
+//
+// | xlsx             | width | css    |
+// |------------------|-------|--------|
+// | hair             | 1px   | dashed |
+// | thin             | 0.5px | solid  |
+// | medium           | 1px   | solid  |
+// | thick            | 2px   | solid  |
+// | double           | 1px   | double |
+// | dotted           | 1px   | dotted |
+// | dashed           | 0.5px | dashed |
+// | mediumDashed     | 1px   | dashed |
+// | dashDot          | 0.5px | dashed |
+// | mediumDashDot    | 1px   | dashed |
+// | dashDotDot       | 0.5px | dotted |
+// | mediumDashDotDot | 1px   | dotted |
+// | slantDashDot     | 1px   | dashed |
+#[derive(Clone, Copy)]
 enum BorderStyle {
     Medium,
     Dotted,
@@ -15,8 +40,82 @@ enum BorderStyle {
     Thin,
     Hair,
     Double,
+    Dashed,
+    MediumDashed,
+    DashDot,
+    MediumDashDot,
+    DashDotDot,
+    MediumDashDotDot,
+    SlantDashDot,
+}
+
+impl BorderStyle {
+    fn from_xlsx(style: &str) -> Option<BorderStyle> {
+        match style {
+            "thin" => Some(BorderStyle::Thin),
+            "medium" => Some(BorderStyle::Medium),
+            "thick" => Some(BorderStyle::Thick),
+            "double" => Some(BorderStyle::Double),
+            "dotted" => Some(BorderStyle::Dotted),
+            "hair" => Some(BorderStyle::Hair),
+            "dashed" => Some(BorderStyle::Dashed),
+            "mediumDashed" => Some(BorderStyle::MediumDashed),
+            "dashDot" => Some(BorderStyle::DashDot),
+            "mediumDashDot" => Some(BorderStyle::MediumDashDot),
+            "dashDotDot" => Some(BorderStyle::DashDotDot),
+            "mediumDashDotDot" => Some(BorderStyle::MediumDashDotDot),
+            "slantDashDot" => Some(BorderStyle::SlantDashDot),
+            // "none" and unknown values
+            _ => None,
+        }
+    }
+    fn to_xlsx(self) -> &'static str {
+        match self {
+            BorderStyle::Thin => "thin",
+            BorderStyle::Medium => "medium",
+            BorderStyle::Thick => "thick",
+            BorderStyle::Double => "double",
+            BorderStyle::Dotted => "dotted",
+            BorderStyle::Hair => "hair",
+            BorderStyle::Dashed => "dashed",
+            BorderStyle::MediumDashed => "mediumDashed",
+            BorderStyle::DashDot => "dashDot",
+            BorderStyle::MediumDashDot => "mediumDashDot",
+            BorderStyle::DashDotDot => "dashDotDot",
+            BorderStyle::MediumDashDotDot => "mediumDashDotDot",
+            BorderStyle::SlantDashDot => "slantDashDot",
+        }
+    }
+    fn css_width(self) -> &'static str {
+        match self {
+            BorderStyle::Thin | BorderStyle::Dashed | BorderStyle::DashDot | BorderStyle::DashDotDot => THIN_BORDER,
+            BorderStyle::Thick => THICK_BORDER,
+            _ => MEDIUM_BORDER,
+        }
+    }
+    fn css_style(self) -> &'static str {
+        match self {
+            BorderStyle::Medium | BorderStyle::Thin | BorderStyle::Thick => "solid",
+            BorderStyle::Double => "double",
+            BorderStyle::Dotted | BorderStyle::DashDotDot | BorderStyle::MediumDashDotDot => "dotted",
+            BorderStyle::Hair | BorderStyle::Dashed | BorderStyle::MediumDashed | BorderStyle::DashDot
+                | BorderStyle::MediumDashDot | BorderStyle::SlantDashDot => "dashed",
+        }
+    }
 }
 
+// border as separate parts, alternative to the css shorthand string
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BorderInfo {
+    pub width: String,
+    pub style: String,
+    pub color: String,
+    // original style name, like "mediumDashDot"
+    pub xlsx_style: String,
+}
+
+#[derive(Clone)]
 pub struct Border {
     position: BorderPosition,
     style: Option<BorderStyle>,
@@ -28,55 +127,58 @@ impl Border {
         Border { position, color: String::from("#000000"), style: None }
     }
     pub fn set_style(&mut self, style: String) {
-        if &style == "thin" {
-            self.style = Some(BorderStyle::Thin);
-        } else if &style == "double" {
-            self.style = Some(BorderStyle::Double);
-        } else if &style == "thick" {
-            self.style = Some(BorderStyle::Thick);
-        } else if &style == "dotted" {
-            self.style = Some(BorderStyle::Dotted);
-        } else if &style == "hair" {
-            self.style = Some(BorderStyle::Hair);
-        } else if &style == "medium" {
-            self.style = Some(BorderStyle::Medium);
-        }
+        self.style = BorderStyle::from_xlsx(&style);
     }
     pub fn set_color(&mut self, color: String) {
         self.color = color;
     }
-    pub fn get_computed_style(self) -> (String, String) {
-        if self.style.is_none() {
-            return (String::from(""), String::from(""))
+    pub fn set_position(&mut self, position: BorderPosition) {
+        self.position = position;
+    }
+    pub fn is_diagonal(&self) -> bool {
+        matches!(self.position, BorderPosition::DiagonalUp | BorderPosition::DiagonalDown)
+    }
+    // key in structured output, like "left"
+    pub fn get_name(&self) -> String {
+        match self.position {
+            BorderPosition::Left => String::from("left"),
+            BorderPosition::Right => String::from("right"),
+            BorderPosition::Top => String::from("top"),
+            BorderPosition::Bottom => String::from("bottom"),
+            BorderPosition::DiagonalUp => String::from("diagonalUp"),
+            BorderPosition::DiagonalDown => String::from("diagonalDown"),
         }
-
-        let position = match self.position {
+    }
+    // style key, like "borderLeft"
+    pub fn get_key(&self) -> String {
+        match self.position {
             BorderPosition::Left => String::from("borderLeft"),
             BorderPosition::Right => String::from("borderRight"),
             BorderPosition::Top => String::from("borderTop"),
-            BorderPosition::Bottom => String::from("borderBottom")
-        };
-
-        let style = match self.style {
-            Some(BorderStyle::Dotted) => "dotted",
-            Some(BorderStyle::Double) => "double",
-            Some(BorderStyle::Hair) => "dashed",
-            Some(BorderStyle::Medium) | Some(BorderStyle::Thin) | Some(BorderStyle::Thick) => "solid",
-            None => "",
-        };
-
-        let size = match self.style {
-            Some(BorderStyle::Thin) => THIN_BORDER,
-            Some(BorderStyle::Thick) => THICK_BORDER,
-            _ => MEDIUM_BORDER,
-        };
-
-        (position, format!("{} {} {}", size, style, self.color))
+            BorderPosition::Bottom => String::from("borderBottom"),
+            BorderPosition::DiagonalUp => String::from("borderDiagonalUp"),
+            BorderPosition::DiagonalDown => String::from("borderDiagonalDown"),
+        }
+    }
+    pub fn get_computed_style(self) -> (String, String) {
+        match self.style {
+            Some(style) => (self.get_key(), format!("{} {} {}", style.css_width(), style.css_style(), self.color)),
+            None => (String::from(""), String::from("")),
+        }
+    }
+    pub fn get_info(&self) -> Option<BorderInfo> {
+        let style = self.style?;
+        Some(BorderInfo {
+            width: style.css_width().to_string(),
+            style: style.css_style().to_string(),
+            color: self.color.clone(),
+            xlsx_style: style.to_xlsx().to_string(),
+        })
     }
 }
 
 
-#[test] 
+#[test]
 fn test_border() {
     let mut b = Border::new(BorderPosition::Top);
     b.set_style(String::from("thin"));
@@ -98,4 +200,39 @@ fn test_border() {
     b = Border::new(BorderPosition::Top);
     let (_, val) = b.get_computed_style();
     assert_eq!(val, "");
-}   
\ No newline at end of file
+}
+
+#[test]
+fn test_border_styles() {
+    let cases = [
+        ("hair", "1px dashed #000000"),
+        ("double", "1px double #000000"),
+        ("dotted", "1px dotted #000000"),
+        ("dashed", "0.5px dashed #000000"),
+        ("mediumDashed", "1px dashed #000000"),
+        ("dashDot", "0.5px dashed #000000"),
+        ("mediumDashDot", "1px dashed #000000"),
+        ("dashDotDot", "0.5px dotted #000000"),
+        ("mediumDashDotDot", "1px dotted #000000"),
+        ("slantDashDot", "1px dashed #000000"),
+        ("none", ""),
+    ];
+    for (style, css) in cases.iter() {
+        let mut b = Border::new(BorderPosition::Left);
+        b.set_style(style.to_string());
+        assert_eq!(b.get_computed_style().1, *css, "{}", style);
+    }
+
+    let mut b = Border::new(BorderPosition::DiagonalUp);
+    b.set_style(String::from("mediumDashDot"));
+    b.set_color(String::from("#FF0000"));
+    assert!(b.is_diagonal());
+    assert_eq!(b.get_info().unwrap(), BorderInfo {
+        width: String::from("1px"),
+        style: String::from("dashed"),
+        color: String::from("#FF0000"),
+        xlsx_style: String::from("mediumDashDot"),
+    });
+    b.set_position(BorderPosition::DiagonalDown);
+    assert_eq!(b.get_computed_style().0, "borderDiagonalDown");
+}
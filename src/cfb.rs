@@ -0,0 +1,188 @@
+// reading streams of compound file binary, the container of .xls files
+use crate::XlsxError;
+
+pub const SIGNATURE: [u8; 8] = [0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1];
+
+const FREE_SECTOR: u32 = 0xFFFFFFFF;
+const END_OF_CHAIN: u32 = 0xFFFFFFFE;
+const HEADER_DIFAT_COUNT: usize = 109;
+const DIR_ENTRY_SIZE: usize = 128;
+const STREAM_ENTRY: u8 = 2;
+const ROOT_ENTRY: u8 = 5;
+// streams smaller than this are stored in the mini stream, the value is fixed by the format
+const MINI_CUTOFF: u64 = 4096;
+
+struct DirEntry {
+    name: String,
+    kind: u8,
+    start: u32,
+    size: u64,
+}
+
+pub struct CompoundFile {
+    data: Vec<u8>,
+    sector_size: usize,
+    mini_sector_size: usize,
+    fat: Vec<u32>,
+    mini_fat: Vec<u32>,
+    mini_stream: Vec<u8>,
+    entries: Vec<DirEntry>,
+}
+
+fn bad_file(reason: &str) -> XlsxError {
+    XlsxError::BadCompoundFile { reason: reason.to_string() }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+impl CompoundFile {
+    pub fn open(data: Vec<u8>) -> Result<CompoundFile, XlsxError> {
+        if data.len() < 512 || data[..8] != SIGNATURE {
+            return Err(bad_file("invalid signature"));
+        }
+        let sector_shift = read_u16(&data, 0x1E);
+        let mini_sector_shift = read_u16(&data, 0x20);
+        if !(7..=16).contains(&sector_shift) || mini_sector_shift > sector_shift {
+            return Err(bad_file("invalid sector size"));
+        }
+        if read_u32(&data, 0x38) as u64 != MINI_CUTOFF {
+            return Err(bad_file("invalid mini stream cutoff"));
+        }
+
+        let mut file = CompoundFile {
+            sector_size: 1 << sector_shift,
+            mini_sector_size: 1 << mini_sector_shift,
+            fat: vec!(),
+            mini_fat: vec!(),
+            mini_stream: vec!(),
+            entries: vec!(),
+            data,
+        };
+
+        // sectors of the allocation table, first 109 are listed in the header, others in DIFAT sectors
+        let fat_count = read_u32(&file.data, 0x2C) as usize;
+        let mut fat_sectors: Vec<u32> = (0..HEADER_DIFAT_COUNT)
+            .map(|i| read_u32(&file.data, 0x4C + i * 4))
+            .take_while(|s| *s != FREE_SECTOR)
+            .collect();
+        let mut difat = read_u32(&file.data, 0x44);
+        let per_sector = file.sector_size / 4 - 1;
+        let mut visited = 0;
+        while difat != END_OF_CHAIN && difat != FREE_SECTOR && fat_sectors.len() < fat_count {
+            let sector = file.sector(difat)?;
+            fat_sectors.extend((0..per_sector).map(|i| read_u32(sector, i * 4)).filter(|s| *s != FREE_SECTOR));
+            difat = read_u32(sector, per_sector * 4);
+            visited += 1;
+            if visited > file.data.len() / file.sector_size {
+                return Err(bad_file("loop in DIFAT chain"));
+            }
+        }
+        fat_sectors.truncate(fat_count);
+        let mut fat = Vec::with_capacity(fat_sectors.len() * file.sector_size / 4);
+        for s in fat_sectors {
+            let sector = file.sector(s)?;
+            fat.extend((0..file.sector_size / 4).map(|i| read_u32(sector, i * 4)));
+        }
+        file.fat = fat;
+
+        let directory = file.read_chain(read_u32(&file.data, 0x30), None)?;
+        for entry in directory.chunks_exact(DIR_ENTRY_SIZE) {
+            let name_len = (read_u16(entry, 64) as usize).min(64);
+            let name: Vec<u16> = (0..name_len.saturating_sub(2) / 2).map(|i| read_u16(entry, i * 2)).collect();
+            let size = if sector_shift == 9 {
+                // high part of the size is not used by version 3 files
+                read_u32(entry, 120) as u64
+            } else {
+                read_u32(entry, 120) as u64 | (read_u32(entry, 124) as u64) << 32
+            };
+            file.entries.push(DirEntry {
+                name: String::from_utf16_lossy(&name),
+                kind: entry[66],
+                start: read_u32(entry, 116),
+                size,
+            });
+        }
+
+        let root = match file.entries.first() {
+            Some(root) if root.kind == ROOT_ENTRY => (root.start, root.size),
+            _ => return Err(bad_file("missing root entry")),
+        };
+        file.mini_stream = file.read_chain(root.0, Some(root.1))?;
+        let mini_fat = file.read_chain(read_u32(&file.data, 0x3C), None)?;
+        file.mini_fat = mini_fat.chunks_exact(4).map(|c| read_u32(c, 0)).collect();
+        Ok(file)
+    }
+    // content of the stream, names are compared ignoring case, like in the format itself
+    pub fn stream(&self, name: &str) -> Option<Result<Vec<u8>, XlsxError>> {
+        let entry = self.entries.iter()
+            .find(|e| e.kind == STREAM_ENTRY && e.name.eq_ignore_ascii_case(name))?;
+        if entry.size < MINI_CUTOFF {
+            Some(self.read_mini_chain(entry.start, entry.size))
+        } else {
+            Some(self.read_chain(entry.start, Some(entry.size)))
+        }
+    }
+    fn sector(&self, index: u32) -> Result<&[u8], XlsxError> {
+        let start = (index as usize + 1) * self.sector_size;
+        self.data.get(start..start + self.sector_size).ok_or_else(|| bad_file("sector is out of file"))
+    }
+    fn read_chain(&self, start: u32, size: Option<u64>) -> Result<Vec<u8>, XlsxError> {
+        let mut result = vec!();
+        let mut next = start;
+        while next != END_OF_CHAIN && next != FREE_SECTOR {
+            result.extend_from_slice(self.sector(next)?);
+            next = *self.fat.get(next as usize).ok_or_else(|| bad_file("sector is out of allocation table"))?;
+            if result.len() > self.data.len() {
+                return Err(bad_file("loop in sector chain"));
+            }
+        }
+        if let Some(size) = size {
+            if (result.len() as u64) < size {
+                return Err(bad_file("stream is shorter than its size"));
+            }
+            result.truncate(size as usize);
+        }
+        Ok(result)
+    }
+    fn read_mini_chain(&self, start: u32, size: u64) -> Result<Vec<u8>, XlsxError> {
+        let mut result = vec!();
+        let mut next = start;
+        while next != END_OF_CHAIN && next != FREE_SECTOR && (result.len() as u64) < size {
+            let from = next as usize * self.mini_sector_size;
+            let sector = self.mini_stream.get(from..from + self.mini_sector_size)
+                .ok_or_else(|| bad_file("sector is out of mini stream"))?;
+            result.extend_from_slice(sector);
+            next = *self.mini_fat.get(next as usize).ok_or_else(|| bad_file("sector is out of mini allocation table"))?;
+            if result.len() > self.mini_stream.len() {
+                return Err(bad_file("loop in sector chain"));
+            }
+        }
+        if (result.len() as u64) < size {
+            return Err(bad_file("stream is shorter than its size"));
+        }
+        result.truncate(size as usize);
+        Ok(result)
+    }
+}
+
+#[test]
+fn test_mini_chain_loop() {
+    // the only mini sector points to itself
+    let file = CompoundFile {
+        data: vec!(),
+        sector_size: 512,
+        mini_sector_size: 64,
+        fat: vec!(),
+        mini_fat: vec![0],
+        mini_stream: vec![0; 64],
+        entries: vec!(),
+    };
+    assert_eq!(file.read_mini_chain(0, 32).unwrap().len(), 32);
+    assert!(matches!(file.read_mini_chain(0, 1 << 32), Err(XlsxError::BadCompoundFile { .. })));
+}
@@ -0,0 +1,233 @@
+// conditional formatting of xlsx sheets
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::reader::Reader as XmlReader;
+
+use std::io::BufRead;
+
+use crate::range::sqref_ranges;
+use crate::theme::Theme;
+use crate::{ColorScaleStop, ConditionalFormat, ConditionalFormatOperator, ConditionalFormatRule, ConditionalFormatType, ConditionalValue, ConditionalValueType, DataBar, IconSet, XlsxError};
+use crate::{attr_parse, attr_value, read_color, xml_error};
+
+// <conditionalFormatting> with its rules, rules of the x14 extension list are not read
+pub(crate) fn read_conditional_format<R: BufRead>(xml: &mut XmlReader<R>, part: &str, e: &BytesStart, theme: &Theme) -> Result<ConditionalFormat, XlsxError> {
+    let mut range = String::new();
+    for a in e.attributes() {
+        let att = a.map_err(|e| xml_error(part, xml, e))?;
+        if att.key.as_ref() == b"sqref" {
+            range = attr_value(xml, part, &att)?;
+        }
+    }
+
+    let mut rules = Vec::new();
+    // None for rules of unknown types, their children are skipped
+    let mut rule: Option<ConditionalFormatRule> = None;
+    // <cfvo> and <color> of the current color scale, data bar or icon set
+    let mut values = Vec::new();
+    let mut colors = Vec::new();
+    let mut in_formula = false;
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        match xml.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) if e.name().as_ref() == b"cfRule" => {
+                rule = read_rule(xml, part, e)?;
+                values.clear();
+                colors.clear();
+            },
+            Ok(Event::Start(ref e)) => {
+                let rule = match rule.as_mut() {
+                    Some(rule) => rule,
+                    None => continue,
+                };
+                match e.name().as_ref() {
+                    b"formula" => {
+                        in_formula = true;
+                        rule.formulas.push(String::new());
+                    },
+                    b"cfvo" => values.push(read_value(xml, part, e)?),
+                    b"color" => colors.push(read_color(xml, part, e, theme)?),
+                    b"dataBar" => rule.data_bar = Some(read_data_bar(xml, part, e)?),
+                    b"iconSet" => rule.icon_set = Some(read_icon_set(xml, part, e)?),
+                    _ => (),
+                }
+            },
+            Ok(Event::Text(t)) if in_formula => {
+                let text = t.unescape().map_err(|e| xml_error(part, xml, e))?;
+                if let Some(formula) = rule.as_mut().and_then(|r| r.formulas.last_mut()) {
+                    formula.push_str(&text);
+                }
+            },
+            Ok(Event::End(ref e)) => match e.name().as_ref() {
+                b"formula" => in_formula = false,
+                b"colorScale" => if let Some(rule) = rule.as_mut() {
+                    let stops = values.drain(..).zip(colors.drain(..)).map(|(value, color)| ColorScaleStop { value, color });
+                    rule.color_scale = Some(stops.collect());
+                },
+                b"dataBar" => if let Some(bar) = rule.as_mut().and_then(|r| r.data_bar.as_mut()) {
+                    let mut thresholds = values.drain(..);
+                    if let Some(min) = thresholds.next() {
+                        bar.min = min;
+                    }
+                    if let Some(max) = thresholds.next() {
+                        bar.max = max;
+                    }
+                    bar.color = colors.drain(..).next().flatten();
+                },
+                b"iconSet" => if let Some(icons) = rule.as_mut().and_then(|r| r.icon_set.as_mut()) {
+                    icons.values = std::mem::take(&mut values);
+                },
+                b"cfRule" => rules.extend(rule.take()),
+                b"conditionalFormatting" => break,
+                _ => (),
+            },
+            Ok(Event::Eof) => return Err(xml_error(part, xml, "unexpected end of document")),
+            Err(e) => return Err(xml_error(part, xml, e)),
+            _ => (),
+        }
+    }
+
+    Ok(ConditionalFormat { ranges: sqref_ranges(&range), range, rules })
+}
+
+fn read_rule<R: BufRead>(xml: &XmlReader<R>, part: &str, e: &BytesStart) -> Result<Option<ConditionalFormatRule>, XlsxError> {
+    let kind = match e.try_get_attribute("type").map_err(|e| xml_error(part, xml, e))? {
+        Some(att) => ConditionalFormatType::from_xlsx(&attr_value(xml, part, &att)?),
+        None => None,
+    };
+    let mut rule = match kind {
+        Some(kind) => ConditionalFormatRule::new(kind),
+        None => return Ok(None),
+    };
+    for a in e.attributes() {
+        let att = a.map_err(|e| xml_error(part, xml, e))?;
+        let value = attr_value(xml, part, &att)?;
+        let flag = value == "1" || value == "true";
+        match att.key.as_ref() {
+            b"priority" => rule.priority = attr_parse(xml, part, &att)?,
+            b"dxfId" => rule.dxf_id = Some(attr_parse(xml, part, &att)?),
+            b"stopIfTrue" => rule.stop_if_true = flag,
+            b"operator" => rule.operator = ConditionalFormatOperator::from_xlsx(&value),
+            b"text" => rule.text = Some(value),
+            b"timePeriod" => rule.time_period = Some(value),
+            b"rank" => rule.rank = Some(attr_parse(xml, part, &att)?),
+            b"percent" => rule.percent = flag,
+            b"bottom" => rule.bottom = flag,
+            b"aboveAverage" => rule.above_average = flag,
+            b"equalAverage" => rule.equal_average = flag,
+            b"stdDev" => rule.std_dev = Some(attr_parse(xml, part, &att)?),
+            _ => (),
+        }
+    }
+    Ok(Some(rule))
+}
+
+fn read_value<R: BufRead>(xml: &XmlReader<R>, part: &str, e: &BytesStart) -> Result<ConditionalValue, XlsxError> {
+    let mut value = ConditionalValue::new(ConditionalValueType::Num);
+    for a in e.attributes() {
+        let att = a.map_err(|e| xml_error(part, xml, e))?;
+        match att.key.as_ref() {
+            b"type" => value.kind = ConditionalValueType::from_xlsx(&attr_value(xml, part, &att)?),
+            b"val" => value.value = Some(attr_value(xml, part, &att)?),
+            b"gte" => value.gte = matches!(attr_value(xml, part, &att)?.as_str(), "1" | "true"),
+            _ => (),
+        }
+    }
+    Ok(value)
+}
+
+// thresholds and color are set by the child elements
+fn read_data_bar<R: BufRead>(xml: &XmlReader<R>, part: &str, e: &BytesStart) -> Result<DataBar, XlsxError> {
+    let mut bar = DataBar {
+        min: ConditionalValue::new(ConditionalValueType::Min),
+        max: ConditionalValue::new(ConditionalValueType::Max),
+        color: None,
+        show_value: true,
+        min_length: 10,
+        max_length: 90,
+    };
+    for a in e.attributes() {
+        let att = a.map_err(|e| xml_error(part, xml, e))?;
+        match att.key.as_ref() {
+            b"minLength" => bar.min_length = attr_parse(xml, part, &att)?,
+            b"maxLength" => bar.max_length = attr_parse(xml, part, &att)?,
+            b"showValue" => bar.show_value = matches!(attr_value(xml, part, &att)?.as_str(), "1" | "true"),
+            _ => (),
+        }
+    }
+    Ok(bar)
+}
+
+fn read_icon_set<R: BufRead>(xml: &XmlReader<R>, part: &str, e: &BytesStart) -> Result<IconSet, XlsxError> {
+    let mut icons = IconSet {
+        name: String::from("3TrafficLights1"),
+        values: vec![],
+        show_value: true,
+        reverse: false,
+        percent: true,
+    };
+    for a in e.attributes() {
+        let att = a.map_err(|e| xml_error(part, xml, e))?;
+        let value = attr_value(xml, part, &att)?;
+        let flag = value == "1" || value == "true";
+        match att.key.as_ref() {
+            b"iconSet" => icons.name = value,
+            b"showValue" => icons.show_value = flag,
+            b"reverse" => icons.reverse = flag,
+            b"percent" => icons.percent = flag,
+            _ => (),
+        }
+    }
+    Ok(icons)
+}
+
+#[test]
+fn test_read_conditional_format() {
+    let read = |content: &str| {
+        let mut xml = crate::xml_reader_from(std::io::Cursor::new(content.as_bytes()));
+        let mut buf = Vec::new();
+        loop {
+            match xml.read_event_into(&mut buf) {
+                Ok(Event::Start(ref e)) if e.name().as_ref() == b"conditionalFormatting" => {
+                    let e = e.to_owned();
+                    return read_conditional_format(&mut xml, "sheet1.xml", &e, &Theme::default()).unwrap();
+                },
+                Ok(Event::Eof) => panic!("no conditional formatting"),
+                _ => (),
+            }
+        }
+    };
+
+    let format = read(r#"<conditionalFormatting sqref="A1:A10 C1"><cfRule type="cellIs" dxfId="0" priority="2" operator="between" stopIfTrue="1"><formula>1</formula><formula>$B$1&lt;5</formula></cfRule><cfRule type="unknownRule" priority="3"><formula>1</formula></cfRule><cfRule type="containsText" dxfId="1" priority="1" operator="containsText" text="ok"><formula>NOT(ISERROR(SEARCH("ok",A1)))</formula></cfRule></conditionalFormatting>"#);
+    assert_eq!(format.range, "A1:A10 C1");
+    assert_eq!(format.ranges.len(), 2);
+    assert_eq!(format.rules.len(), 2);
+    let rule = &format.rules[0];
+    assert_eq!((rule.kind, rule.priority, rule.dxf_id), (ConditionalFormatType::CellIs, 2, Some(0)));
+    assert_eq!(rule.operator, Some(ConditionalFormatOperator::Between));
+    assert!(rule.stop_if_true);
+    assert_eq!(rule.formulas, vec!["1", "$B$1<5"]);
+    assert_eq!(format.rules[1].text.as_deref(), Some("ok"));
+
+    let format = read(r#"<conditionalFormatting sqref="B1:B5"><cfRule type="colorScale" priority="1"><colorScale><cfvo type="min"/><cfvo type="percentile" val="50"/><cfvo type="max"/><color rgb="FFF8696B"/><color auto="1"/><color rgb="FF63BE7B"/></colorScale></cfRule></conditionalFormatting>"#);
+    let stops = format.rules[0].color_scale.as_ref().unwrap();
+    assert_eq!(stops.len(), 3);
+    assert_eq!(stops[1].value.kind, ConditionalValueType::Percentile);
+    assert_eq!(stops[1].value.value.as_deref(), Some("50"));
+    assert_eq!(stops[1].color, None);
+    assert_eq!(stops[2].color.as_deref(), Some("rgba(99,190,123,1)"));
+
+    let format = read(r#"<conditionalFormatting sqref="C1:C5"><cfRule type="dataBar" priority="1"><dataBar showValue="0" minLength="0"><cfvo type="num" val="0"/><cfvo type="max"/><color rgb="FF638EC6"/></dataBar><extLst><ext><x14:id>{1}</x14:id></ext></extLst></cfRule><cfRule type="iconSet" priority="2"><iconSet iconSet="3Arrows" reverse="1"><cfvo type="percent" val="0"/><cfvo type="percent" val="33"/><cfvo type="percent" val="67" gte="0"/></iconSet></cfRule><cfRule type="top10" priority="3" rank="5" bottom="1" percent="1" dxfId="2"/><cfRule type="aboveAverage" priority="4" aboveAverage="0" stdDev="1" dxfId="2"/></conditionalFormatting>"#);
+    let bar = format.rules[0].data_bar.as_ref().unwrap();
+    assert_eq!((bar.min.kind, bar.min.value.as_deref(), bar.max.kind), (ConditionalValueType::Num, Some("0"), ConditionalValueType::Max));
+    assert_eq!(bar.color.as_deref(), Some("rgba(99,142,198,1)"));
+    assert_eq!((bar.show_value, bar.min_length, bar.max_length), (false, 0, 90));
+    let icons = format.rules[1].icon_set.as_ref().unwrap();
+    assert_eq!((icons.name.as_str(), icons.reverse, icons.show_value), ("3Arrows", true, true));
+    assert_eq!(icons.values.len(), 3);
+    assert!(icons.values[1].gte && !icons.values[2].gte);
+    let top = &format.rules[2];
+    assert_eq!((top.rank, top.bottom, top.percent), (Some(5), true, true));
+    let average = &format.rules[3];
+    assert_eq!((average.above_average, average.std_dev), (false, Some(1)));
+}
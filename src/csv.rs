@@ -0,0 +1,339 @@
+// parsing csv and tsv, text file with a single sheet
+use serde_json::Value as JsonValue;
+
+use crate::date;
+use crate::sheet::{number_text, set_type, CellOptions, ParsedSheet};
+use crate::theme::Theme;
+use crate::{Cell, CellType, SparseCell, SheetData, SheetDescriptor, Style, XlsxError};
+
+const MAX_COLS: usize = 16384;
+const MAX_ROWS: u32 = 1_048_576;
+// lines used to detect the delimiter and the quote
+const SAMPLE_LINES: usize = 20;
+const DELIMITERS: [char; 4] = [',', ';', '\t', '|'];
+
+// number formats of inferred values, index is the style of the cell
+const FORMATS: [&str; 3] = ["General", "yyyy-mm-dd", "yyyy-mm-dd hh:mm:ss"];
+const DATE_STYLE: u32 = 1;
+const DATE_TIME_STYLE: u32 = 2;
+
+// characters 0x80 - 0x9F of windows-1252, other bytes are the same as in latin-1
+const WINDOWS_1252: [char; 32] = [
+    '\u{20AC}', '\u{81}', '\u{201A}', '\u{192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{2C6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8D}', '\u{17D}', '\u{8F}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{2DC}', '\u{2122}', '\u{161}', '\u{203A}', '\u{153}', '\u{9D}', '\u{17E}', '\u{178}',
+];
+
+// options of `Workbook::from_csv`, delimiter and quote are detected when not set
+#[derive(Default, Clone)]
+pub struct CsvOptions {
+    pub delimiter: Option<char>,
+    pub quote: Option<char>,
+    // numbers and ISO dates are exported as numbers instead of text
+    pub infer_types: bool,
+    // "Sheet1" by default
+    pub sheet_name: Option<String>,
+}
+
+// text of the file, utf-8 and utf-16 are detected by BOM or content, windows-1252 otherwise
+pub(crate) fn decode(data: &[u8]) -> String {
+    if let Some(data) = data.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+        return String::from_utf8_lossy(data).into_owned();
+    }
+    if let Some(data) = data.strip_prefix(&[0xFF, 0xFE]) {
+        return decode_utf16(data, u16::from_le_bytes);
+    }
+    if let Some(data) = data.strip_prefix(&[0xFE, 0xFF]) {
+        return decode_utf16(data, u16::from_be_bytes);
+    }
+    // utf-16 without BOM, ascii text has zero in every second byte
+    let sample = &data[..data.len().min(1000) & !1];
+    let zeros = |offset: usize| sample.iter().skip(offset).step_by(2).filter(|b| **b == 0).count();
+    if !sample.is_empty() && zeros(1) * 2 > sample.len() / 2 && zeros(0) == 0 {
+        return decode_utf16(data, u16::from_le_bytes);
+    }
+    if !sample.is_empty() && zeros(0) * 2 > sample.len() / 2 && zeros(1) == 0 {
+        return decode_utf16(data, u16::from_be_bytes);
+    }
+    match std::str::from_utf8(data) {
+        Ok(text) => text.to_string(),
+        Err(_) => data.iter().map(|b| match b {
+            0x80..=0x9F => WINDOWS_1252[(b - 0x80) as usize],
+            _ => *b as char,
+        }).collect(),
+    }
+}
+
+fn decode_utf16(data: &[u8], to_u16: fn([u8; 2]) -> u16) -> String {
+    let units = data.chunks_exact(2).map(|pair| to_u16([pair[0], pair[1]]));
+    char::decode_utf16(units).map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER)).collect()
+}
+
+// delimiter which splits sample lines into the same number of fields
+fn detect_delimiter(text: &str, quote: char) -> char {
+    let lines: Vec<&str> = text.lines().filter(|line| !line.trim().is_empty()).take(SAMPLE_LINES).collect();
+    let mut best = (',', 0, 0);
+    for delimiter in DELIMITERS {
+        let counts: Vec<usize> = lines.iter().map(|line| {
+            let mut quoted = false;
+            line.chars().filter(|c| {
+                if *c == quote {
+                    quoted = !quoted;
+                }
+                !quoted && *c == delimiter
+            }).count()
+        }).collect();
+        let first = counts.first().copied().unwrap_or(0);
+        if first == 0 {
+            continue;
+        }
+        // lines with the same count as the first (header) line
+        let same = counts.iter().filter(|c| **c == first).count();
+        if (same, first) > (best.1, best.2) {
+            best = (delimiter, same, first);
+        }
+    }
+    best.0
+}
+
+// single quotes are used only when fields start with them and double quotes are not used at all
+fn detect_quote(text: &str) -> char {
+    let sample: String = text.lines().take(SAMPLE_LINES).collect::<Vec<&str>>().join("\n");
+    if sample.contains('"') {
+        return '"';
+    }
+    let quoted = sample.split(|c| c == '\n' || DELIMITERS.contains(&c))
+        .filter(|field| field.len() > 1 && field.starts_with('\'') && field.ends_with('\''))
+        .count();
+    if quoted > 0 { '\'' } else { '"' }
+}
+
+// splits text into records, quoted fields can contain delimiters, quotes (doubled) and line breaks
+fn parse_records(text: &str, delimiter: char, quote: char) -> Vec<Vec<String>> {
+    let mut records = vec!();
+    let mut record = vec!();
+    let mut field = String::new();
+    let mut quoted = false;
+    // field had quotes, so it is not empty even without content
+    let mut started = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if quoted {
+            if c == quote {
+                if chars.peek() == Some(&quote) {
+                    field.push(quote);
+                    chars.next();
+                } else {
+                    quoted = false;
+                }
+            } else {
+                field.push(c);
+            }
+            continue;
+        }
+        match c {
+            _ if c == quote && field.is_empty() && !started => {
+                quoted = true;
+                started = true;
+            },
+            _ if c == delimiter => {
+                record.push(std::mem::take(&mut field));
+                started = false;
+            },
+            '\r' | '\n' => {
+                if c == '\r' && chars.peek() == Some(&'\n') {
+                    chars.next();
+                }
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+                started = false;
+            },
+            _ => field.push(c),
+        }
+    }
+    if started || !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+// "-12.5", "1e3"; numbers with leading zeros, like zip codes, are kept as text
+fn infer_number(text: &str) -> Option<f64> {
+    let digits = text.strip_prefix('-').unwrap_or(text);
+    if digits.is_empty() || !digits.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    if digits.len() > 1 && digits.starts_with('0') && !digits.starts_with("0.") {
+        return None;
+    }
+    if !digits.chars().all(|c| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '+' | '-')) {
+        return None;
+    }
+    text.parse::<f64>().ok().filter(|n| n.is_finite())
+}
+
+// "2024-01-31" or "2024-01-31 12:30:00", style of the cell and serial date
+fn infer_date(text: &str) -> Option<(u32, f64)> {
+    let bytes = text.as_bytes();
+    let is_date = bytes.len() >= 10 && bytes[4] == b'-' && bytes[7] == b'-'
+        && bytes[..10].iter().enumerate().all(|(i, b)| i == 4 || i == 7 || b.is_ascii_digit());
+    if !is_date {
+        return None;
+    }
+    if bytes.len() == 10 {
+        return date::iso_to_serial(text).map(|serial| (DATE_STYLE, serial));
+    }
+    if (bytes[10] == b' ' || bytes[10] == b'T') && bytes.len() >= 16 {
+        let time = &text[11..];
+        if !time.chars().all(|c| c.is_ascii_digit() || c == ':' || c == '.') {
+            return None;
+        }
+        return date::iso_to_serial(&format!("{}T{}", &text[..10], time)).map(|serial| (DATE_TIME_STYLE, serial));
+    }
+    None
+}
+
+// csv file, parsed when the sheet is requested
+pub(crate) struct Csv {
+    text: String,
+    name: String,
+    delimiter: char,
+    quote: char,
+    infer_types: bool,
+}
+
+impl Csv {
+    pub fn from_vec(data: Vec<u8>, options: CsvOptions) -> Csv {
+        let text = decode(&data);
+        let quote = options.quote.unwrap_or_else(|| detect_quote(&text));
+        let delimiter = options.delimiter.unwrap_or_else(|| detect_delimiter(&text, quote));
+        Csv {
+            text,
+            name: options.sheet_name.unwrap_or_else(|| String::from("Sheet1")),
+            delimiter,
+            quote,
+            infer_types: options.infer_types,
+        }
+    }
+    pub fn sheet_names(&self) -> Vec<String> {
+        vec!(self.name.clone())
+    }
+    pub fn sheets(&self) -> Vec<SheetDescriptor> {
+        vec!(SheetDescriptor::new(self.name.clone(), 1))
+    }
+    pub fn styles_with(&self, _flags: u32) -> Vec<Style> {
+        FORMATS.iter().map(|format| {
+            let mut style = Style::new();
+            style.insert(String::from("format"), JsonValue::String(format.to_string()));
+            style
+        }).collect()
+    }
+    pub fn sheet_with(&self, sheet_name: &str, flags: u32) -> Result<SheetData, XlsxError> {
+        Ok(self.read_sheet(sheet_name, flags)?.into_sheet(flags))
+    }
+    pub fn read_sheet(&self, sheet_name: &str, flags: u32) -> Result<ParsedSheet, XlsxError> {
+        if sheet_name != self.name {
+            return Err(XlsxError::UnknownSheet { name: sheet_name.to_string() });
+        }
+        let cell_formats: Vec<String> = FORMATS.iter().map(|f| f.to_string()).collect();
+        let options = CellOptions::new(flags, false, &cell_formats, &Theme::default());
+        let mut sheet = ParsedSheet::new(self.name.clone());
+
+        for (r, record) in parse_records(&self.text, self.delimiter, self.quote).into_iter().enumerate().take(MAX_ROWS as usize) {
+            sheet.cols_count = sheet.cols_count.max(record.len().min(MAX_COLS) as u32);
+            for (c, text) in record.into_iter().enumerate().take(MAX_COLS) {
+                if text.is_empty() {
+                    continue;
+                }
+                let mut cell = Cell::new();
+                let inferred = if self.infer_types {
+                    infer_date(&text).or_else(|| infer_number(&text).map(|n| (0, n)))
+                } else {
+                    None
+                };
+                let cell_type = match inferred {
+                    Some((style, value)) => {
+                        cell.s = style;
+                        options.set_value(&mut cell, number_text(value), CellType::Number, &cell_formats)
+                    },
+                    None => {
+                        options.set_text(&mut cell, text, None, &cell_formats);
+                        CellType::String
+                    },
+                };
+                set_type(&mut cell, cell_type);
+                sheet.cells.push(SparseCell { r: r as u32, c: c as u32, cell });
+            }
+        }
+        Ok(sheet)
+    }
+}
+
+#[test]
+fn test_decode() {
+    assert_eq!(decode(b"\xEF\xBB\xBFa,b"), "a,b");
+    assert_eq!(decode(b"\xFF\xFEa\0,\0\xE9\0"), "a,\u{E9}");
+    assert_eq!(decode(b"\0a\0,\0b"), "a,b");
+    assert_eq!(decode(b"caf\xE9 \x80"), "caf\u{E9} \u{20AC}");
+    assert_eq!(decode("café".as_bytes()), "café");
+}
+
+#[test]
+fn test_parse_records() {
+    let text = "name;note\r\n\"Smith; J\";\"said \"\"hi\"\"\nand left\"\n;\n";
+    assert_eq!(detect_delimiter(text, '"'), ';');
+    let records = parse_records(text, ';', '"');
+    assert_eq!(records, vec![
+        vec!["name", "note"],
+        vec!["Smith; J", "said \"hi\"\nand left"],
+        vec!["", ""],
+    ]);
+    assert_eq!(detect_delimiter("a\tb\tc\n1\t2,5\t3", '"'), '\t');
+    assert_eq!(detect_quote("'a,b',c\n'd',e"), '\'');
+    assert_eq!(parse_records("a,b", ',', '"'), vec![vec!["a", "b"]]);
+}
+
+#[test]
+fn test_infer_types() {
+    assert_eq!(infer_number("-12.5"), Some(-12.5));
+    assert_eq!(infer_number("1e3"), Some(1000.0));
+    assert_eq!(infer_number("0.5"), Some(0.5));
+    assert_eq!(infer_number("007"), None);
+    assert_eq!(infer_number("inf"), None);
+    assert_eq!(infer_number("12 pcs"), None);
+    assert_eq!(infer_date("2024-01-31"), Some((DATE_STYLE, 45322.0)));
+    assert_eq!(infer_date("2024-01-31 12:00"), Some((DATE_TIME_STYLE, 45322.5)));
+    assert_eq!(infer_date("2024-01-31x"), None);
+}
+
+#[test]
+fn test_csv_sheet() {
+    use crate::{CellValue, Workbook, WITH_TYPED_VALUES};
+
+    let data = b"\xEF\xBB\xBFname;amount;date\r\n\"Smith; J\";12.5;2024-01-31\nZip;007;\n".to_vec();
+    let mut csv = Workbook::from_csv(data, CsvOptions { infer_types: true, sheet_name: Some(String::from("orders")), ..CsvOptions::default() });
+    assert_eq!(csv.sheet_names(), vec![String::from("orders")]);
+
+    let data = csv.sheet_with("orders", WITH_TYPED_VALUES).unwrap();
+    let value = |row: usize, col: usize| data.cells[row][col].as_ref().and_then(|c| c.v.clone());
+    assert_eq!(data.cells.len(), 3);
+    assert_eq!(data.cols.len(), 3);
+    assert_eq!(value(1, 0), Some(CellValue::String(String::from("Smith; J"))));
+    assert_eq!(value(1, 1), Some(CellValue::Number(12.5)));
+    assert_eq!(value(1, 2), Some(CellValue::Number(45322.0)));
+    assert_eq!(data.cells[1][2].as_ref().unwrap().s, 1);
+    assert_eq!(value(2, 1), Some(CellValue::String(String::from("007"))));
+    assert!(data.cells[2][2].is_none());
+
+    let styles = csv.styles().unwrap();
+    assert_eq!(styles[1]["format"], "yyyy-mm-dd");
+    assert!(csv.sheet("Sheet1").is_err());
+
+    let mut tsv = Workbook::from_csv(b"a\t1\nb\t2".to_vec(), CsvOptions::default());
+    let data = tsv.sheet(&tsv.sheet_names()[0]).unwrap();
+    assert_eq!(data.name, "Sheet1");
+    assert_eq!(data.cells[1][1].as_ref().unwrap().v, Some(CellValue::String(String::from("2"))));
+}
@@ -0,0 +1,320 @@
+// days from 1970-01-01 to the start of excel epochs
+const EPOCH_1900: i64 = -25569; // 1899-12-30
+const EPOCH_1904: i64 = -24107; // 1904-01-01
+const MS_IN_DAY: i64 = 86_400_000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DateKind {
+    Date,
+    Time,
+}
+
+// detects date and time number formats, like "mm-dd-yy" or "[h]:mm:ss"
+// only the first section of format is checked, as it is the one used for positive numbers
+pub fn date_kind(code: &str) -> Option<DateKind> {
+    // groups of date letters in order of appearance, like ['m', 'd', 'y']
+    let mut groups: Vec<char> = vec!();
+    let mut has_am_pm = false;
+
+    let mut chars = code.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            ';' => break,
+            '"' => {
+                for q in chars.by_ref() {
+                    if q == '"' {
+                        break;
+                    }
+                }
+            },
+            '\\' | '_' | '*' => {
+                chars.next();
+            },
+            '[' => {
+                let mut content = String::new();
+                for q in chars.by_ref() {
+                    if q == ']' {
+                        break;
+                    }
+                    content.push(q.to_ascii_lowercase());
+                }
+                // elapsed time, other brackets are colors, conditions or locales
+                if !content.is_empty() && content.chars().all(|x| x == 'h' || x == 'm' || x == 's') {
+                    // elapsed minutes are marked as 'n' to not confuse them with months
+                    groups.push(match content.chars().next() {
+                        Some('m') => 'n',
+                        Some(x) => x,
+                        None => 'h',
+                    });
+                }
+            },
+            'a' | 'A' => {
+                let rest: String = chars.clone().take(4).collect::<String>().to_ascii_lowercase();
+                if rest.starts_with("m/pm") {
+                    has_am_pm = true;
+                    chars.nth(3);
+                } else if rest.starts_with("/p") {
+                    has_am_pm = true;
+                    chars.nth(1);
+                }
+            },
+            _ => {
+                let lower = c.to_ascii_lowercase();
+                if matches!(lower, 'y' | 'd' | 'm' | 'h' | 's') {
+                    while chars.peek().map(|x| x.to_ascii_lowercase()) == Some(lower) {
+                        chars.next();
+                    }
+                    groups.push(lower);
+                }
+            },
+        }
+    }
+
+    let mut has_date = false;
+    let mut has_time = has_am_pm;
+    for (i, g) in groups.iter().enumerate() {
+        match g {
+            'y' | 'd' => has_date = true,
+            'h' | 's' | 'n' => has_time = true,
+            // "m" is minutes after hours or before seconds, months otherwise
+            'm' => {
+                let after_hours = i > 0 && groups[i - 1] == 'h';
+                let before_seconds = groups.get(i + 1) == Some(&'s');
+                if after_hours || before_seconds {
+                    has_time = true;
+                } else {
+                    has_date = true;
+                }
+            },
+            _ => (),
+        }
+    }
+
+    if has_date {
+        Some(DateKind::Date)
+    } else if has_time {
+        Some(DateKind::Time)
+    } else {
+        None
+    }
+}
+
+pub struct DateParts {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    // 0 - Sunday
+    pub weekday: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    pub ms: u32,
+    // whole days and milliseconds since the epoch, for elapsed time formats
+    pub serial_days: i64,
+    pub day_ms: i64,
+}
+
+// splits excel serial date to parts, time is rounded to `digits` digits of fractional seconds (0 - 3)
+pub fn serial_to_parts(serial: f64, date1904: bool, digits: u32) -> Option<DateParts> {
+    // 2958465 is 9999-12-31, the last date supported by excel
+    if !(0.0..2_958_466.0).contains(&serial) {
+        return None;
+    }
+
+    let unit = 10i64.pow(3 - digits.min(3));
+    let total = (serial * (MS_IN_DAY / unit) as f64).round() as i64 * unit;
+    let serial_days = total.div_euclid(MS_IN_DAY);
+    let day_ms = total.rem_euclid(MS_IN_DAY);
+
+    let days = if date1904 {
+        EPOCH_1904 + serial_days
+    } else if serial_days < 60 {
+        // excel counts non-existent 1900-02-29 as day 60, so all earlier days are shifted
+        EPOCH_1900 + serial_days + 1
+    } else {
+        EPOCH_1900 + serial_days
+    };
+    let (year, month, day) = civil_from_days(days);
+    let seconds = day_ms / 1000;
+
+    Some(DateParts {
+        year,
+        month,
+        day,
+        // 1970-01-01 is Thursday
+        weekday: (days + 4).rem_euclid(7) as u32,
+        hour: (seconds / 3600) as u32,
+        minute: (seconds / 60 % 60) as u32,
+        second: (seconds % 60) as u32,
+        ms: (day_ms % 1000) as u32,
+        serial_days,
+        day_ms,
+    })
+}
+
+// converts excel serial date to ISO-8601 string
+// "2023-07-14" for whole days, "2023-07-14T12:00:00" with time part, "12:00:00" for time formats below one day
+pub fn serial_to_iso(serial: f64, kind: DateKind, date1904: bool) -> Option<String> {
+    let parts = serial_to_parts(serial, date1904, 3)?;
+
+    let time = format_time(parts.day_ms);
+    if kind == DateKind::Time && parts.serial_days == 0 {
+        return Some(time);
+    }
+
+    let (y, m, d) = (parts.year, parts.month, parts.day);
+    if parts.day_ms == 0 && kind == DateKind::Date {
+        Some(format!("{:04}-{:02}-{:02}", y, m, d))
+    } else {
+        Some(format!("{:04}-{:02}-{:02}T{}", y, m, d, time))
+    }
+}
+
+fn format_time(ms: i64) -> String {
+    let seconds = ms / 1000;
+    let fraction = ms % 1000;
+    let time = format!("{:02}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if fraction == 0 {
+        time
+    } else {
+        format!("{}.{:03}", time, fraction)
+    }
+}
+
+// ISO-8601 date or date-time, like "2023-07-16T12:00:00", to excel serial date of 1900 system
+// time without date, like "12:00:00", is a part of day, as `serial_to_iso` exports time formats
+pub fn iso_to_serial(value: &str) -> Option<f64> {
+    if !value.contains('-') && value.contains(':') {
+        return time_to_fraction(value);
+    }
+    let (date, time) = match value.find('T') {
+        Some(i) => (&value[..i], Some(&value[i + 1..])),
+        None => (value, None),
+    };
+    let mut parts = date.splitn(3, '-');
+    let year = parts.next()?.parse::<i64>().ok()?;
+    let month = parts.next()?.parse::<u32>().ok()?;
+    let day = parts.next()?.parse::<u32>().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let days = days_from_civil(year, month, day) - EPOCH_1900;
+    // days before non-existent 1900-02-29 are shifted, see `serial_to_parts`
+    let serial = if days <= 60 { days - 1 } else { days };
+
+    let fraction = match time {
+        Some(time) => time_to_fraction(time)?,
+        None => 0.0,
+    };
+    Some(serial as f64 + fraction)
+}
+
+// "12:30:15" to part of day
+fn time_to_fraction(time: &str) -> Option<f64> {
+    let mut parts = time.trim_end_matches('Z').splitn(3, ':');
+    let hours = parts.next()?.parse::<f64>().ok()?;
+    let minutes = parts.next().unwrap_or("0").parse::<f64>().ok()?;
+    let seconds = parts.next().unwrap_or("0").parse::<f64>().ok()?;
+    Some((hours * 3600.0 + minutes * 60.0 + seconds) / 86400.0)
+}
+
+// ISO-8601 duration, like "PT12H30M15S", to part of day
+pub fn duration_to_serial(value: &str) -> Option<f64> {
+    let value = value.strip_prefix('P')?;
+    let (days, time) = match value.find('T') {
+        Some(i) => (&value[..i], &value[i + 1..]),
+        None => (value, ""),
+    };
+    let mut seconds = match days.strip_suffix('D') {
+        Some(days) => days.parse::<f64>().ok()? * 86400.0,
+        None if days.is_empty() => 0.0,
+        None => return None,
+    };
+    let mut number = String::new();
+    for c in time.chars() {
+        let scale = match c {
+            'H' => 3600.0,
+            'M' => 60.0,
+            'S' => 1.0,
+            _ => {
+                number.push(c);
+                continue;
+            },
+        };
+        seconds += number.parse::<f64>().ok()? * scale;
+        number.clear();
+    }
+    Some(seconds / 86400.0)
+}
+
+// (year, month, day) to days since 1970-01-01, reverse of `civil_from_days`
+fn days_from_civil(y: i64, m: u32, d: u32) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let mp = if m > 2 { m - 3 } else { m + 9 } as i64;
+    let doy = (153 * mp + 2) / 5 + d as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+// days since 1970-01-01 to (year, month, day), see http://howardhinnant.github.io/date_algorithms.html
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    (y, m, d)
+}
+
+
+#[test]
+fn test_date_kind() {
+    assert_eq!(date_kind("mm-dd-yy"), Some(DateKind::Date));
+    assert_eq!(date_kind("m/d/yy h:mm"), Some(DateKind::Date));
+    assert_eq!(date_kind("h:mm AM/PM"), Some(DateKind::Time));
+    assert_eq!(date_kind("mm:ss"), Some(DateKind::Time));
+    assert_eq!(date_kind("h AM/PM"), Some(DateKind::Time));
+    assert_eq!(date_kind("h A/P"), Some(DateKind::Time));
+    assert_eq!(date_kind("[h]:mm:ss"), Some(DateKind::Time));
+    assert_eq!(date_kind("[h]:mm"), Some(DateKind::Time));
+    assert_eq!(date_kind("[mm]"), Some(DateKind::Time));
+    assert_eq!(date_kind("[$-409]dddd, mmmm d, yyyy"), Some(DateKind::Date));
+    assert_eq!(date_kind("yyyy\\-mm\\-dd"), Some(DateKind::Date));
+    assert_eq!(date_kind("General"), None);
+    assert_eq!(date_kind("0.00%"), None);
+    assert_eq!(date_kind("#,##0 ;[Red](#,##0)"), None);
+    assert_eq!(date_kind("\"days\" 0"), None);
+    assert_eq!(date_kind("@"), None);
+}
+
+#[test]
+fn test_serial_to_iso() {
+    assert_eq!(serial_to_iso(45123.0, DateKind::Date, false).unwrap(), "2023-07-16");
+    assert_eq!(serial_to_iso(45123.5, DateKind::Date, false).unwrap(), "2023-07-16T12:00:00");
+    assert_eq!(serial_to_iso(0.75, DateKind::Time, false).unwrap(), "18:00:00");
+    assert_eq!(serial_to_iso(1.0, DateKind::Date, false).unwrap(), "1900-01-01");
+    assert_eq!(serial_to_iso(59.0, DateKind::Date, false).unwrap(), "1900-02-28");
+    assert_eq!(serial_to_iso(61.0, DateKind::Date, false).unwrap(), "1900-03-01");
+    assert_eq!(serial_to_iso(0.0, DateKind::Date, true).unwrap(), "1904-01-01");
+    assert_eq!(serial_to_iso(43661.0, DateKind::Date, true).unwrap(), "2023-07-16");
+    assert_eq!(serial_to_iso(-1.0, DateKind::Date, false), None);
+}
+
+#[test]
+fn test_iso_to_serial() {
+    assert_eq!(iso_to_serial("2023-07-16"), Some(45123.0));
+    assert_eq!(iso_to_serial("2023-07-16T12:00:00"), Some(45123.5));
+    assert_eq!(iso_to_serial("1900-01-01"), Some(1.0));
+    assert_eq!(iso_to_serial("1900-03-01"), Some(61.0));
+    assert_eq!(iso_to_serial("16.07.2023"), None);
+    assert_eq!(iso_to_serial("18:00:00"), Some(0.75));
+    assert_eq!(duration_to_serial("PT18H00M00S"), Some(0.75));
+    assert_eq!(duration_to_serial("P1DT06H"), Some(1.25));
+    assert_eq!(duration_to_serial("12:00"), None);
+}
@@ -0,0 +1,73 @@
+use serde::Serialize;
+use std::fmt;
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum XlsxError {
+    // input is not a zip archive, or the archive is damaged
+    BadZip { reason: String },
+    // required file is absent from the archive
+    MissingPart { part: String },
+    // xml parser failed, offset is a byte position inside of the part
+    MalformedXml { part: String, offset: usize, reason: String },
+    UnknownSheet { name: String },
+    InvalidAttribute { part: String, attribute: String, value: String },
+    // input looks like .xls, but its compound file container is damaged
+    BadCompoundFile { reason: String },
+    // binary record is broken, offset is a byte position inside of the stream or part
+    MalformedRecord { part: String, offset: usize, reason: String },
+    // input of `write_xlsx` can't be converted
+    InvalidData { reason: String },
+}
+
+impl fmt::Display for XlsxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            XlsxError::BadZip { reason } => write!(f, "not a valid xlsx file: {}", reason),
+            XlsxError::MissingPart { part } => write!(f, "missing part {}", part),
+            XlsxError::MalformedXml { part, offset, reason } => {
+                write!(f, "malformed XML in {} at byte {}: {}", part, offset, reason)
+            },
+            XlsxError::UnknownSheet { name } => write!(f, "unknown sheet \"{}\"", name),
+            XlsxError::InvalidAttribute { part, attribute, value } => {
+                write!(f, "invalid value \"{}\" of attribute {} in {}", value, attribute, part)
+            },
+            XlsxError::BadCompoundFile { reason } => write!(f, "not a valid xls file: {}", reason),
+            XlsxError::MalformedRecord { part, offset, reason } => {
+                write!(f, "malformed record in {} at byte {}: {}", part, offset, reason)
+            },
+            XlsxError::InvalidData { reason } => write!(f, "invalid data: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for XlsxError {}
+
+// error object passed to js side, `kind` and variant fields plus readable message
+#[derive(Serialize)]
+pub struct ErrorInfo<'a> {
+    #[serde(flatten)]
+    pub error: &'a XlsxError,
+    pub message: String,
+}
+
+impl<'a> ErrorInfo<'a> {
+    pub fn new(error: &'a XlsxError) -> ErrorInfo<'a> {
+        ErrorInfo { error, message: error.to_string() }
+    }
+}
+
+
+#[test]
+fn test_error_info() {
+    let error = XlsxError::MalformedXml {
+        part: String::from("xl/workbook.xml"),
+        offset: 42,
+        reason: String::from("unexpected token"),
+    };
+    let json = serde_json::to_value(ErrorInfo::new(&error)).unwrap();
+    assert_eq!(json["kind"], "malformedXml");
+    assert_eq!(json["part"], "xl/workbook.xml");
+    assert_eq!(json["offset"], 42);
+    assert_eq!(json["message"], "malformed XML in xl/workbook.xml at byte 42: unexpected token");
+}
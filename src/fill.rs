@@ -0,0 +1,175 @@
+use serde_json::{json, Value as JsonValue};
+
+use crate::Dict;
+
+// <gradientFill>, `path` gradients spread from the rectangle set by left, right, top and bottom (0 - 1)
+#[derive(Default)]
+pub struct Gradient {
+    pub path: bool,
+    pub degree: f64,
+    pub left: f64,
+    pub right: f64,
+    pub top: f64,
+    pub bottom: f64,
+    stops: Vec<(f64, String)>,
+}
+
+impl Gradient {
+    // "linear-gradient(90deg, #FFFFFF 0%, #000000 100%)"
+    fn to_css(&self) -> Option<String> {
+        if self.stops.is_empty() {
+            return None;
+        }
+        let stops: Vec<String> = self.stops.iter()
+            .map(|(position, color)| format!("{} {}%", color, round(position * 100.0)))
+            .collect();
+
+        if self.path {
+            let x = round((self.left + self.right) / 2.0 * 100.0);
+            let y = round((self.top + self.bottom) / 2.0 * 100.0);
+            Some(format!("radial-gradient(at {}% {}%, {})", x, y, stops.join(", ")))
+        } else {
+            // excel angle starts from left-to-right direction, css angle - from bottom-to-top
+            let angle = round((self.degree + 90.0).rem_euclid(360.0));
+            Some(format!("linear-gradient({}deg, {})", angle, stops.join(", ")))
+        }
+    }
+}
+
+fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+#[derive(Default)]
+pub struct Fill {
+    pattern: Option<String>,
+    fg_color: Option<String>,
+    bg_color: Option<String>,
+    gradient: Option<Gradient>,
+}
+
+impl Fill {
+    pub fn new() -> Fill {
+        Fill::default()
+    }
+    pub fn set_pattern(&mut self, pattern: String) {
+        self.pattern = Some(pattern);
+    }
+    pub fn set_fg_color(&mut self, color: String) {
+        self.fg_color = Some(color);
+    }
+    pub fn set_bg_color(&mut self, color: String) {
+        self.bg_color = Some(color);
+    }
+    pub fn set_gradient(&mut self, gradient: Gradient) {
+        self.gradient = Some(gradient);
+    }
+    pub fn add_stop(&mut self, position: f64) {
+        if let Some(gradient) = self.gradient.as_mut() {
+            gradient.stops.push((position, String::from("#000000")));
+        }
+    }
+    pub fn set_stop_color(&mut self, color: String) {
+        if let Some(stop) = self.gradient.as_mut().and_then(|g| g.stops.last_mut()) {
+            stop.1 = color;
+        }
+    }
+    // fills of differential styles keep the solid color in bgColor, and their pattern type is optional
+    pub fn get_differential_style(mut self) -> Dict {
+        if self.gradient.is_none() && matches!(self.pattern.as_deref(), None | Some("solid")) {
+            self.pattern = Some(String::from("solid"));
+            self.fg_color = self.bg_color.take().or(self.fg_color);
+        }
+        self.get_computed_style()
+    }
+    // `background` css value, and `pattern` object for patterns other than solid
+    pub fn get_computed_style(self) -> Dict {
+        let mut style = Dict::new();
+        if let Some(gradient) = self.gradient {
+            if let Some(css) = gradient.to_css() {
+                style.insert(String::from("background"), JsonValue::String(css));
+            }
+            return style;
+        }
+
+        // pattern is "none" when not set
+        match self.pattern.as_deref() {
+            None | Some("none") => (),
+            Some("solid") => {
+                if let Some(color) = self.fg_color.or(self.bg_color) {
+                    style.insert(String::from("background"), JsonValue::String(color));
+                }
+            },
+            // css can't draw patterns, so background color is used and the pattern itself is described separately
+            Some(pattern) => {
+                let mut info = json!({ "type": pattern });
+                if let Some(color) = self.fg_color {
+                    info["fgColor"] = JsonValue::String(color);
+                }
+                if let Some(color) = self.bg_color {
+                    style.insert(String::from("background"), JsonValue::String(color.clone()));
+                    info["bgColor"] = JsonValue::String(color);
+                }
+                style.insert(String::from("pattern"), info);
+            },
+        }
+        style
+    }
+}
+
+
+#[test]
+fn test_pattern_fill() {
+    let mut fill = Fill::new();
+    fill.set_fg_color(String::from("#FF0000"));
+    assert!(fill.get_computed_style().is_empty());
+
+    let mut fill = Fill::new();
+    fill.set_pattern(String::from("solid"));
+    fill.set_fg_color(String::from("#FF0000"));
+    fill.set_bg_color(String::from("#000000"));
+    let style = fill.get_computed_style();
+    assert_eq!(style["background"], "#FF0000");
+    assert!(!style.contains_key("pattern"));
+
+    let mut fill = Fill::new();
+    fill.set_pattern(String::from("darkGrid"));
+    fill.set_fg_color(String::from("#FF0000"));
+    fill.set_bg_color(String::from("#FFFF00"));
+    let style = fill.get_computed_style();
+    assert_eq!(style["background"], "#FFFF00");
+    assert_eq!(style["pattern"], json!({ "type": "darkGrid", "fgColor": "#FF0000", "bgColor": "#FFFF00" }));
+}
+
+#[test]
+fn test_differential_fill() {
+    let mut fill = Fill::new();
+    fill.set_bg_color(String::from("#FFC7CE"));
+    assert_eq!(fill.get_differential_style()["background"], "#FFC7CE");
+
+    let mut fill = Fill::new();
+    fill.set_pattern(String::from("lightGray"));
+    fill.set_fg_color(String::from("#FF0000"));
+    fill.set_bg_color(String::from("#FFFF00"));
+    assert_eq!(fill.get_differential_style()["background"], "#FFFF00");
+}
+
+#[test]
+fn test_gradient_fill() {
+    let mut fill = Fill::new();
+    fill.set_gradient(Gradient { degree: 90.0, ..Gradient::default() });
+    fill.add_stop(0.0);
+    fill.set_stop_color(String::from("#FFFFFF"));
+    fill.add_stop(1.0);
+    fill.set_stop_color(String::from("#4472C4"));
+    let style = fill.get_computed_style();
+    assert_eq!(style["background"], "linear-gradient(180deg, #FFFFFF 0%, #4472C4 100%)");
+
+    let mut fill = Fill::new();
+    fill.set_gradient(Gradient { path: true, left: 0.5, right: 0.5, top: 0.5, bottom: 0.5, ..Gradient::default() });
+    fill.add_stop(0.0);
+    fill.add_stop(0.333);
+    fill.set_stop_color(String::from("#FF0000"));
+    let style = fill.get_computed_style();
+    assert_eq!(style["background"], "radial-gradient(at 50% 50%, #000000 0%, #FF0000 33.3%)");
+}
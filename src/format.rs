@@ -0,0 +1,920 @@
+use crate::date::{serial_to_parts, DateParts};
+use crate::theme::Theme;
+use crate::CellType;
+
+const MONTHS: [&str; 12] = [
+    "January", "February", "March", "April", "May", "June",
+    "July", "August", "September", "October", "November", "December",
+];
+const DAYS: [&str; 7] = ["Sunday", "Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday"];
+
+// text of a value formatted with excel number format, as it is shown in a cell
+#[derive(Debug, PartialEq)]
+pub struct Formatted {
+    pub text: String,
+    // color of the used format section, like [Red]
+    pub color: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Literal(String),
+    // 0, # or ?
+    Digit(char),
+    Point,
+    Comma,
+    Percent,
+    // E+ or E-, with the case of "E"
+    Exponent(char, bool),
+    Slash,
+    // @
+    Text,
+    General,
+    Year(usize),
+    Month(usize),
+    Minute(usize),
+    Day(usize),
+    Hour(usize),
+    Second(usize),
+    SubSecond(usize),
+    AmPm(String),
+    // [h], [mm], [ss]
+    Elapsed(char, usize),
+}
+
+impl Token {
+    fn is_date(&self) -> bool {
+        matches!(self, Token::Year(_) | Token::Month(_) | Token::Minute(_) | Token::Day(_)
+            | Token::Hour(_) | Token::Second(_) | Token::AmPm(_) | Token::Elapsed(_, _))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Operator {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+}
+
+#[derive(Debug, Default)]
+struct Section {
+    tokens: Vec<Token>,
+    color: Option<String>,
+    condition: Option<(Operator, f64)>,
+}
+
+impl Section {
+    fn matches(&self, value: f64) -> bool {
+        match self.condition {
+            Some((Operator::Less, x)) => value < x,
+            Some((Operator::LessOrEqual, x)) => value <= x,
+            Some((Operator::Greater, x)) => value > x,
+            Some((Operator::GreaterOrEqual, x)) => value >= x,
+            Some((Operator::Equal, x)) => value == x,
+            Some((Operator::NotEqual, x)) => value != x,
+            None => true,
+        }
+    }
+    fn is_date(&self) -> bool {
+        self.tokens.iter().any(|t| t.is_date())
+    }
+    fn has_text(&self) -> bool {
+        self.tokens.contains(&Token::Text)
+    }
+}
+
+// formats raw cell value, as it is stored in xlsx, according to its type
+// `palette` resolves [Color1] - [Color56] with the custom colors of the workbook
+pub fn format_cell(raw: &str, t: CellType, code: &str, date1904: bool, palette: &Theme) -> Formatted {
+    let plain = |text: String| Formatted { text, color: None };
+    match t {
+        CellType::Number => match raw.trim().parse::<f64>() {
+            Ok(n) => format_number(n, code, date1904, palette),
+            Err(_) => plain(raw.to_string()),
+        },
+        CellType::String => format_text(raw, code, palette),
+        CellType::Boolean => match raw.trim() {
+            "1" | "true" => plain(String::from("TRUE")),
+            "0" | "false" => plain(String::from("FALSE")),
+            _ => plain(raw.to_string()),
+        },
+        CellType::Error | CellType::Date => plain(raw.to_string()),
+    }
+}
+
+pub fn format_number(value: f64, code: &str, date1904: bool, palette: &Theme) -> Formatted {
+    let sections = parse(code, palette);
+    let (section, abs) = match pick_section(&sections, value) {
+        Some(x) => x,
+        None => return Formatted { text: format_general(value), color: None },
+    };
+    let value = if abs { value.abs() } else { value };
+
+    let text = if section.is_date() {
+        format_date(&section.tokens, value, date1904)
+    } else {
+        format_numeric(&section.tokens, value)
+    };
+    Formatted { text, color: section.color.clone() }
+}
+
+pub fn format_text(value: &str, code: &str, palette: &Theme) -> Formatted {
+    let sections = parse(code, palette);
+    let section = match sections.get(3) {
+        Some(section) => Some(section),
+        None => sections.first().filter(|s| s.has_text()),
+    };
+
+    match section {
+        Some(section) => {
+            let mut text = String::new();
+            for token in section.tokens.iter() {
+                match token {
+                    Token::Text => text.push_str(value),
+                    Token::Literal(s) => text.push_str(s),
+                    _ => (),
+                }
+            }
+            Formatted { text, color: section.color.clone() }
+        },
+        None => Formatted { text: value.to_string(), color: None },
+    }
+}
+
+// section used for a number and a flag to drop the sign of the number
+fn pick_section(sections: &[Section], value: f64) -> Option<(&Section, bool)> {
+    // 4th section and a single text-only section are not used for numbers
+    let numeric: Vec<&Section> = sections.iter().take(3).collect();
+    if numeric.is_empty() || (numeric.len() == 1 && numeric[0].has_text()) {
+        return None;
+    }
+
+    if numeric.iter().any(|s| s.condition.is_some()) {
+        for section in numeric.iter() {
+            if section.matches(value) {
+                return Some((section, false));
+            }
+        }
+        return None;
+    }
+
+    match numeric.len() {
+        1 => Some((numeric[0], false)),
+        2 if value >= 0.0 => Some((numeric[0], false)),
+        2 => Some((numeric[1], true)),
+        _ if value > 0.0 => Some((numeric[0], false)),
+        _ if value < 0.0 => Some((numeric[1], true)),
+        _ => Some((numeric[2], false)),
+    }
+}
+
+fn parse(code: &str, palette: &Theme) -> Vec<Section> {
+    let mut sections = vec!();
+    let mut section = Section::default();
+
+    let mut chars = code.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            ';' => {
+                sections.push(finish_section(section));
+                section = Section::default();
+            },
+            '"' => {
+                let mut text = String::new();
+                for q in chars.by_ref() {
+                    if q == '"' {
+                        break;
+                    }
+                    text.push(q);
+                }
+                section.tokens.push(Token::Literal(text));
+            },
+            '\\' => {
+                if let Some(x) = chars.next() {
+                    section.tokens.push(Token::Literal(x.to_string()));
+                }
+            },
+            // space with width of the next char
+            '_' => {
+                chars.next();
+                section.tokens.push(Token::Literal(String::from(" ")));
+            },
+            // repeat next char to fill the cell, not applicable to plain text
+            '*' => {
+                chars.next();
+            },
+            '[' => {
+                let mut content = String::new();
+                for q in chars.by_ref() {
+                    if q == ']' {
+                        break;
+                    }
+                    content.push(q);
+                }
+                parse_bracket(&content, &mut section, palette);
+            },
+            '0' | '#' | '?' => section.tokens.push(Token::Digit(c)),
+            '.' => section.tokens.push(Token::Point),
+            ',' => section.tokens.push(Token::Comma),
+            '%' => section.tokens.push(Token::Percent),
+            '/' => section.tokens.push(Token::Slash),
+            '@' => section.tokens.push(Token::Text),
+            'E' | 'e' if chars.peek() == Some(&'+') || chars.peek() == Some(&'-') => {
+                let plus = chars.next() == Some('+');
+                section.tokens.push(Token::Exponent(c, plus));
+            },
+            'G' | 'g' if starts_with_ignore_case(&chars, "eneral") => {
+                chars.nth(5);
+                section.tokens.push(Token::General);
+            },
+            'A' | 'a' if starts_with_ignore_case(&chars, "m/pm") => {
+                let rest: String = chars.by_ref().take(4).collect();
+                section.tokens.push(Token::AmPm(format!("{}{}", c, rest)));
+            },
+            'A' | 'a' if starts_with_ignore_case(&chars, "/p") => {
+                let rest: String = chars.by_ref().take(2).collect();
+                section.tokens.push(Token::AmPm(format!("{}{}", c, rest)));
+            },
+            _ => {
+                let lower = c.to_ascii_lowercase();
+                if matches!(lower, 'y' | 'm' | 'd' | 'h' | 's') {
+                    let mut len = 1;
+                    while chars.peek().map(|x| x.to_ascii_lowercase()) == Some(lower) {
+                        chars.next();
+                        len += 1;
+                    }
+                    section.tokens.push(match lower {
+                        'y' => Token::Year(len),
+                        'm' => Token::Month(len),
+                        'd' => Token::Day(len),
+                        'h' => Token::Hour(len),
+                        _ => Token::Second(len),
+                    });
+                } else {
+                    section.tokens.push(Token::Literal(c.to_string()));
+                }
+            },
+        }
+    }
+    sections.push(finish_section(section));
+    sections
+}
+
+fn starts_with_ignore_case(chars: &std::iter::Peekable<std::str::Chars>, text: &str) -> bool {
+    let next: String = chars.clone().take(text.len()).collect();
+    next.eq_ignore_ascii_case(text)
+}
+
+fn parse_bracket(content: &str, section: &mut Section, palette: &Theme) {
+    let lower = content.to_ascii_lowercase();
+    if !lower.is_empty() && lower.chars().all(|x| x == 'h' || x == 'm' || x == 's') {
+        let first = lower.chars().next().unwrap_or('h');
+        section.tokens.push(Token::Elapsed(first, lower.len()));
+    } else if let Some(locale) = content.strip_prefix('$') {
+        // [$€-407], the part before "-" is a currency symbol
+        let symbol = locale.split('-').next().unwrap_or("");
+        if !symbol.is_empty() {
+            section.tokens.push(Token::Literal(symbol.to_string()));
+        }
+    } else if let Some(condition) = parse_condition(content) {
+        section.condition = Some(condition);
+    } else if let Some(color) = parse_color(&lower, palette) {
+        section.color = Some(color);
+    }
+}
+
+fn parse_condition(content: &str) -> Option<(Operator, f64)> {
+    let operators = [
+        ("<=", Operator::LessOrEqual),
+        (">=", Operator::GreaterOrEqual),
+        ("<>", Operator::NotEqual),
+        ("<", Operator::Less),
+        (">", Operator::Greater),
+        ("=", Operator::Equal),
+    ];
+    for (prefix, operator) in operators.iter() {
+        if let Some(value) = content.strip_prefix(prefix) {
+            return value.trim().parse::<f64>().ok().map(|v| (*operator, v));
+        }
+    }
+    None
+}
+
+fn parse_color(name: &str, palette: &Theme) -> Option<String> {
+    let color = match name {
+        "black" => "#000000",
+        "blue" => "#0000FF",
+        "cyan" => "#00FFFF",
+        "green" => "#00FF00",
+        "magenta" => "#FF00FF",
+        "red" => "#FF0000",
+        "white" => "#FFFFFF",
+        "yellow" => "#FFFF00",
+        _ => {
+            // [Color1] - [Color56] are the entries of indexed palette, starting from 8
+            let index = name.strip_prefix("color")?.parse::<u32>().ok()?;
+            if !(1..=56).contains(&index) {
+                return None;
+            }
+            return Some(palette.indexed_color(&(index + 7).to_string()));
+        }
+    };
+    Some(String::from(color))
+}
+
+// resolves tokens which meaning depends on the section type
+fn finish_section(mut section: Section) -> Section {
+    if !section.is_date() {
+        return section;
+    }
+
+    let mut tokens: Vec<Token> = vec!();
+    let mut iter = section.tokens.into_iter().peekable();
+    while let Some(token) = iter.next() {
+        match token {
+            // fractional seconds, like ss.00
+            Token::Point if iter.peek() == Some(&Token::Digit('0')) => {
+                let mut len = 0;
+                while iter.peek() == Some(&Token::Digit('0')) {
+                    iter.next();
+                    len += 1;
+                }
+                tokens.push(Token::SubSecond(len));
+            },
+            Token::Point => tokens.push(Token::Literal(String::from("."))),
+            Token::Comma => tokens.push(Token::Literal(String::from(","))),
+            Token::Slash => tokens.push(Token::Literal(String::from("/"))),
+            Token::Percent => tokens.push(Token::Literal(String::from("%"))),
+            Token::Digit(c) => tokens.push(Token::Literal(c.to_string())),
+            t => tokens.push(t),
+        }
+    }
+
+    // "m" is minutes after hours or before seconds, months otherwise
+    let date_positions: Vec<usize> = tokens.iter().enumerate()
+        .filter(|(_, t)| t.is_date())
+        .map(|(i, _)| i)
+        .collect();
+    for (n, &i) in date_positions.iter().enumerate() {
+        if let Token::Month(len) = tokens[i] {
+            let after_hours = n > 0 && matches!(tokens[date_positions[n - 1]], Token::Hour(_) | Token::Elapsed('h', _));
+            let before_seconds = date_positions.get(n + 1)
+                .map(|&j| matches!(tokens[j], Token::Second(_) | Token::Elapsed('s', _)))
+                .unwrap_or(false);
+            if after_hours || before_seconds {
+                tokens[i] = Token::Minute(len);
+            }
+        }
+    }
+
+    section.tokens = tokens;
+    section
+}
+
+// excel "General" format, up to 11 chars
+fn format_general(value: f64) -> String {
+    if value == 0.0 || !value.is_finite() {
+        return String::from("0");
+    }
+    let abs = value.abs();
+    if !(1e-9..1e11).contains(&abs) {
+        let text = format!("{:.5E}", value);
+        let (mantissa, exp) = text.split_once('E').unwrap_or((&text, "0"));
+        let mantissa = trim_zeros(mantissa);
+        let exp: i32 = exp.parse().unwrap_or(0);
+        return format!("{}E{}{:02}", mantissa, if exp < 0 { '-' } else { '+' }, exp.abs());
+    }
+    let int_digits = (abs.log10().floor() as i32 + 1).max(1);
+    let decimals = (10 - int_digits).max(0) as usize;
+    let text = trim_zeros(&round_to(abs, decimals));
+    if value < 0.0 {
+        format!("-{}", text)
+    } else {
+        text
+    }
+}
+
+// rounds not negative value half away from zero, as excel does, and prints it with fixed decimals
+// rounding is done on 15 significant digits, so binary noise like 1.005 -> 1.00499.. is dropped
+fn round_to(value: f64, decimals: usize) -> String {
+    let sci = format!("{:.14e}", value);
+    let (mantissa, exp) = sci.split_once('e').unwrap_or((&sci, "0"));
+    let exp: i32 = exp.parse().unwrap_or(0);
+    let mut digits: Vec<u8> = mantissa.bytes().filter(|b| b.is_ascii_digit()).map(|b| b - b'0').collect();
+    // count of digits before the decimal point
+    let mut int_len = exp + 1;
+
+    let keep = int_len + decimals as i32;
+    if keep < 0 {
+        digits.clear();
+    } else if (keep as usize) < digits.len() {
+        let keep = keep as usize;
+        let round_up = digits[keep] >= 5;
+        digits.truncate(keep);
+        if round_up {
+            let mut i = keep;
+            loop {
+                if i == 0 {
+                    digits.insert(0, 1);
+                    int_len += 1;
+                    break;
+                }
+                i -= 1;
+                if digits[i] == 9 {
+                    digits[i] = 0;
+                } else {
+                    digits[i] += 1;
+                    break;
+                }
+            }
+        }
+    }
+
+    let digit = |i: i32| -> char {
+        if i < 0 {
+            '0'
+        } else {
+            (b'0' + digits.get(i as usize).copied().unwrap_or(0)) as char
+        }
+    };
+    let mut text: String = if int_len <= 0 { String::from("0") } else { (0..int_len).map(digit).collect() };
+    if decimals > 0 {
+        text.push('.');
+        text.extend((0..decimals as i32).map(|i| digit(int_len + i)));
+    }
+    text
+}
+
+fn trim_zeros(text: &str) -> String {
+    if text.contains('.') {
+        text.trim_end_matches('0').trim_end_matches('.').to_string()
+    } else {
+        text.to_string()
+    }
+}
+
+fn format_numeric(tokens: &[Token], value: f64) -> String {
+    if tokens.contains(&Token::General) {
+        let mut text = String::new();
+        for token in tokens.iter() {
+            match token {
+                Token::General => text.push_str(&format_general(value)),
+                Token::Literal(s) => text.push_str(s),
+                Token::Percent => text.push('%'),
+                _ => (),
+            }
+        }
+        return text;
+    }
+
+    let negative = value < 0.0;
+    let mut value = value.abs();
+    for token in tokens.iter() {
+        if *token == Token::Percent {
+            value *= 100.0;
+        }
+    }
+
+    let text = if let Some(index) = tokens.iter().position(|t| matches!(t, Token::Exponent(_, _))) {
+        format_scientific(&tokens[..index], &tokens[index..], value)
+    } else if let Some(index) = tokens.iter().position(|t| *t == Token::Slash) {
+        format_fraction(&tokens[..index], &tokens[index + 1..], value)
+    } else {
+        let (tokens, scale) = scale_commas(tokens);
+        format_decimal(&tokens, value / 1000f64.powi(scale))
+    };
+
+    // minus is not shown when the number is rounded to zero
+    if negative && text.chars().any(|c| c.is_ascii_digit() && c != '0') {
+        format!("-{}", text)
+    } else {
+        text
+    }
+}
+
+// commas right after the last digit placeholder of integer part divide the number by 1000
+fn scale_commas(tokens: &[Token]) -> (Vec<Token>, i32) {
+    let int_end = tokens.iter().position(|t| *t == Token::Point).unwrap_or(tokens.len());
+    let last_digit = match tokens[..int_end].iter().rposition(|t| matches!(t, Token::Digit(_))) {
+        Some(i) => i,
+        None => return (tokens.to_vec(), 0),
+    };
+
+    let mut scale = 0;
+    let mut result = vec!();
+    for (i, token) in tokens.iter().enumerate() {
+        if *token == Token::Comma && i > last_digit && i < int_end {
+            scale += 1;
+        } else {
+            result.push(token.clone());
+        }
+    }
+    (result, scale)
+}
+
+// value is not negative
+// `is_multiple_of` suggested by newer clippy needs rust 1.87
+#[allow(unknown_lints, clippy::manual_is_multiple_of)]
+fn format_decimal(tokens: &[Token], value: f64) -> String {
+    let point = tokens.iter().position(|t| *t == Token::Point);
+    let (int_tokens, frac_tokens) = match point {
+        Some(i) => (&tokens[..i], &tokens[i + 1..]),
+        None => (tokens, &tokens[tokens.len()..]),
+    };
+
+    let decimals = frac_tokens.iter().filter(|t| matches!(t, Token::Digit(_))).count();
+    let rounded = round_to(value, decimals);
+    let (int_str, frac_str) = rounded.split_once('.').unwrap_or((&rounded, ""));
+    let int_digits: Vec<char> = if int_str == "0" { vec!() } else { int_str.chars().collect() };
+
+    // comma between digit placeholders turns on thousands separator
+    let first_digit = int_tokens.iter().position(|t| matches!(t, Token::Digit(_)));
+    let grouping = first_digit
+        .map(|first| int_tokens[first..].contains(&Token::Comma))
+        .unwrap_or(false);
+    let placeholders = int_tokens.iter().filter(|t| matches!(t, Token::Digit(_))).count();
+
+    // integer part is filled from right to left
+    let mut reversed: Vec<char> = vec!();
+    let mut position = 0;
+    let mut seen = 0;
+    let push_digit = |reversed: &mut Vec<char>, position: &mut usize, placeholder: char| {
+        let digit = if *position < int_digits.len() {
+            Some(int_digits[int_digits.len() - 1 - *position])
+        } else {
+            match placeholder {
+                '0' => Some('0'),
+                '?' => {
+                    reversed.push(' ');
+                    None
+                },
+                _ => None,
+            }
+        };
+        if let Some(digit) = digit {
+            if grouping && *position > 0 && *position % 3 == 0 {
+                reversed.push(',');
+            }
+            reversed.push(digit);
+        }
+        *position += 1;
+    };
+
+    if placeholders == 0 && point.is_some() {
+        while position < int_digits.len() {
+            push_digit(&mut reversed, &mut position, '#');
+        }
+    }
+    for token in int_tokens.iter().rev() {
+        match token {
+            Token::Digit(placeholder) => {
+                seen += 1;
+                push_digit(&mut reversed, &mut position, *placeholder);
+                // the leftmost placeholder takes all remaining digits
+                if seen == placeholders {
+                    while position < int_digits.len() {
+                        push_digit(&mut reversed, &mut position, *placeholder);
+                    }
+                }
+            },
+            Token::Literal(s) => reversed.extend(s.chars().rev()),
+            Token::Percent => reversed.push('%'),
+            _ => (),
+        }
+    }
+    let mut text: String = reversed.into_iter().rev().collect();
+
+    if point.is_some() {
+        text.push('.');
+        let frac_digits: Vec<char> = frac_str.chars().collect();
+        let placeholders: Vec<char> = frac_tokens.iter()
+            .filter_map(|t| if let Token::Digit(c) = t { Some(*c) } else { None })
+            .collect();
+        // trailing zeros are hidden for # and ? placeholders
+        let mut keep = frac_digits.len();
+        while keep > 0 && frac_digits[keep - 1] == '0' && placeholders[keep - 1] != '0' {
+            keep -= 1;
+        }
+
+        let mut index = 0;
+        for token in frac_tokens.iter() {
+            match token {
+                Token::Digit(placeholder) => {
+                    if index < keep {
+                        text.push(frac_digits[index]);
+                    } else if *placeholder == '?' {
+                        text.push(' ');
+                    }
+                    index += 1;
+                },
+                Token::Literal(s) => text.push_str(s),
+                Token::Percent => text.push('%'),
+                _ => (),
+            }
+        }
+    }
+
+    text
+}
+
+fn format_scientific(mantissa_tokens: &[Token], exp_tokens: &[Token], value: f64) -> String {
+    let int_end = mantissa_tokens.iter().position(|t| *t == Token::Point).unwrap_or(mantissa_tokens.len());
+    let int_placeholders: Vec<char> = mantissa_tokens[..int_end].iter()
+        .filter_map(|t| if let Token::Digit(c) = t { Some(*c) } else { None })
+        .collect();
+    let decimals = mantissa_tokens[int_end..].iter().filter(|t| matches!(t, Token::Digit(_))).count();
+    let n = int_placeholders.len() as i32;
+
+    let exponent_for = |magnitude: i32| -> i32 {
+        if n > 1 && int_placeholders.contains(&'#') {
+            // engineering notation, like ##0.0E+0
+            magnitude.div_euclid(n) * n
+        } else {
+            magnitude - (n - 1).max(0)
+        }
+    };
+
+    let mut exp = if value == 0.0 { 0 } else { exponent_for(value.log10().floor() as i32) };
+    let mut mantissa = value / 10f64.powi(exp);
+    // rounding may produce one more digit, like 9.99 -> 10.0
+    let rounded: f64 = round_to(mantissa, decimals).parse().unwrap_or(mantissa);
+    if value != 0.0 && rounded >= 10f64.powi(n.max(1)) {
+        exp = exponent_for(exp + n.max(1));
+        mantissa = value / 10f64.powi(exp);
+    }
+
+    let mut text = format_decimal(mantissa_tokens, mantissa);
+
+    let (e, plus) = match exp_tokens.first() {
+        Some(Token::Exponent(e, plus)) => (*e, *plus),
+        _ => ('E', true),
+    };
+    text.push(e);
+    if exp < 0 {
+        text.push('-');
+    } else if plus {
+        text.push('+');
+    }
+    let width = exp_tokens.iter().filter(|t| matches!(t, Token::Digit('0'))).count();
+    text.push_str(&format!("{:0width$}", exp.abs(), width = width));
+    for token in exp_tokens.iter() {
+        if let Token::Literal(s) = token {
+            text.push_str(s);
+        }
+    }
+    text
+}
+
+fn format_fraction(before: &[Token], after: &[Token], value: f64) -> String {
+    // numerator placeholders are right before the slash, integer part is before them
+    let numerator_start = before.iter().rposition(|t| !matches!(t, Token::Digit(_))).map(|i| i + 1).unwrap_or(0);
+    let int_tokens = &before[..numerator_start];
+    let has_int = int_tokens.iter().any(|t| matches!(t, Token::Digit(_)));
+
+    // denominator is either a number, like /8, or placeholders, like /??
+    let mut fixed = String::new();
+    let mut den_placeholders = 0;
+    let mut rest = after.len();
+    for (i, token) in after.iter().enumerate() {
+        match token {
+            Token::Literal(s) if s.len() == 1 && s.chars().all(|c| c.is_ascii_digit()) && den_placeholders == 0 => fixed.push_str(s),
+            Token::Digit(_) if !fixed.is_empty() => fixed.push('0'),
+            Token::Digit(_) if fixed.is_empty() => den_placeholders += 1,
+            _ => {
+                rest = i;
+                break;
+            },
+        }
+    }
+    let fixed: u64 = fixed.parse().unwrap_or(0);
+
+    let (mut whole, frac) = if has_int { (value.trunc(), value.fract()) } else { (0.0, value) };
+    let (mut numerator, denominator) = if fixed > 0 {
+        ((frac * fixed as f64).round() as u64, fixed)
+    } else {
+        let max = 10u64.pow(den_placeholders.clamp(1, 4) as u32) - 1;
+        approximate(frac, max)
+    };
+    if has_int && numerator == denominator {
+        whole += 1.0;
+        numerator = 0;
+    }
+
+    let mut text = String::new();
+    if has_int && (whole > 0.0 || numerator == 0) {
+        text.push_str(&format_decimal(int_tokens, whole));
+        if numerator == 0 {
+            return text.trim().to_string();
+        }
+    }
+    if numerator > 0 || !has_int {
+        text.push_str(&format!("{}/{}", numerator, denominator));
+    }
+    for token in after[rest..].iter() {
+        if let Token::Literal(s) = token {
+            text.push_str(s);
+        }
+    }
+    text.trim().to_string()
+}
+
+// closest fraction with denominator not greater than max
+fn approximate(value: f64, max: u64) -> (u64, u64) {
+    let mut best = ((value).round() as u64, 1);
+    let mut best_error = (value - best.0 as f64).abs();
+    for denominator in 2..=max {
+        let numerator = (value * denominator as f64).round();
+        let error = (value - numerator / denominator as f64).abs();
+        if error < best_error - 1e-12 {
+            best = (numerator as u64, denominator);
+            best_error = error;
+        }
+    }
+    best
+}
+
+fn format_date(tokens: &[Token], value: f64, date1904: bool) -> String {
+    let digits = tokens.iter()
+        .filter_map(|t| if let Token::SubSecond(n) = t { Some(*n as u32) } else { None })
+        .max()
+        .unwrap_or(0);
+    let parts: DateParts = match serial_to_parts(value, date1904, digits) {
+        Some(parts) => parts,
+        None => return String::from("#######"),
+    };
+    let twelve_hours = tokens.iter().any(|t| matches!(t, Token::AmPm(_)));
+
+    let mut text = String::new();
+    for token in tokens.iter() {
+        match token {
+            Token::Literal(s) => text.push_str(s),
+            Token::Year(n) if *n <= 2 => text.push_str(&format!("{:02}", parts.year % 100)),
+            Token::Year(_) => text.push_str(&format!("{:04}", parts.year)),
+            Token::Month(1) => text.push_str(&parts.month.to_string()),
+            Token::Month(2) => text.push_str(&format!("{:02}", parts.month)),
+            Token::Month(n) => {
+                let name = MONTHS[(parts.month as usize + 11) % 12];
+                match n {
+                    3 => text.push_str(&name[..3]),
+                    4 => text.push_str(name),
+                    _ => text.push_str(&name[..1]),
+                }
+            },
+            Token::Day(1) => text.push_str(&parts.day.to_string()),
+            Token::Day(2) => text.push_str(&format!("{:02}", parts.day)),
+            Token::Day(n) => {
+                let name = DAYS[parts.weekday as usize % 7];
+                if *n == 3 {
+                    text.push_str(&name[..3]);
+                } else {
+                    text.push_str(name);
+                }
+            },
+            Token::Hour(n) => {
+                let hour = if twelve_hours {
+                    match parts.hour % 12 {
+                        0 => 12,
+                        h => h,
+                    }
+                } else {
+                    parts.hour
+                };
+                if *n == 1 {
+                    text.push_str(&hour.to_string());
+                } else {
+                    text.push_str(&format!("{:02}", hour));
+                }
+            },
+            Token::Minute(1) => text.push_str(&parts.minute.to_string()),
+            Token::Minute(_) => text.push_str(&format!("{:02}", parts.minute)),
+            Token::Second(1) => text.push_str(&parts.second.to_string()),
+            Token::Second(_) => text.push_str(&format!("{:02}", parts.second)),
+            Token::SubSecond(n) => {
+                let ms = format!("{:03}", parts.ms);
+                text.push('.');
+                text.push_str(&ms[..(*n).min(3)]);
+            },
+            Token::AmPm(s) => {
+                let pm = parts.hour >= 12;
+                let marker = if s.len() > 3 {
+                    if pm { "PM" } else { "AM" }
+                } else if pm {
+                    "P"
+                } else {
+                    "A"
+                };
+                if s.starts_with(|c: char| c.is_ascii_lowercase()) {
+                    text.push_str(&marker.to_ascii_lowercase());
+                } else {
+                    text.push_str(marker);
+                }
+            },
+            Token::Elapsed(unit, n) => {
+                let seconds = parts.serial_days * 86_400 + parts.day_ms / 1000;
+                let total = match unit {
+                    'h' => seconds / 3600,
+                    'm' => seconds / 60,
+                    _ => seconds,
+                };
+                text.push_str(&format!("{:0width$}", total, width = *n));
+            },
+            _ => (),
+        }
+    }
+    text
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn f(value: f64, code: &str) -> String {
+        format_number(value, code, false, &Theme::default()).text
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(f(1234.5678, "General"), "1234.5678");
+        assert_eq!(f(0.1234, "0.00%"), "12.34%");
+        assert_eq!(f(1234567.891, "#,##0.00"), "1,234,567.89");
+        assert_eq!(f(-1234.5, "#,##0"), "-1,235");
+        assert_eq!(f(1.005, "0.00"), "1.01");
+        assert_eq!(f(0.6, "0"), "1");
+        assert_eq!(f(1e20, "0"), "100000000000000000000");
+        assert_eq!(f(5.0, "000"), "005");
+        assert_eq!(f(0.5, "#.##"), ".5");
+        assert_eq!(f(1.5, "0.0#"), "1.5");
+        assert_eq!(f(1234567.0, "#,##0,\"K\""), "1,235K");
+        assert_eq!(f(123456789.0, "000-00-0000"), "123-45-6789");
+        assert_eq!(f(-0.001, "0.00"), "0.00");
+        assert_eq!(f(1e12, "General"), "1E+12");
+        assert_eq!(f(1.0 / 3.0, "General"), "0.333333333");
+    }
+
+    #[test]
+    fn sections_and_colors() {
+        assert_eq!(f(-5.0, "0;(0)"), "(5)");
+        assert_eq!(f(0.0, "0;-0;\"zero\""), "zero");
+        assert_eq!(f(-3.0, "0;;0"), "");
+
+        let formatted = format_number(-10.0, "#,##0.00;[Red]-#,##0.00", false, &Theme::default());
+        assert_eq!(formatted.text, "-10.00");
+        assert_eq!(formatted.color, Some(String::from("#FF0000")));
+
+        // [Color1] is the 8th entry of the palette, which can be replaced by the workbook
+        let mut palette = Theme::default();
+        assert_eq!(format_number(1.0, "[Color1]0", false, &palette).color.as_deref(), Some("#000000"));
+        let mut colors = vec![String::from("#FFFFFF"); 8];
+        colors.push(String::from("rgba(1,2,3,1)"));
+        palette.set_indexed_colors(colors);
+        assert_eq!(format_number(1.0, "[Color1]0", false, &palette).color.as_deref(), Some("rgba(1,2,3,1)"));
+
+        assert_eq!(f(150.0, "[>100]\"big\";[<=100]0"), "big");
+        assert_eq!(f(50.0, "[>100]\"big\";[<=100]0"), "50");
+        assert_eq!(f(9.5, "[$€-407]#,##0.00"), "€9.50");
+    }
+
+    #[test]
+    fn scientific() {
+        assert_eq!(f(12345.0, "0.00E+00"), "1.23E+04");
+        assert_eq!(f(0.00012, "0.0E+0"), "1.2E-4");
+        assert_eq!(f(12345.0, "##0.0E+0"), "12.3E+3");
+        assert_eq!(f(9.999, "0.00E+00"), "1.00E+01");
+    }
+
+    #[test]
+    fn fractions() {
+        assert_eq!(f(1.5, "# ?/?"), "1 1/2");
+        assert_eq!(f(0.75, "# ?/?"), "3/4");
+        assert_eq!(f(3.14158, "# ??/??"), "3 14/99");
+        assert_eq!(f(3.14158, "# ?/?"), "3 1/7");
+        assert_eq!(f(2.0, "# ?/?"), "2");
+        assert_eq!(f(0.3, "?/8"), "2/8");
+        assert_eq!(f(1.25, "?/?"), "5/4");
+    }
+
+    #[test]
+    fn dates_and_times() {
+        assert_eq!(f(45123.0, "mm-dd-yy"), "07-16-23");
+        assert_eq!(f(45123.0, "d-mmm-yy"), "16-Jul-23");
+        assert_eq!(f(45123.0, "dddd, mmmm d, yyyy"), "Sunday, July 16, 2023");
+        assert_eq!(f(45123.75, "h:mm AM/PM"), "6:00 PM");
+        assert_eq!(f(45123.5, "m/d/yy h:mm"), "7/16/23 12:00");
+        assert_eq!(f(1.5, "[h]:mm:ss"), "36:00:00");
+        assert_eq!(f(0.000_011_6, "mm:ss.0"), "00:01.0");
+        assert_eq!(f(-1.0, "yyyy"), "#######");
+    }
+
+    #[test]
+    fn text() {
+        assert_eq!(format_text("abc", "@", &Theme::default()).text, "abc");
+        assert_eq!(format_text("abc", "\"Name: \"@", &Theme::default()).text, "Name: abc");
+        assert_eq!(format_text("abc", "0;0;0;[Blue]\"<\"@\">\"", &Theme::default()).text, "<abc>");
+        assert_eq!(format_text("abc", "0.00", &Theme::default()).text, "abc");
+        assert_eq!(f(12.0, "@"), "12");
+    }
+}
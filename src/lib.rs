@@ -1,135 +1,722 @@
-use wasm_bindgen;
-use wasm_bindgen::prelude::*;
-
 // serializing helpers
-use serde::Serialize;
-use gloo_utils::format::JsValueSerdeExt;
+use serde::{Deserialize, Serialize};
 
 // parsing xlsx (zip + xml)
-use quick_xml;
-use zip;
-
-use quick_xml::events::Event;
+use quick_xml::events::BytesStart;
+use quick_xml::events::attributes::Attribute;
 use quick_xml::reader::Reader as XmlReader;
 use zip::read::{ZipArchive, ZipFile};
+use zip::result::ZipError;
 
 // default collections
 use std::io::Cursor;
-use std::io::BufReader;
+use std::io::{BufRead, BufReader};
 use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
 use serde_json::Value as JsonValue;
 
 
+#[cfg(feature = "wasm")]
 pub mod utils;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "wasm")]
+pub use crate::wasm::XLSX;
+pub mod workbook;
+pub use crate::workbook::Workbook;
+pub mod sheet;
+pub use crate::sheet::{Row, RowCursor, Rows};
+mod cfb;
+mod xls;
+mod xlsb;
+mod ods;
+mod csv;
+pub use crate::csv::CsvOptions;
+mod writer;
+pub use crate::writer::{write_xlsx, write_xlsx_with, ExportData};
 pub mod border;
-use crate::border::{Border, BorderPosition};
+pub mod fill;
 pub mod range;
-use crate::range::{Range, cell_index_to_offsets};
+mod validation;
+mod conditional_format;
+#[cfg(test)]
+use crate::range::cell_index_to_offsets;
+pub mod error;
+pub use crate::error::XlsxError;
+pub mod date;
+pub mod format;
+pub mod rich_text;
+use crate::rich_text::TextRun;
+pub mod theme;
+use crate::theme::Theme;
 
 type XlsReader<'a> = XmlReader<BufReader<ZipFile<'a>>>;
-type Sheet = (String, String);
 type Dict = HashMap<String, JsonValue>;
+// css-like style properties, like { "fontWeight": "bold", "background": "#FF0000" }
+pub type Style = HashMap<String, JsonValue>;
 
-// default sheet settings
-const DEFAULT_CELL_WIDTH: f32 = 15.75;
-const DEFAULT_CELL_HEIGHT: f32 = 14.25;
-const WIDTH_COEF: f32 = 8.5;
-const HEIGHT_COEF: f32 = 0.75;
 const PT_COEF: f32 = 0.75;
 
-#[derive(PartialEq)]
-enum StyleXMLPath {
-    Any,
-    Font,
-    Fill,
-    Border,
-    CellXfs,
-    Xf,
-}
-
 pub const WITH_FORMULAS: u32   = 1;
-
-#[derive(PartialEq)]
-enum SharedStringXMLPath {
-    Any,
-    Si,
-    T,
-}
-
-#[derive(Debug)]
-enum XlsxError {
-    Default,
-    FileNotFound(String)
-}
+pub const WITH_TYPED_VALUES: u32   = 2;
+pub const WITH_DATES: u32   = 4;
+pub const WITH_FORMATTED_TEXT: u32   = 8;
+pub const WITH_RICH_TEXT: u32   = 16;
+pub const WITH_BORDER_DETAILS: u32   = 32;
+pub const WITH_SPARSE_CELLS: u32   = 64;
+pub const WITH_VALIDATION_VALUES: u32   = 128;
 
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 pub struct ColumnData {
     pub width: f32,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub hidden: Option<bool>
+    pub hidden: Option<bool>,
+    // set with WITH_SPARSE_CELLS, when only columns with custom width are listed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<u32>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 pub struct RowData {
     pub height: f32,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub hidden: Option<bool>
+    pub hidden: Option<bool>,
+    // set with WITH_SPARSE_CELLS, when only rows with custom height are listed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<u32>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
 pub struct CellCoords {
     pub column: u32,
     pub row: u32,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
 pub struct MergedCell {
     pub from: CellCoords,
     pub to: CellCoords,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CellType {
+    Number,
+    String,
+    Boolean,
+    Error,
+    Date,
+}
+
+impl CellType {
+    // value of the `t` attribute of <c> tag
+    pub fn from_xlsx(t: &str) -> CellType {
+        match t {
+            "s" | "str" | "inlineStr" => CellType::String,
+            "b" => CellType::Boolean,
+            "e" => CellType::Error,
+            "d" => CellType::Date,
+            _ => CellType::Number,
+        }
+    }
+}
+
+// numbers and booleans are stored as strings unless WITH_TYPED_VALUES flag is used
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum CellValue {
+    Bool(bool),
+    Number(f64),
+    String(String),
+}
+
+impl CellValue {
+    pub fn typed(value: String, t: CellType) -> CellValue {
+        match t {
+            CellType::Number => match value.trim().parse::<f64>() {
+                Ok(n) if n.is_finite() => CellValue::Number(n),
+                _ => CellValue::String(value),
+            },
+            CellType::Boolean => match value.trim() {
+                "1" | "true" => CellValue::Bool(true),
+                "0" | "false" => CellValue::Bool(false),
+                _ => CellValue::String(value),
+            },
+            _ => CellValue::String(value),
+        }
+    }
+}
+
+impl From<String> for CellValue {
+    fn from(value: String) -> CellValue {
+        CellValue::String(value)
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
 pub struct Cell {
-    pub v: Option<String>,
+    pub v: Option<CellValue>,
     pub s: u32,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub t: Option<CellType>,
+    // value formatted with the cell's number format, only with WITH_FORMATTED_TEXT flag
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub w: Option<String>,
+    // color of the number format section used for `w`, like [Red]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text_color: Option<String>,
+    // text runs of rich strings, only with WITH_RICH_TEXT flag
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rich: Option<Vec<TextRun>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub hyperlink: Option<String>,
 }
 
+// cell with its position, `r` and `c` start from 0
+#[derive(Serialize, Deserialize)]
+pub struct SparseCell {
+    pub r: u32,
+    pub c: u32,
+    #[serde(flatten)]
+    pub cell: Cell,
+}
+
 impl Cell {
     pub fn new() -> Cell {
         Cell {
             v: None,
             s: 0,
+            t: None,
+            w: None,
+            text_color: None,
+            rich: None,
             hyperlink: None,
         }
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "type", content = "value")]
 pub enum DataValidationSource {
     List(Vec<String>),
     RangeReference(String),
 }
 
-#[derive(Debug, Serialize)]
+// names are the same as in xlsx, "none" allows any value
+// validations exported without type are lists
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum DataValidationType {
+    #[serde(rename = "none")]
+    Any,
+    Whole,
+    Decimal,
+    #[default]
+    List,
+    Date,
+    Time,
+    TextLength,
+    Custom,
+}
+
+impl DataValidationType {
+    // value of the `type` attribute of <dataValidation> tag
+    pub fn from_xlsx(value: &str) -> DataValidationType {
+        match value {
+            "whole" => DataValidationType::Whole,
+            "decimal" => DataValidationType::Decimal,
+            "list" => DataValidationType::List,
+            "date" => DataValidationType::Date,
+            "time" => DataValidationType::Time,
+            "textLength" => DataValidationType::TextLength,
+            "custom" => DataValidationType::Custom,
+            _ => DataValidationType::Any,
+        }
+    }
+    pub fn to_xlsx(self) -> &'static str {
+        match self {
+            DataValidationType::Any => "none",
+            DataValidationType::Whole => "whole",
+            DataValidationType::Decimal => "decimal",
+            DataValidationType::List => "list",
+            DataValidationType::Date => "date",
+            DataValidationType::Time => "time",
+            DataValidationType::TextLength => "textLength",
+            DataValidationType::Custom => "custom",
+        }
+    }
+    // whole, decimal, date, time and text length are compared with formulas by the operator
+    pub fn has_operator(self) -> bool {
+        !matches!(self, DataValidationType::Any | DataValidationType::List | DataValidationType::Custom)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum DataValidationOperator {
+    Between,
+    NotBetween,
+    Equal,
+    NotEqual,
+    LessThan,
+    LessThanOrEqual,
+    GreaterThan,
+    GreaterThanOrEqual,
+}
+
+impl DataValidationOperator {
+    // value of the `operator` attribute, "between" when it is missing
+    pub fn from_xlsx(value: &str) -> DataValidationOperator {
+        match value {
+            "notBetween" => DataValidationOperator::NotBetween,
+            "equal" => DataValidationOperator::Equal,
+            "notEqual" => DataValidationOperator::NotEqual,
+            "lessThan" => DataValidationOperator::LessThan,
+            "lessThanOrEqual" => DataValidationOperator::LessThanOrEqual,
+            "greaterThan" => DataValidationOperator::GreaterThan,
+            "greaterThanOrEqual" => DataValidationOperator::GreaterThanOrEqual,
+            _ => DataValidationOperator::Between,
+        }
+    }
+    pub fn to_xlsx(self) -> &'static str {
+        match self {
+            DataValidationOperator::Between => "between",
+            DataValidationOperator::NotBetween => "notBetween",
+            DataValidationOperator::Equal => "equal",
+            DataValidationOperator::NotEqual => "notEqual",
+            DataValidationOperator::LessThan => "lessThan",
+            DataValidationOperator::LessThanOrEqual => "lessThanOrEqual",
+            DataValidationOperator::GreaterThan => "greaterThan",
+            DataValidationOperator::GreaterThanOrEqual => "greaterThanOrEqual",
+        }
+    }
+}
+
+// how invalid values are rejected: stop doesn't allow them, warning and information only ask
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DataValidationErrorStyle {
+    #[default]
+    Stop,
+    Warning,
+    Information,
+}
+
+impl DataValidationErrorStyle {
+    pub fn from_xlsx(value: &str) -> DataValidationErrorStyle {
+        match value {
+            "warning" => DataValidationErrorStyle::Warning,
+            "information" => DataValidationErrorStyle::Information,
+            _ => DataValidationErrorStyle::Stop,
+        }
+    }
+    pub fn to_xlsx(self) -> &'static str {
+        match self {
+            DataValidationErrorStyle::Stop => "stop",
+            DataValidationErrorStyle::Warning => "warning",
+            DataValidationErrorStyle::Information => "information",
+        }
+    }
+}
+
+// flags are false and messages are None when they are not set in the file
+#[derive(Debug, Serialize, Deserialize)]
 pub struct DataValidation {
+    #[serde(default)]
+    pub range: String,
+    // `range` split into cell ranges, "A1:A5 C1" -> A1:A5 and C1:C1
+    #[serde(default)]
+    pub ranges: Vec<MergedCell>,
+    #[serde(rename = "type", default)]
+    pub kind: DataValidationType,
+    // only for types with operator
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operator: Option<DataValidationOperator>,
+    // values or reference of list validations
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<DataValidationSource>,
+    // values of the referenced cells for RangeReference source, only with WITH_VALIDATION_VALUES flag
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub values: Option<Vec<String>>,
+    // formulas as they are stored, second one is the upper bound of "between" and "notBetween"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub formula1: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub formula2: Option<String>,
+    #[serde(default)]
+    pub allow_blank: bool,
+    // `showDropDown` attribute, which hides the in-cell dropdown of lists despite its name
+    #[serde(default)]
+    pub hide_drop_down: bool,
+    #[serde(default)]
+    pub show_input_message: bool,
+    #[serde(default)]
+    pub show_error_message: bool,
+    #[serde(default)]
+    pub error_style: DataValidationErrorStyle,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl DataValidation {
+    pub fn new(range: String, kind: DataValidationType) -> DataValidation {
+        DataValidation {
+            ranges: range::sqref_ranges(&range),
+            range,
+            kind,
+            operator: None,
+            source: None,
+            values: None,
+            formula1: None,
+            formula2: None,
+            allow_blank: false,
+            hide_drop_down: false,
+            show_input_message: false,
+            show_error_message: false,
+            error_style: DataValidationErrorStyle::Stop,
+            prompt_title: None,
+            prompt: None,
+            error_title: None,
+            error: None,
+        }
+    }
+}
+
+// type of <cfRule>, rules of unknown types are skipped
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ConditionalFormatType {
+    Expression,
+    CellIs,
+    ColorScale,
+    DataBar,
+    IconSet,
+    Top10,
+    UniqueValues,
+    DuplicateValues,
+    ContainsText,
+    NotContainsText,
+    BeginsWith,
+    EndsWith,
+    ContainsBlanks,
+    NotContainsBlanks,
+    ContainsErrors,
+    NotContainsErrors,
+    TimePeriod,
+    AboveAverage,
+}
+
+impl ConditionalFormatType {
+    pub fn from_xlsx(value: &str) -> Option<ConditionalFormatType> {
+        match value {
+            "expression" => Some(ConditionalFormatType::Expression),
+            "cellIs" => Some(ConditionalFormatType::CellIs),
+            "colorScale" => Some(ConditionalFormatType::ColorScale),
+            "dataBar" => Some(ConditionalFormatType::DataBar),
+            "iconSet" => Some(ConditionalFormatType::IconSet),
+            "top10" => Some(ConditionalFormatType::Top10),
+            "uniqueValues" => Some(ConditionalFormatType::UniqueValues),
+            "duplicateValues" => Some(ConditionalFormatType::DuplicateValues),
+            "containsText" => Some(ConditionalFormatType::ContainsText),
+            "notContainsText" => Some(ConditionalFormatType::NotContainsText),
+            "beginsWith" => Some(ConditionalFormatType::BeginsWith),
+            "endsWith" => Some(ConditionalFormatType::EndsWith),
+            "containsBlanks" => Some(ConditionalFormatType::ContainsBlanks),
+            "notContainsBlanks" => Some(ConditionalFormatType::NotContainsBlanks),
+            "containsErrors" => Some(ConditionalFormatType::ContainsErrors),
+            "notContainsErrors" => Some(ConditionalFormatType::NotContainsErrors),
+            "timePeriod" => Some(ConditionalFormatType::TimePeriod),
+            "aboveAverage" => Some(ConditionalFormatType::AboveAverage),
+            _ => None,
+        }
+    }
+}
+
+// operator of "cellIs" rules and of the text rules
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ConditionalFormatOperator {
+    LessThan,
+    LessThanOrEqual,
+    Equal,
+    NotEqual,
+    GreaterThanOrEqual,
+    GreaterThan,
+    Between,
+    NotBetween,
+    ContainsText,
+    NotContains,
+    BeginsWith,
+    EndsWith,
+}
+
+impl ConditionalFormatOperator {
+    pub fn from_xlsx(value: &str) -> Option<ConditionalFormatOperator> {
+        match value {
+            "lessThan" => Some(ConditionalFormatOperator::LessThan),
+            "lessThanOrEqual" => Some(ConditionalFormatOperator::LessThanOrEqual),
+            "equal" => Some(ConditionalFormatOperator::Equal),
+            "notEqual" => Some(ConditionalFormatOperator::NotEqual),
+            "greaterThanOrEqual" => Some(ConditionalFormatOperator::GreaterThanOrEqual),
+            "greaterThan" => Some(ConditionalFormatOperator::GreaterThan),
+            "between" => Some(ConditionalFormatOperator::Between),
+            "notBetween" => Some(ConditionalFormatOperator::NotBetween),
+            "containsText" => Some(ConditionalFormatOperator::ContainsText),
+            "notContains" => Some(ConditionalFormatOperator::NotContains),
+            "beginsWith" => Some(ConditionalFormatOperator::BeginsWith),
+            "endsWith" => Some(ConditionalFormatOperator::EndsWith),
+            _ => None,
+        }
+    }
+}
+
+// type of <cfvo> threshold
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum ConditionalValueType {
+    #[default]
+    Num,
+    Percent,
+    Percentile,
+    Formula,
+    Min,
+    Max,
+    AutoMin,
+    AutoMax,
+}
+
+impl ConditionalValueType {
+    pub fn from_xlsx(value: &str) -> ConditionalValueType {
+        match value {
+            "percent" => ConditionalValueType::Percent,
+            "percentile" => ConditionalValueType::Percentile,
+            "formula" => ConditionalValueType::Formula,
+            "min" => ConditionalValueType::Min,
+            "max" => ConditionalValueType::Max,
+            "autoMin" => ConditionalValueType::AutoMin,
+            "autoMax" => ConditionalValueType::AutoMax,
+            _ => ConditionalValueType::Num,
+        }
+    }
+}
+
+// threshold of color scales, data bars and icon sets, `value` is a number or a formula
+// `gte` is false when the value itself belongs to the previous icon
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct ConditionalValue {
+    #[serde(rename = "type")]
+    pub kind: ConditionalValueType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    pub gte: bool,
+}
+
+impl ConditionalValue {
+    pub fn new(kind: ConditionalValueType) -> ConditionalValue {
+        ConditionalValue { kind, value: None, gte: true }
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct ColorScaleStop {
+    pub value: ConditionalValue,
+    // none for colors which can't be resolved, like auto ones
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+}
+
+// bar length is from `min_length` to `max_length` percents of the cell width
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct DataBar {
+    pub min: ConditionalValue,
+    pub max: ConditionalValue,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    pub show_value: bool,
+    pub min_length: u32,
+    pub max_length: u32,
+}
+
+// `name` of the icons, like "3TrafficLights1", one value per icon
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct IconSet {
+    pub name: String,
+    pub values: Vec<ConditionalValue>,
+    pub show_value: bool,
+    pub reverse: bool,
+    pub percent: bool,
+}
+
+// style of the matched cells is the differential style `dxf_id`, see `Workbook::differential_styles`
+// rules with lower `priority` are applied first, `stop_if_true` stops the rules with higher one
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct ConditionalFormatRule {
+    #[serde(rename = "type")]
+    pub kind: ConditionalFormatType,
+    pub priority: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dxf_id: Option<u32>,
+    pub stop_if_true: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub operator: Option<ConditionalFormatOperator>,
+    // <formula> elements, two of them for "between" and "notBetween"
+    pub formulas: Vec<String>,
+    // text of the text rules, period of "timePeriod" rules, like "last7Days"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_period: Option<String>,
+    // "top10" rules
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rank: Option<u32>,
+    pub percent: bool,
+    pub bottom: bool,
+    // "aboveAverage" rules
+    pub above_average: bool,
+    pub equal_average: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub std_dev: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color_scale: Option<Vec<ColorScaleStop>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_bar: Option<DataBar>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon_set: Option<IconSet>,
+}
+
+impl ConditionalFormatRule {
+    pub fn new(kind: ConditionalFormatType) -> ConditionalFormatRule {
+        ConditionalFormatRule {
+            kind,
+            priority: 0,
+            dxf_id: None,
+            stop_if_true: false,
+            operator: None,
+            formulas: vec![],
+            text: None,
+            time_period: None,
+            rank: None,
+            percent: false,
+            bottom: false,
+            above_average: true,
+            equal_average: false,
+            std_dev: None,
+            color_scale: None,
+            data_bar: None,
+            icon_set: None,
+        }
+    }
+}
+
+// rules of <conditionalFormatting>, `range` is the same as in `DataValidation`
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct ConditionalFormat {
     pub range: String,
-    pub source: DataValidationSource,
+    pub ranges: Vec<MergedCell>,
+    pub rules: Vec<ConditionalFormatRule>,
+}
+
+// named range or formula of the workbook, like print areas ("_xlnm.Print_Area")
+// `scope` is the name of the sheet for names local to it, None for global names
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct DefinedName {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    pub hidden: bool,
+    // formula without "=", like "Sheet1!$A$1:$B$5"
+    pub reference: String,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum SheetState {
+    Visible,
+    Hidden,
+    // can be shown only by a macro
+    VeryHidden,
+}
+
+impl SheetState {
+    // value of the `state` attribute of <sheet> tag
+    pub fn from_xlsx(state: &str) -> SheetState {
+        match state {
+            "hidden" => SheetState::Hidden,
+            "veryHidden" => SheetState::VeryHidden,
+            _ => SheetState::Visible,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SheetKind {
+    Worksheet,
+    Chartsheet,
+    Dialogsheet,
+    Macrosheet,
+}
+
+// sheet as it is listed in the workbook
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct SheetDescriptor {
+    pub name: String,
+    // sheetId of xlsx and xlsb, position in the workbook (from 1) for other formats
+    pub id: u32,
+    pub state: SheetState,
+    #[serde(rename = "type")]
+    pub kind: SheetKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tab_color: Option<String>,
 }
 
+impl SheetDescriptor {
+    pub fn new(name: String, id: u32) -> SheetDescriptor {
+        SheetDescriptor {
+            name,
+            id,
+            state: SheetState::Visible,
+            kind: SheetKind::Worksheet,
+            tab_color: None,
+        }
+    }
+    // chart and dialog sheets have no cells, they are read as empty sheets
+    pub fn has_cells(&self) -> bool {
+        self.kind == SheetKind::Worksheet || self.kind == SheetKind::Macrosheet
+    }
+}
 
-#[derive(Serialize)]
+
+// vectors can be omitted in the input of `write_xlsx`
+#[derive(Serialize, Deserialize)]
 pub struct SheetData {
     pub name: String,
+    #[serde(default)]
     pub cols: Vec<ColumnData>,
+    #[serde(default)]
     pub rows: Vec<RowData>,
+    #[serde(default)]
     pub cells: Vec<Vec<Option<Cell>>>,
+    // populated cells only, instead of `cells`, with WITH_SPARSE_CELLS flag
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sparse_cells: Option<Vec<SparseCell>>,
+    #[serde(default)]
     pub merged: Vec<MergedCell>,
+    #[serde(default)]
     pub validations: Vec<DataValidation>,
-    
+    // xlsx only, they are not written by `write_xlsx`
+    #[serde(default, skip_deserializing)]
+    pub conditional_formats: Vec<ConditionalFormat>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub frozen_cols: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -143,943 +730,132 @@ impl SheetData {
             cols: vec!(),
             rows: vec!(),
             cells: vec!(),
+            sparse_cells: None,
             merged: vec!(),
             frozen_cols: None,
             frozen_rows: None,
             validations: vec![],
+            conditional_formats: vec![],
         }
     }
 }
 
-struct SheetInfo {
-    cols_count: u32,
-    default_col_width: f32,
-    default_row_height: f32,
-    use_shared_string_for_next: bool,
+fn xml_reader_from<R: BufRead>(source: R) -> XmlReader<R> {
+    let mut r = XmlReader::from_reader(source);
+    r.check_end_names(false)
+        .trim_text(false)
+        .check_comments(false)
+        .expand_empty_elements(true);
+    r
 }
 
-impl SheetInfo {
-    pub fn new() -> SheetInfo {
-        SheetInfo {
-            cols_count: 0,
-            default_col_width: DEFAULT_CELL_WIDTH * WIDTH_COEF,
-            default_row_height: DEFAULT_CELL_HEIGHT / HEIGHT_COEF,
-            use_shared_string_for_next: false,
-        }
+// content of the opened file, shared by the archive and row cursors which read its parts later
+#[derive(Clone)]
+pub(crate) struct SharedBytes(Arc<Vec<u8>>);
+
+impl SharedBytes {
+    pub fn new(data: Vec<u8>) -> SharedBytes {
+        SharedBytes(Arc::new(data))
     }
 }
 
-#[wasm_bindgen]
-pub struct XLSX {
-    shared_strings: Vec<String>,
-    sheets: Vec<Sheet>,
-    zip: ZipArchive<Cursor<Vec<u8>>>,
+impl AsRef<[u8]> for SharedBytes {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
 }
 
-#[wasm_bindgen]
-impl XLSX {
-    pub fn new(data: Vec<u8>) -> XLSX {
-        utils::set_panic_hook();
-
-        let buf = Cursor::new(data);
-        let zip = ZipArchive::new(buf).unwrap();
-
-        let mut xlsx = XLSX {
-            shared_strings: vec!(),
-            sheets: vec!(),
-            zip
-        };
-
-        let rels = xlsx.read_relationships().unwrap();
-        xlsx.read_workbook(&rels).unwrap();
-        match xlsx.read_shared_strings() {
-            Ok(_) => (),
-            Err(_) => ()
-        }
+pub(crate) type Archive = ZipArchive<Cursor<SharedBytes>>;
 
-        xlsx
-    }
-    pub fn with_formulas() -> u32{
-        return WITH_FORMULAS;
-    }
-    pub fn get_styles(&mut self) -> JsValue {
-        let styles = self.read_style().unwrap();
-        JsValue::from_serde(&styles).unwrap()
-    }
-    pub fn get_sheets(&self) -> Vec<JsValue> {
-        self.sheets.clone().iter().map(|s| JsValue::from(&s.0)).collect()
+fn xml_reader<'a>(zip: &'a mut Archive, path: &str) -> Option<Result<XlsReader<'a>, XlsxError>> {
+    match zip.by_name(path) {
+        Ok(f) => Some(Ok(xml_reader_from(BufReader::new(f)))),
+        Err(ZipError::FileNotFound) => None,
+        Err(e) => Some(Err(XlsxError::BadZip { reason: e.to_string() })),
     }
-    pub fn get_sheet_data(&mut self, sheet_name: String, flags: u32) -> JsValue {
-        let (name, path) = self.sheets.iter().find(|(name, _)| name == &sheet_name).unwrap().clone();
-        let data = self.read_sheet(path, name, flags).unwrap();
+}
 
-        JsValue::from_serde(&data).unwrap()
-    }
-    fn read_shared_strings(&mut self) -> Result<(), XlsxError> {
-        let mut xml = match xml_reader(&mut self.zip, "xl/sharedStrings.xml") {
-            None => return Ok(()),
-            Some(x) => x?,
-        };
-        let mut buf = Vec::new();
-        
-        let mut xml_path = SharedStringXMLPath::Any;
-        let mut temp:String = "".to_string();
-        let mut ignore = false;
-
-        loop {
-            buf.clear();
-            let t = xml.read_event_into(&mut buf);
-            match t {
-                Ok(Event::Start(ref e)) if e.name().as_ref() == b"si" => {
-                    xml_path = SharedStringXMLPath::Si;
-                }
-                Ok(Event::Start(ref e)) if e.name().as_ref() == b"t" && xml_path == SharedStringXMLPath::Si && ignore == false => {
-                    xml_path = SharedStringXMLPath::T;
-                }
-                Ok(Event::Start(ref e)) if xml_path == SharedStringXMLPath::Si && ( e.name().as_ref() == b"rPh" || e.name().as_ref() == b"phoneticPr") => {
-                    ignore = true
-                }
-                Ok(Event::End(ref e)) if xml_path == SharedStringXMLPath::Si && ( e.name().as_ref() == b"rPh" || e.name().as_ref() == b"phoneticPr") => {
-                    ignore = false
-                }
-                Ok(Event::Text(ref e)) if xml_path == SharedStringXMLPath::T => {
-                    let value = e.unescape().unwrap();
-                    temp.push_str(value.as_ref());
-                }
-                Ok(Event::End(ref e)) if e.name().as_ref() == b"t" && xml_path == SharedStringXMLPath::T && ignore == false => {
-                    xml_path = SharedStringXMLPath::Si;
-                },
-                Ok(Event::End(ref e)) if e.name().as_ref() == b"si" => {
-                    self.shared_strings.push(temp);
-                    temp = "".to_string();
-                    xml_path = SharedStringXMLPath::Any;
-                },
-                Ok(Event::End(ref e)) if e.name().as_ref() == b"sst" => break,
-                Err(_) => return Err(XlsxError::Default),
-                _ => (),
-            }
-        }
-        Ok(())
+fn xml_error<R>(part: &str, xml: &XmlReader<R>, reason: impl ToString) -> XlsxError {
+    XlsxError::MalformedXml {
+        part: part.to_string(),
+        offset: xml.buffer_position(),
+        reason: reason.to_string(),
     }
-    fn read_sheet(&mut self, path: String, sheet_name: String, flags: u32) -> Result<SheetData, XlsxError> {
-        let sheet_rel_path = path
-            .trim_start_matches("xl/worksheets/")
-            .trim_end_matches(".xml");
-        
-        let hyperlinks_map = Self::read_sheet_relationships(&mut self.zip, sheet_rel_path)?;
-        dbg!(&hyperlinks_map);
-        let mut xml = match xml_reader(&mut self.zip, &path) {
-            None => {
-                return Err(XlsxError::FileNotFound(path))
-            }
-            Some(x) => x?,
-        };
-        let mut buf = Vec::new();
-
-        let mut data = SheetData::new(sheet_name);
-        let mut info = SheetInfo::new();
-
-        let mut last_cell = Cell::new();
-        let mut mode = 0u8;
-
-        
-        let mut hyperlinks = HashMap::<String, String>::new();
-        let mut current_cell_name: Option<String> = None;
-
-        loop {
-            buf.clear();
-            match xml.read_event_into(&mut buf) {
-                Ok(Event::Start(ref e)) if e.name().as_ref() == b"sheetFormatPr" => {
-                    for a in e.attributes() {
-                        let att = a.unwrap();
-                        match att.key.as_ref() {
-                            b"tdefaultRowHeight" => {
-                                info.default_row_height = att.decode_and_unescape_value(&xml).unwrap().parse::<f32>().unwrap() / HEIGHT_COEF;
-                            },
-                            b"defaultColWidth" => {
-                                info.default_col_width = att.decode_and_unescape_value(&xml).unwrap().parse::<f32>().unwrap() * WIDTH_COEF;
-                            },
-                            _ => ()
-                        }
-                    }
-                },
-                Ok(Event::Start(ref e)) if e.name().as_ref() == b"col" => {
-                    let mut min = 0;
-                    let mut max = 0;
-                    let mut width = 0.0;
-                    let mut use_custom_width = false;
-                    let mut hidden: Option<bool> = None;
-
-                    for a in e.attributes() {
-                        let att = a.unwrap();
-                        match att.key.as_ref() {
-                            b"width" => {
-                                width = att.decode_and_unescape_value(&xml).unwrap().parse::<f32>().unwrap() * WIDTH_COEF;
-                            },
-                            b"min" => {
-                                min = att.decode_and_unescape_value(&xml).unwrap().parse::<usize>().unwrap();
-                            },
-                            b"max" => {
-                                max = att.decode_and_unescape_value(&xml).unwrap().parse::<usize>().unwrap();
-                            },
-                            b"customWidth" => {
-                                let v = att.decode_and_unescape_value(&xml).unwrap();
-                                use_custom_width = v == "1" || v == "true";
-                            },
-                            b"hidden" => {
-                                let v = att.decode_and_unescape_value(&xml).unwrap();
-                                hidden = Some(v == "1" || v == "true");
-                            },
-                            _ => ()
-                        }
-                    }
-                    if use_custom_width {
-                        for i in data.cols.len()..max {
-                            if i >= min-1 {
-                                data.cols.push(ColumnData {width, hidden});
-                            } else {
-                                data.cols.push(ColumnData {width: info.default_col_width, hidden: None});
-                            }
-                        }
-                    }
-                },
-                Ok(Event::Start(ref e)) if e.name().as_ref() == b"row" => {
-                    let mut use_custom_height = false;
-                    let mut height = 0.0;
-                    let mut index = 0;
-                    let mut hidden: Option<bool> = None;
-
-                    for a in e.attributes() {
-                        let att = a.unwrap();
-                        match att.key.as_ref() {
-                            b"ht" => {
-                                height = att.decode_and_unescape_value(&xml).unwrap().parse::<f32>().unwrap() / HEIGHT_COEF;
-                            },
-                            b"customHeight" => {
-                                let v = att.decode_and_unescape_value(&xml).unwrap();
-                                use_custom_height = v == "1" || v == "true";
-                            },
-                            b"r" => {
-                                index = att.decode_and_unescape_value(&xml).unwrap().parse::<usize>().unwrap();
-                            },
-                            b"hidden" => {
-                                let v = att.decode_and_unescape_value(&xml).unwrap();
-                                hidden = Some(v == "1" || v == "true");
-                            },
-                            _ => ()
-                        }
-                    }
-                    for _ in data.cells.len()..index {
-                        data.cells.push(vec!());
-                    }
-                    for _ in data.rows.len()..index-1 {
-                        data.rows.push(RowData {height: info.default_row_height, hidden: None});
-                    }
-                    let final_height = (height > 0.0).then(|| height).unwrap_or(info.default_row_height);
-                    data.rows.push(RowData { height: final_height, hidden });
-                },
-                Ok(Event::Start(ref e)) if e.name().as_ref() == b"c" => {
-                    info.use_shared_string_for_next = false;
-
-                    for a in e.attributes() {
-                        let att = a.unwrap();
-                        match att.key.as_ref() {
-                            b"t" => {
-                                if att.decode_and_unescape_value(&xml).unwrap() == "s" {
-                                    info.use_shared_string_for_next = true;
-                                }
-                            },
-                            b"s" => {
-                                let value = att.decode_and_unescape_value(&xml).unwrap().parse::<u32>().unwrap();
-                                last_cell.s = value;
-                            },
-                            b"r" => {
-                                let cell_name = att.decode_and_unescape_value(&xml).unwrap().to_string();
-                                let (col, _) = cell_index_to_offsets(cell_name.clone());
-                                current_cell_name = Some(cell_name.clone());
-
-                                let cols = data.cells.last_mut().unwrap();
-
-                                for _ in cols.len()..col as usize {
-                                    cols.push(None);
-                                }
-
-                                if col + 1 > info.cols_count {
-                                    info.cols_count = col + 1;
-                                }
-                            },
-                            _ => ()
-                        }
-                    }
-                },
-                Ok(Event::End(ref e)) if e.name().as_ref() == b"c" => {
-                    let has_value = last_cell.v.is_some() || last_cell.s > 0;
-                    let has_hyperlink = current_cell_name
-                        .as_ref()
-                        .map(|name| hyperlinks.contains_key(name))
-                        .unwrap_or(false);
-
-                    if has_value || has_hyperlink {
-    
-                        if let Some(ref name) = current_cell_name {
-                            if let Some(link) = hyperlinks.get(name) {
-                                last_cell.hyperlink = Some(link.clone());
-                            }
-                        }
-                        data.cells.last_mut().unwrap().push(Some(last_cell));
-                    }
-                    last_cell = Cell::new();
-                    current_cell_name = None;
-                },
-                Ok(Event::Start(ref e)) if e.name().as_ref() == b"f" => {
-                    mode = 1
-                }
-                Ok(Event::Start(ref e)) if e.name().as_ref() == b"v" => {
-                    mode = 2
-                },
-                Ok(Event::Text(ref e)) if mode == 1 => {
-                    if flags & WITH_FORMULAS > 0 {
-                        let value = e.unescape().unwrap().to_string();
-                        last_cell.v = Some("=".to_owned() + &value);
-                    }
-                }
-                Ok(Event::Text(ref e)) if mode == 2 => {
-                    if last_cell.v.is_none(){
-                        let value = e.unescape().unwrap().to_string();
-                        if info.use_shared_string_for_next {
-                            let index: usize = value.parse().unwrap();
-                            if self.shared_strings[index].len() > 0 {
-                                last_cell.v = Some(self.shared_strings[index].to_owned());
-                            }
-                        } else {
-                            last_cell.v = Some(value);
-                        }
-                    }
-                },
-                Ok(Event::End(ref e)) if e.name().as_ref() == b"f" => {
-                    mode = 0
-                }
-                Ok(Event::End(ref e)) if e.name().as_ref() == b"v" => {
-                    mode = 0
-                },
-                Ok(Event::Start(ref e)) if e.name().as_ref() == b"mergeCell" => {
-                    for a in e.attributes() {
-                        let att = a.unwrap();
-                        match att.key.as_ref() {
-                            b"ref" => {
-                                let raw_merged_cell = att.decode_and_unescape_value(&xml).unwrap();
-                                let range = Range::new(raw_merged_cell.into());
-
-                                let from = range.first;
-                                let to = range.last;
-
-                                let merged_cell = MergedCell {
-                                    from: CellCoords { column: from.0, row: from.1 },
-                                    to: CellCoords { column: to.0, row: to.1 }
-                                };
-                                data.merged.push(merged_cell);
-                            },
-                            _ => ()
-                        }
-                    }
-                },
-                Ok(Event::End(ref e)) if e.name().as_ref() == b"worksheet" => {
-                    loop {
-                        match data.cells.last() {
-                            Some(last) => {
-                                if last.len() == 0 {
-                                    data.cells.pop();
-                                } else {
-                                    break;
-                                }
-                            },
-                            None => break
-                        }
-                    }
-                    let rows_count = data.cells.len();
-                    for row in data.cells.iter_mut() {
-                        let missed_cols_count = info.cols_count as usize - row.len();
-                        if missed_cols_count != 0 {
-                            row.extend((0..missed_cols_count).map(|_| None));
-                        }
-                    }
-                    let missed_row_data_count = rows_count as i32 - data.rows.len() as i32;
-                    if missed_row_data_count < 0 {
-                        data.rows = data.rows.into_iter().take(rows_count).collect();
-                    } else {
-                        data.rows.extend((0..missed_row_data_count).map(|_| RowData {height: info.default_row_height, hidden: None}));
-                    }
-                    let missed_col_data_count = info.cols_count as i32 - data.cols.len() as i32;
-                    if missed_col_data_count < 0 {
-                        data.cols = data.cols.into_iter().take(info.cols_count as usize).collect();
-                    } else {
-                        data.cols.extend((0..missed_col_data_count).map(|_| ColumnData {width: info.default_col_width, hidden: None}));
-                    }
-                    for (cell_name, link) in hyperlinks {
-                        let (col, row) = cell_index_to_offsets(cell_name);
-                        if let Some(Some(cell)) = data.cells.get_mut(row as usize).and_then(|r| r.get_mut(col as usize)) {
-                            cell.hyperlink = Some(link);
-                        }
-                    }
-                    return Ok(data);
-                },
-                Ok(Event::Start(ref e)) if e.name().as_ref() == b"pane" => {
-                    for a in e.attributes() {
-                        if let Ok(att) = a {
-                            match att.key.as_ref() {
-                                b"xSplit" => {
-                                    if let Ok(val) = att.decode_and_unescape_value(&xml) {
-                                        if let Ok(n) = val.parse::<f64>() {
-                                            data.frozen_cols = Some(n as u32);
-                                        } else {
-                                        }
-                                    }
-                                },
-                                b"ySplit" => {
-                                    if let Ok(val) = att.decode_and_unescape_value(&xml) {
-                                        if let Ok(n) = val.parse::<f64>() {
-                                            data.frozen_rows = Some(n as u32);
-                                        } else {
-                                        }
-                                    }
-                                },
-                                _ => (),
-                            }
-                        }
-                    }
-                },
-                Ok(Event::Start(ref e)) if e.name().as_ref() == b"dataValidation" => {
-                    let mut range: Option<String> = None;
-                    let mut formula1: Option<String> = None;
-
-                    for a in e.attributes().flatten() {
-                        if a.key.as_ref() == b"sqref" {
-                            range = Some(a.decode_and_unescape_value(&xml).unwrap().to_string());
-                        }
-                    }
-
-                    let mut inner_buf = Vec::new();
-                    loop {
-                        inner_buf.clear();
-                        match xml.read_event_into(&mut inner_buf) {
-                            Ok(Event::Start(ref inner)) if inner.name().as_ref() == b"formula1" => {},
-                            Ok(Event::Text(t)) => {
-                                formula1 = Some(t.unescape().unwrap().to_string());
-                            },
-                            Ok(Event::End(ref inner)) if inner.name().as_ref() == b"dataValidation" => break,
-                            Err(_) => return Err(XlsxError::Default),
-                            _ => ()
-                        }
-                    }
-
-                    if let (Some(range), Some(formula)) = (range, formula1) {
-                        let source = if formula.starts_with('"') && formula.ends_with('"') {
-                            let list_raw = &formula[1..formula.len()-1];
-                            let list = list_raw.split(',').map(|s| s.trim().to_string()).collect();
-                            DataValidationSource::List(list)
-                        } else {
-                            DataValidationSource::RangeReference(formula)
-                        };
-
-                        data.validations.push(DataValidation {
-                            range,
-                            source,
-                        });
-                    }
-                },
-                Ok(Event::Start(ref e)) if e.name().as_ref() == b"hyperlink" => {
-                    let mut cell_ref = None;
-                    let mut rel_id = None;
-
-                    for a in e.attributes() {
-                        let att = a.unwrap();
-                        match att.key.as_ref() {
-                            b"ref" => {
-                                cell_ref = Some(att.decode_and_unescape_value(&xml).unwrap().to_string());
-                            },
-                            b"r:id" => {
-                                rel_id = Some(att.decode_and_unescape_value(&xml).unwrap().into_owned());
-                            },
-                            _ => (),
-                        }
-                    }
-
-                    if let (Some(cell), Some(id)) = (cell_ref, rel_id) {
-                        if let Some(link) = hyperlinks_map.get(&id) {
-                            hyperlinks.insert(cell.clone(), link.clone());
-                        }
-                    }
-                }                
-                Err(_) => return Err(XlsxError::Default),
-                _ => ()
-            }
-        }
+}
+
+fn invalid_attribute(part: &str, attribute: &str, value: &str) -> XlsxError {
+    XlsxError::InvalidAttribute {
+        part: part.to_string(),
+        attribute: attribute.to_string(),
+        value: value.to_string(),
     }
+}
 
-    fn read_sheet_relationships(
-        zip: &mut ZipArchive<Cursor<Vec<u8>>>,
-        sheet_path: &str,
-    ) -> Result<HashMap<String, String>, XlsxError> {
-        let rel_path = format!("xl/worksheets/_rels/{}.xml.rels", sheet_path); // ✅ фикс
-        let mut xml = match xml_reader(zip, &rel_path) {
-            Some(Ok(x)) => x,
-            _ => return Ok(HashMap::new()),
-        };
-
-        let mut map = HashMap::new();
-        let mut buf = Vec::new();
-
-        loop {
-            buf.clear();
-            match xml.read_event_into(&mut buf) {
-                Ok(Event::Start(ref e)) if e.name().as_ref() == b"Relationship" => {
-                    let mut id = String::new();
-                    let mut target = String::new();
-
-                    for a in e.attributes().flatten() {
-                        match a.key.as_ref() {
-                            b"Id" => id = a.unescape_value().unwrap().into_owned(),
-                            b"Target" => target = a.unescape_value().unwrap().into_owned(),
-                            _ => (),
-                        }
-                    }
-
-                    if !id.is_empty() && !target.is_empty() {
-                        map.insert(id, target);
-                    }
-                }
-                Ok(Event::End(ref e)) if e.name().as_ref() == b"Relationships" => break,
-                Err(_) => return Err(XlsxError::Default),
-                _ => (),
-            }
-        }
+fn attr_value<R>(xml: &XmlReader<R>, part: &str, att: &Attribute) -> Result<String, XlsxError> {
+    att.decode_and_unescape_value(xml)
+        .map(|v| v.into_owned())
+        .map_err(|e| xml_error(part, xml, e))
+}
 
-        Ok(map)
-    }
-    fn read_relationships(&mut self) -> Result<HashMap<Vec<u8>, String>, XlsxError> {
-        let mut xml = match xml_reader(&mut self.zip, "xl/_rels/workbook.xml.rels") {
-            None => {
-                return Err(XlsxError::FileNotFound(
-                    String::from("xl/_rels/workbook.xml.rels"),
-                ))
-            },
-            Some(x) => x?,
-        };
-        let mut relationships = HashMap::new();
-        let mut buf = Vec::new();
-        loop {
-            buf.clear();
-            match xml.read_event_into(&mut buf) {
-                Ok(Event::Start(ref e)) if e.name().as_ref() == b"Relationship" => {
-                    let mut id = Vec::new();
-                    let mut target = String::new();
-                    for a in e.attributes() {
-                        let att = a.unwrap();
-                        match att.key.as_ref() {
-                            b"Id" => {
-                                id.extend_from_slice(att.value.as_ref());
-                            },
-                            b"Target" => {
-                                target = att.decode_and_unescape_value(&xml).unwrap().into();
-                            },
-                            _ => (),
-                        }
-                    }
-                    relationships.insert(id, target);
-                }
-                Ok(Event::End(ref e)) if e.name().as_ref() == b"Relationships" => break,
-                Err(_) => return Err(XlsxError::Default),
-                _ => (),
-            }
-        }
-        Ok(relationships)
-    }
+fn attr_parse<T: FromStr, R>(xml: &XmlReader<R>, part: &str, att: &Attribute) -> Result<T, XlsxError> {
+    let value = attr_value(xml, part, att)?;
+    value.trim().parse::<T>().map_err(|_| {
+        invalid_attribute(part, &String::from_utf8_lossy(att.key.as_ref()), &value)
+    })
+}
 
-    fn read_workbook(&mut self, relationships: &HashMap<Vec<u8>, String>) -> Result<(), XlsxError> {
-        let mut xml = match xml_reader(&mut self.zip, "xl/workbook.xml") {
-            None => {
-                return Err(XlsxError::FileNotFound(
-                    String::from("xl/workbook.xml")
-                ))
-            },
-            Some(x) => x?,
-        };
-        let mut buf = Vec::new();
-        loop {
-            buf.clear();
-            match xml.read_event_into(&mut buf) {
-                Ok(Event::Start(ref e)) if e.name().as_ref() == b"sheet" => {
-                    let mut name = String::new();
-                    let mut path = String::new();
-                    for a in e.attributes() {
-                        let att = a.unwrap();
-                        match att.key.as_ref() {
-                            b"name" => {
-                                name = att.decode_and_unescape_value(&xml).unwrap().into();
-                            },
-                            b"r:id" => {
-                                let r = &relationships[&*att.value][..];
-                                path = if r.starts_with("/xl/") {
-                                    r[1..].to_string()
-                                } else if r.starts_with("xl/") {
-                                    r.to_string()
-                                } else {
-                                    format!("xl/{}", r)
-                                };
-                            }
-                            _ => ()
-                        }
-                    }
-                    self.sheets.push((name, path));
-                },
-                Ok(Event::End(ref e)) if e.name().as_ref() == b"workbook" => break,
-                Err(_) => return Err(XlsxError::Default),
-                _ => (),
-            }
-        }
-        Ok(())
+// style part referenced by index, like fontId or fillId
+fn lookup<R>(list: &[Dict], xml: &XmlReader<R>, part: &str, att: &Attribute) -> Result<Dict, XlsxError> {
+    let index = attr_parse::<usize, _>(xml, part, att)?;
+    match list.get(index) {
+        Some(v) => Ok(v.clone()),
+        None => Err(invalid_attribute(part, &String::from_utf8_lossy(att.key.as_ref()), &index.to_string())),
     }
+}
 
-    fn read_style(&mut self) -> Result<Vec<Dict>, XlsxError> {
-        let mut xml = match xml_reader(&mut self.zip, "xl/styles.xml") {
-            None => {
-                return Err(XlsxError::FileNotFound(
-                    String::from("xl/styles.xml")
-                ))
+// color of <color>, <fgColor> and similar tags, given by rgb, indexed or theme attribute
+fn read_color<R>(xml: &XmlReader<R>, part: &str, e: &BytesStart, theme: &Theme) -> Result<Option<String>, XlsxError> {
+    let mut color = None;
+    let mut theme_index = None;
+    let mut tint = 0.0;
+    for a in e.attributes() {
+        let att = a.map_err(|e| xml_error(part, xml, e))?;
+        match att.key.as_ref() {
+            b"rgb" => {
+                let value = attr_value(xml, part, &att)?;
+                color = Some(get_xlsx_rgb(&value).ok_or_else(|| invalid_attribute(part, "rgb", &value))?);
+            },
+            b"indexed" => {
+                color = Some(theme.indexed_color(&attr_value(xml, part, &att)?));
+            },
+            b"theme" => {
+                theme_index = Some(attr_parse::<usize, _>(xml, part, &att)?);
             },
-            Some(x) => x?,
-        };
-        let mut buf = Vec::new();
-
-        let mut xml_path: StyleXMLPath = StyleXMLPath::Any;
-        let mut xml_parent_path: StyleXMLPath = StyleXMLPath::Any;
-
-        let mut fonts: Vec<Dict> = vec!();
-        let mut fills: Vec<Dict> = vec!();
-        let mut borders: Vec<Dict> = vec!();
-        let mut border_structs: Vec<Border> = vec!();
-
-        let mut extra_formats: Dict = HashMap::new();
-
-        let mut styles: Vec<Dict> = vec!();
-
-        loop {
-            buf.clear();
-            match xml.read_event_into(&mut buf) {
-                Ok(Event::Start(ref e)) if xml_parent_path == StyleXMLPath::CellXfs && e.name().as_ref() == b"xf"  => {
-                    xml_path = StyleXMLPath::Xf;
-                    let mut xf = HashMap::new();
-                    for a in e.attributes() {
-                        let att = a.unwrap();
-                        match att.key.as_ref() {
-                            b"fontId" => {
-                                let value = att.decode_and_unescape_value(&xml).unwrap();
-                                let index: usize = value.parse().unwrap();
-                                let font_style: Dict = fonts[index].clone();
-
-                                xf.extend(font_style);
-                            },
-                            b"borderId" => {
-                                let value = att.decode_and_unescape_value(&xml).unwrap();
-                                let index: usize = value.parse().unwrap();
-                                let border_style: Dict = borders[index].clone();
-
-                                xf.extend(border_style);
-                            },
-                            b"fillId" => {
-                                let value = att.decode_and_unescape_value(&xml).unwrap();
-                                let index: usize = value.parse().unwrap();
-                                let fill_style: Dict = fills[index].clone();
-
-                                xf.extend(fill_style);
-                            },
-                            b"numFmtId" => {
-                                let format_id = att.decode_and_unescape_value(&xml).unwrap();
-                                let format = match get_format(&format_id) {
-                                    Some(v) => JsonValue::String(v),
-                                    None => extra_formats.get(format_id.as_ref()).unwrap().to_owned()
-                                };
-                                xf.insert(String::from("format"), format);
-                            },
-                            _ => ()
-                        }
-                    }
-                    styles.push(xf);
-                },
-                Ok(Event::Start(ref e)) if xml_path == StyleXMLPath::Xf && xml_parent_path == StyleXMLPath::CellXfs && e.name().as_ref() == b"alignment" => {
-                    let xf = styles.last_mut().unwrap();
-                    for a in e.attributes() {
-                        let att = a.unwrap();
-                        match att.key.as_ref() {
-                            b"vertical" => {
-                                let value = att.decode_and_unescape_value(&xml).unwrap();
-                                xf.insert(String::from("verticalAlign"), value.into());
-                            },
-                            b"horizontal" => {
-                                let value = att.decode_and_unescape_value(&xml).unwrap();
-                                xf.insert(String::from("align"), value.into());
-                            },
-                            b"wrapText" => {
-                                let value = att.decode_and_unescape_value(&xml).unwrap();
-                                if value == "1" || value == "true" {
-                                    xf.insert(String::from("wrapText"), JsonValue::Bool(true));
-                                }
-                            },
-                            _ => ()
-                        }
-                    }
-                },
-                Ok(Event::Start(ref e)) if e.name().as_ref() == b"numFmt" => {
-                    let mut format_code = String::from("");
-                    let mut format_id = String::from("");
-                    for a in e.attributes() {
-                        let att = a.unwrap();
-                        match att.key.as_ref() {
-                            b"formatCode" => {
-                                format_code = att.decode_and_unescape_value(&xml).unwrap().into();
-                            },
-                            b"numFmtId" => {
-                                format_id = att.decode_and_unescape_value(&xml).unwrap().into();
-                            },
-                            _ => ()
-                        }
-                    }
-                    extra_formats.insert(format_id, JsonValue::String(format_code));
-                },
-                Ok(Event::Start(ref e)) if e.name().as_ref() == b"font" => {
-                    xml_path = StyleXMLPath::Font;
-                    fonts.push(HashMap::new());
-                },
-                Ok(Event::Start(ref e)) if e.name().as_ref() == b"fill" => {
-                    xml_path = StyleXMLPath::Fill;
-                    fills.push(HashMap::new());
-                },
-                Ok(Event::Start(ref e)) if e.name().as_ref() == b"border" => {
-                    xml_path = StyleXMLPath::Border;
-                },
-                Ok(Event::Start(ref e)) if e.name().as_ref() == b"cellXfs" => {
-                    xml_parent_path = StyleXMLPath::CellXfs;
-                },
-                // font styles
-                Ok(Event::Start(ref e)) if xml_path == StyleXMLPath::Font && e.name().as_ref() == b"sz"  => {
-                    for a in e.attributes() {
-                        let att = a.unwrap();
-                        match att.key.as_ref() {
-                            b"val" => {
-                                let font = fonts.last_mut().unwrap();
-                                let value = att.decode_and_unescape_value(&xml).unwrap().parse::<f32>().unwrap();
-                                font.insert(String::from("fontSize"), JsonValue::String((value / PT_COEF).to_string() + "px"));
-                            },
-                            _ => ()
-                        }
-                    }
-                },
-                Ok(Event::Start(ref e)) if xml_path == StyleXMLPath::Font && e.name().as_ref() ==  b"name" => {
-                    for a in e.attributes() {
-                        let att = a.unwrap();
-                        match att.key.as_ref() {
-                            b"val" => {
-                                let font = fonts.last_mut().unwrap();
-                                let value = att.decode_and_unescape_value(&xml).unwrap();
-                                font.insert(String::from("fontFamily"), JsonValue::String(value.into()));
-                            },
-                            _ => ()
-                        }
-                    } 
-                },
-                Ok(Event::Start(ref e)) if xml_path == StyleXMLPath::Font && e.name().as_ref() == b"color" => {
-                    let font = fonts.last_mut().unwrap();
-                    for a in e.attributes() {
-                        let att = a.unwrap();
-                        match att.key.as_ref() {
-                            b"rgb" => {
-                                let value = att.decode_and_unescape_value(&xml).unwrap();
-                                font.insert(String::from("color"), JsonValue::String(get_xlsx_rgb(value.into())));
-                            },
-                            b"indexed" => {
-                                let value = att.decode_and_unescape_value(&xml).unwrap();
-                                font.insert(String::from("color"), JsonValue::String(get_indexed_color(&value)));
-                            },
-                            _ => ()
-                        }
-                    }
-                },
-                Ok(Event::Start(ref e)) if xml_path == StyleXMLPath::Font && e.name().as_ref() == b"b"  => {
-                    let font = fonts.last_mut().unwrap();
-                    font.insert(String::from("fontWeight"), JsonValue::String(String::from("bold")));
-                },
-                Ok(Event::Start(ref e)) if xml_path == StyleXMLPath::Font && e.name().as_ref() == b"i" => {
-                    let font = fonts.last_mut().unwrap();
-                    font.insert(String::from("fontStyle"), JsonValue::String(String::from("italic")));
-                },
-                Ok(Event::Start(ref e)) if xml_path == StyleXMLPath::Font && e.name().as_ref() == b"u" => {
-                    let font = fonts.last_mut().unwrap();
-                    if font.contains_key("textDecoration") {
-                        font.insert(String::from("textDecoration"), JsonValue::String(String::from("line-through underline")));
-                    } else {
-                        font.insert(String::from("textDecoration"), JsonValue::String(String::from("underline")));
-                    }
-                },
-                Ok(Event::Start(ref e)) if xml_path == StyleXMLPath::Font && e.name().as_ref() == b"strike"  => {
-                    let font = fonts.last_mut().unwrap();
-                    if font.contains_key("textDecoration") {
-                        font.insert(String::from("textDecoration"), JsonValue::String(String::from("line-through underline")));
-                    } else {
-                        font.insert(String::from("textDecoration"), JsonValue::String(String::from("line-through")));
-                    }
-                },
-                // borders styles
-                Ok(Event::Start(ref e)) if xml_path == StyleXMLPath::Border && e.name().as_ref() == b"left" => {
-                    let mut border = Border::new(BorderPosition::Left);
-
-                    for a in e.attributes() {
-                        let att = a.unwrap();
-                        match att.key.as_ref() {
-                            b"style" => {
-                                let value = att.decode_and_unescape_value(&xml).unwrap();
-                                border.set_style(value.into());
-                            },
-                            _ => ()
-                        }
-                    }
-                    border_structs.push(border);
-                },
-                Ok(Event::Start(ref e)) if xml_path == StyleXMLPath::Border && e.name().as_ref() == b"right" => {
-                    let mut border = Border::new(BorderPosition::Right);
-
-                    for a in e.attributes() {
-                        let att = a.unwrap();
-                        match att.key.as_ref() {
-                            b"style" => {
-                                let value = att.decode_and_unescape_value(&xml).unwrap();
-                                border.set_style(value.into());
-                            },
-                            _ => ()
-                        }
-                    }
-                    border_structs.push(border);
-                },
-                Ok(Event::Start(ref e)) if xml_path == StyleXMLPath::Border && e.name().as_ref() == b"bottom" => {
-                    let mut border = Border::new(BorderPosition::Bottom);
-
-                    for a in e.attributes() {
-                        let att = a.unwrap();
-                        match att.key.as_ref() {
-                            b"style" => {
-                                let value = att.decode_and_unescape_value(&xml).unwrap();
-                                border.set_style(value.into());
-                            },
-                            _ => ()
-                        }
-                    }
-                    border_structs.push(border);
-                },
-                Ok(Event::Start(ref e)) if xml_path == StyleXMLPath::Border && e.name().as_ref() == b"top" => {
-                    let mut border = Border::new(BorderPosition::Top);
-
-                    for a in e.attributes() {
-                        let att = a.unwrap();
-                        match att.key.as_ref() {
-                            b"style" => {
-                                let value = att.decode_and_unescape_value(&xml).unwrap();
-                                border.set_style(value.into());
-                            },
-                            _ => ()
-                        }
-                    }
-                    border_structs.push(border);
-                },
-                Ok(Event::Start(ref e)) if xml_path == StyleXMLPath::Border && e.name().as_ref() == b"color" => {
-                    let border = border_structs.last_mut().unwrap();
-                    for a in e.attributes() {
-                        let att = a.unwrap();
-                        match att.key.as_ref() {
-                            b"rgb" => {
-                                let value = att.decode_and_unescape_value(&xml).unwrap();
-                                border.set_color(get_xlsx_rgb(value.into()));
-                            },
-                            b"indexed" => {
-                                let value = att.decode_and_unescape_value(&xml).unwrap();
-                                border.set_color(get_indexed_color(&value));
-                            },
-                            _ => ()
-                        }
-                    }
-                },
-                // fills
-                Ok(Event::Start(ref e)) if xml_path == StyleXMLPath::Fill && e.name().as_ref() == b"fgColor" => {
-                    let fill = fills.last_mut().unwrap();
-                    for a in e.attributes() {
-                        let att = a.unwrap();
-                        match att.key.as_ref() {
-                            b"rgb" => {
-                                let value = att.decode_and_unescape_value(&xml).unwrap();
-                                fill.insert(String::from("background"), JsonValue::String(get_xlsx_rgb(value.into())));
-                            },
-                            b"indexed" => {
-                                let value = att.decode_and_unescape_value(&xml).unwrap();
-                                fill.insert(String::from("background"), JsonValue::String(get_indexed_color(&value)));
-                            },
-                            _ => ()
-                        }
-                    }
-                },
-                Ok(Event::End(ref e)) if e.name().as_ref() == b"cellXfs" => {
-                    xml_parent_path = StyleXMLPath::Any;
-                },
-                Ok(Event::End(ref e)) if xml_parent_path == StyleXMLPath::CellXfs && e.name().as_ref() == b"xf" => {
-                    xml_path = StyleXMLPath::Any;
-                },
-                Ok(Event::End(ref e)) if e.name().as_ref() == b"font" => {
-                    xml_path = StyleXMLPath::Any;
-                },
-                Ok(Event::End(ref e)) if e.name().as_ref() == b"fill" => {
-                    xml_path = StyleXMLPath::Any;
-                },
-                Ok(Event::End(ref e)) if e.name().as_ref() == b"border" => {
-                    xml_path = StyleXMLPath::Any;
-                    let mut border = HashMap::new();
-
-                    while border_structs.len() > 0 {
-                        let border_struct = border_structs.pop().unwrap();
-                        let (key, value) = border_struct.get_computed_style();
-                        if value != "" {
-                            border.insert(key, JsonValue::String(value));
-                        }
-                    }
-                    borders.push(border);
-                },
-                Ok(Event::End(ref e)) if e.name().as_ref() == b"styleSheet" => break,
-                Err(_) => return Err(XlsxError::Default),
-                _ => (),
-            }
+            b"tint" => {
+                tint = attr_parse::<f64, _>(xml, part, &att)?;
+            },
+            _ => ()
         }
-        Ok(styles)
     }
-}
-
-fn xml_reader<'a>(zip: &'a mut ZipArchive<Cursor<Vec<u8>>>, path: &str) -> Option<Result<XlsReader<'a>, XlsxError>> {
-    match zip.by_name(path) {
-        Ok(f) => {
-            let mut r = XmlReader::from_reader(BufReader::new(f));
-            r.check_end_names(false)
-                .trim_text(false)
-                .check_comments(false)
-                .expand_empty_elements(true);
-            Some(Ok(r))
-        }
-        Err(_) => None
+    if color.is_none() {
+        color = theme_index.and_then(|index| theme.color(index, tint));
     }
+    Ok(color)
 }
 
-fn get_xlsx_rgb(argb: String) -> String {
-    let raw_a = u8::from_str_radix(&argb[..2], 16).unwrap();
+// "FF00FF00" (or "00FF00" without alpha) -> "rgba(0,255,0,1)"
+fn get_xlsx_rgb(argb: &str) -> Option<String> {
+    let argb = if argb.len() == 6 { format!("FF{}", argb) } else { argb.to_string() };
+    if argb.len() != 8 || !argb.is_ascii() {
+        return None;
+    }
+    let raw_a = u8::from_str_radix(&argb[..2], 16).ok()?;
     let a = (raw_a as f32 / 255f32).to_string();
-    let r = u8::from_str_radix(&argb[2..4], 16).unwrap().to_string();
-    let g = u8::from_str_radix(&argb[4..6], 16).unwrap().to_string();
-    let b = u8::from_str_radix(&argb[6..8], 16).unwrap().to_string();
+    let r = u8::from_str_radix(&argb[2..4], 16).ok()?.to_string();
+    let g = u8::from_str_radix(&argb[4..6], 16).ok()?.to_string();
+    let b = u8::from_str_radix(&argb[6..8], 16).ok()?.to_string();
 
-    format!("rgba({},{},{},{})", r, g, b, a) 
+    Some(format!("rgba({},{},{},{})", r, g, b, a))
 }
 
 fn get_indexed_color(s: &str) -> String {
@@ -1148,10 +924,14 @@ fn get_indexed_color(s: &str) -> String {
         "61" => String::from("#993366"),
         "62" => String::from("#333399"),
         "63" => String::from("#333333"),
+        // system foreground and background
+        "64" => String::from("#000000"),
+        "65" => String::from("#FFFFFF"),
         _ => String::from("#000000")
     }
 }
 
+// built-in formats, ids 23-36 depend on the locale and are not listed
 fn get_format(code: &str) -> Option<String> {
     match code {
         "0" => Some(String::from("General")),
@@ -1159,6 +939,10 @@ fn get_format(code: &str) -> Option<String> {
         "2" => Some(String::from("0.00")),
         "3" => Some(String::from("#,##0")),
         "4" => Some(String::from("#,##0.00")),
+        "5" => Some(String::from("$#,##0_);($#,##0)")),
+        "6" => Some(String::from("$#,##0_);[Red]($#,##0)")),
+        "7" => Some(String::from("$#,##0.00_);($#,##0.00)")),
+        "8" => Some(String::from("$#,##0.00_);[Red]($#,##0.00)")),
         "9" => Some(String::from("0%")),
         "10" => Some(String::from("0.00%")),
         "11" => Some(String::from("0.00E+00")),
@@ -1175,10 +959,15 @@ fn get_format(code: &str) -> Option<String> {
         "22" => Some(String::from("m/d/yy h:mm")),
         "37" => Some(String::from("#,##0 ;(#,##0)")),
         "38" => Some(String::from("#,##0 ;[Red](#,##0)")),
+        "39" => Some(String::from("#,##0.00;(#,##0.00)")),
         "40" => Some(String::from("#,##0.00;[Red](#,##0.00)")),
+        "41" => Some(String::from(r#"_(* #,##0_);_(* \(#,##0\);_(* "-"_);_(@_)"#)),
+        "42" => Some(String::from(r#"_("$"* #,##0_);_("$"* \(#,##0\);_("$"* "-"_);_(@_)"#)),
+        "43" => Some(String::from(r#"_(* #,##0.00_);_(* \(#,##0.00\);_(* "-"??_);_(@_)"#)),
+        "44" => Some(String::from(r#"_("$"* #,##0.00_);_("$"* \(#,##0.00\);_("$"* "-"??_);_(@_)"#)),
         "45" => Some(String::from("mm:ss")),
         "46" => Some(String::from("[h]:mm:ss")),
-        "47" => Some(String::from("mmss.0")),
+        "47" => Some(String::from("mm:ss.0")),
         "48" => Some(String::from("##0.0E+0")),
         "49" => Some(String::from("@")),
         _ => None
@@ -1191,23 +980,11 @@ mod tests {
     use super::*;
 
     #[test]
-    fn file_read() {
-        use std::io::Read;
-
-        let now = std::time::Instant::now();
-        {
-            let mut file = std::fs::File::open("./example/Test.xlsx").unwrap();
-            let mut buf = vec!();
-            file.read_to_end(&mut buf).unwrap();
-            let mut xlsx = XLSX::new(buf);
-            // // cant test with get_sheet_data coz it return jsValue
-            let (name, path) = xlsx.sheets[0].clone();
-            let _data = xlsx.read_sheet(path, name, 0).unwrap();
-            let _styles = xlsx.read_style();
-        }
-        let elapsed = now.elapsed();
-        let sec = (elapsed.as_secs() as f64) + (elapsed.subsec_nanos() as f64 / 1000_000_000.0);
-        println!("time to read 5000 rows: {}",  sec);
+    fn typed_value_conversion() {
+        assert_eq!(CellValue::typed(String::from("1"), CellType::Boolean), CellValue::Bool(true));
+        assert_eq!(CellValue::typed(String::from("TRUE"), CellType::String), CellValue::String(String::from("TRUE")));
+        assert_eq!(CellValue::typed(String::from("1.5E3"), CellType::Number), CellValue::Number(1500.0));
+        assert_eq!(CellValue::typed(String::from("#N/A"), CellType::Error), CellValue::String(String::from("#N/A")));
     }
 
     #[test]
@@ -0,0 +1,1107 @@
+// parsing ods (zip + OpenDocument xml)
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::reader::Reader as XmlReader;
+use serde_json::Value as JsonValue;
+
+use std::collections::HashMap;
+use std::io::{BufRead, Read};
+
+use crate::border::{Border, BorderInfo, BorderPosition};
+use crate::date;
+use crate::fill::Fill;
+use crate::rich_text::TextRun;
+use crate::sheet::{number_text, set_type, CellOptions, ParsedSheet, Row, HEIGHT_COEF, WIDTH_COEF};
+use crate::theme::Theme;
+use crate::{Cell, CellCoords, CellType, CellValue, ColumnData, DefinedName, Dict, MergedCell, SheetData, SheetDescriptor, SheetState, SparseCell, Style, XlsxError};
+use crate::{WITH_BORDER_DETAILS, WITH_FORMULAS, PT_COEF};
+use crate::{attr_value, get_xlsx_rgb, xml_error, xml_reader, Archive};
+
+const MIME_TYPE: &str = "application/vnd.oasis.opendocument.spreadsheet";
+const MAX_COLS: u32 = 16384;
+const MAX_ROWS: u32 = 1_048_576;
+// width of one character in px, column widths are exported in characters like in xlsx
+const CHAR_WIDTH: f32 = 7.0;
+
+// ods has "mimetype" file at the start of the archive
+pub(crate) fn is_ods(zip: &mut Archive) -> bool {
+    let mut file = match zip.by_name("mimetype") {
+        Ok(file) => file,
+        Err(_) => return false,
+    };
+    let mut content = String::new();
+    file.read_to_string(&mut content).is_ok() && content.trim().starts_with(MIME_TYPE)
+}
+
+fn get_attr<R>(xml: &XmlReader<R>, part: &str, e: &BytesStart, key: &[u8]) -> Result<Option<String>, XlsxError> {
+    for a in e.attributes() {
+        let att = a.map_err(|e| xml_error(part, xml, e))?;
+        if att.key.as_ref() == key {
+            return Ok(Some(attr_value(xml, part, &att)?));
+        }
+    }
+    Ok(None)
+}
+
+// repeat and span counts, 1 when not set
+fn get_count<R>(xml: &XmlReader<R>, part: &str, e: &BytesStart, key: &[u8]) -> Result<u32, XlsxError> {
+    Ok(get_attr(xml, part, e, key)?.and_then(|v| v.parse::<u32>().ok()).unwrap_or(1).max(1))
+}
+
+// "0.5in", "1.27cm", "10pt" -> points
+fn length_pt(value: &str) -> Option<f32> {
+    let value = value.trim();
+    let split = value.find(|c: char| c.is_ascii_alphabetic())?;
+    let number = value[..split].parse::<f32>().ok()?;
+    let scale = match &value[split..] {
+        "pt" => 1.0,
+        "in" => 72.0,
+        "cm" => 72.0 / 2.54,
+        "mm" => 72.0 / 25.4,
+        "pc" => 12.0,
+        "px" => 0.75,
+        _ => return None,
+    };
+    Some(number * scale)
+}
+
+// "#ff0000" -> "rgba(255,0,0,1)"
+fn css_color(value: &str) -> Option<String> {
+    get_xlsx_rgb(value.strip_prefix('#')?)
+}
+
+#[derive(Clone, Default)]
+struct CellStyle {
+    parent: Option<String>,
+    data_style: Option<String>,
+    style: Dict,
+    borders: Vec<Border>,
+}
+
+impl CellStyle {
+    fn set_border(&mut self, border: Border) {
+        self.borders.retain(|b| b.get_name() != border.get_name());
+        self.borders.push(border);
+    }
+    fn extend(&mut self, other: &CellStyle) {
+        self.style.extend(other.style.clone());
+        for border in other.borders.iter() {
+            self.set_border(border.clone());
+        }
+        if other.data_style.is_some() {
+            self.data_style = other.data_style.clone();
+        }
+    }
+}
+
+// style element being read, styles of other families are ignored
+enum StyleTarget {
+    None,
+    // name is None for the default style
+    Cell(Option<String>, CellStyle),
+    Text(String, Dict),
+    Column(String),
+    Row(String),
+    Table(String),
+}
+
+// number style being read: name, format code and whether hours are not limited by 24
+struct NumberStyle {
+    name: String,
+    code: String,
+    elapsed: bool,
+}
+
+// text, rich text runs and hyperlink of a cell
+type CellText = (String, Option<Vec<TextRun>>, Option<String>);
+
+// styles of styles.xml and automatic styles of content.xml
+#[derive(Default)]
+struct Styles {
+    default_style: CellStyle,
+    // cell styles in order of declaration, common styles first
+    cell_styles: Vec<(String, CellStyle)>,
+    text_styles: HashMap<String, Dict>,
+    column_widths: HashMap<String, f32>,
+    row_heights: HashMap<String, f32>,
+    // visibility and tab color of sheets
+    table_styles: HashMap<String, (SheetState, Option<String>)>,
+    // number formats by data style name
+    formats: HashMap<String, String>,
+}
+
+impl Styles {
+    // reads style definitions up to the `end` tag
+    fn read<R: BufRead>(&mut self, xml: &mut XmlReader<R>, part: &str, end: &[u8]) -> Result<(), XlsxError> {
+        let mut buf = Vec::new();
+        let mut target = StyleTarget::None;
+        let mut number: Option<NumberStyle> = None;
+        // content of <number:text> and <number:currency-symbol>
+        let mut number_text: Option<String> = None;
+
+        loop {
+            buf.clear();
+            match xml.read_event_into(&mut buf) {
+                Ok(Event::Start(ref e)) => match e.name().as_ref() {
+                    b"style:default-style" => {
+                        if get_attr(xml, part, e, b"style:family")?.as_deref() == Some("table-cell") {
+                            target = StyleTarget::Cell(None, CellStyle::default());
+                        }
+                    },
+                    b"style:style" => {
+                        let name = get_attr(xml, part, e, b"style:name")?.unwrap_or_default();
+                        target = match get_attr(xml, part, e, b"style:family")?.as_deref() {
+                            Some("table-cell") => StyleTarget::Cell(Some(name), CellStyle {
+                                parent: get_attr(xml, part, e, b"style:parent-style-name")?,
+                                data_style: get_attr(xml, part, e, b"style:data-style-name")?,
+                                ..CellStyle::default()
+                            }),
+                            Some("text") => StyleTarget::Text(name, Dict::new()),
+                            Some("table-column") => StyleTarget::Column(name),
+                            Some("table-row") => StyleTarget::Row(name),
+                            Some("table") => StyleTarget::Table(name),
+                            _ => StyleTarget::None,
+                        };
+                    },
+                    b"style:text-properties" => match &mut target {
+                        StyleTarget::Cell(_, cell) => read_text_properties(xml, part, e, &mut cell.style, false)?,
+                        StyleTarget::Text(_, style) => read_text_properties(xml, part, e, style, true)?,
+                        _ => (),
+                    },
+                    b"style:table-cell-properties" => {
+                        if let StyleTarget::Cell(_, cell) = &mut target {
+                            read_cell_properties(xml, part, e, cell)?;
+                        }
+                    },
+                    b"style:paragraph-properties" => {
+                        if let StyleTarget::Cell(_, cell) = &mut target {
+                            let align = match get_attr(xml, part, e, b"fo:text-align")?.as_deref() {
+                                Some("start") | Some("left") => Some("left"),
+                                Some("end") | Some("right") => Some("right"),
+                                Some("center") => Some("center"),
+                                Some("justify") => Some("justify"),
+                                _ => None,
+                            };
+                            if let Some(align) = align {
+                                cell.style.insert(String::from("align"), JsonValue::String(String::from(align)));
+                            }
+                        }
+                    },
+                    b"style:table-column-properties" => {
+                        if let StyleTarget::Column(name) = &target {
+                            if let Some(width) = get_attr(xml, part, e, b"style:column-width")?.and_then(|w| length_pt(&w)) {
+                                self.column_widths.insert(name.clone(), width / HEIGHT_COEF / CHAR_WIDTH * WIDTH_COEF);
+                            }
+                        }
+                    },
+                    b"style:table-row-properties" => {
+                        if let StyleTarget::Row(name) = &target {
+                            if let Some(height) = get_attr(xml, part, e, b"style:row-height")?.and_then(|h| length_pt(&h)) {
+                                self.row_heights.insert(name.clone(), height / HEIGHT_COEF);
+                            }
+                        }
+                    },
+                    b"style:table-properties" => {
+                        if let StyleTarget::Table(name) = &target {
+                            let state = match get_attr(xml, part, e, b"table:display")?.as_deref() {
+                                Some("false") => SheetState::Hidden,
+                                _ => SheetState::Visible,
+                            };
+                            let color = get_attr(xml, part, e, b"tableooo:tab-color")?.and_then(|c| css_color(&c));
+                            self.table_styles.insert(name.clone(), (state, color));
+                        }
+                    },
+                    b"number:number-style" | b"number:percentage-style" | b"number:currency-style" | b"number:date-style"
+                        | b"number:time-style" | b"number:text-style" | b"number:boolean-style" => {
+                        number = Some(NumberStyle {
+                            name: get_attr(xml, part, e, b"style:name")?.unwrap_or_default(),
+                            code: String::new(),
+                            elapsed: get_attr(xml, part, e, b"number:truncate-on-overflow")?.as_deref() == Some("false"),
+                        });
+                    },
+                    b"number:text" | b"number:currency-symbol" if number.is_some() => {
+                        number_text = Some(String::new());
+                    },
+                    name => {
+                        if let Some(number) = number.as_mut() {
+                            if let Some(code) = format_part(xml, part, e, name, number)? {
+                                number.code.push_str(&code);
+                            }
+                        }
+                    },
+                },
+                Ok(Event::Text(ref e)) if number_text.is_some() => {
+                    let value = e.unescape().map_err(|e| xml_error(part, xml, e))?;
+                    if let Some(text) = number_text.as_mut() {
+                        text.push_str(&value);
+                    }
+                },
+                Ok(Event::End(ref e)) => match e.name().as_ref() {
+                    b"style:default-style" | b"style:style" => {
+                        match std::mem::replace(&mut target, StyleTarget::None) {
+                            StyleTarget::Cell(Some(name), style) => self.cell_styles.push((name, style)),
+                            StyleTarget::Cell(None, style) => self.default_style = style,
+                            StyleTarget::Text(name, style) => { self.text_styles.insert(name, style); },
+                            _ => (),
+                        }
+                    },
+                    b"number:text" => {
+                        if let (Some(text), Some(number)) = (number_text.take(), number.as_mut()) {
+                            number.code.push_str(&format_literal(&text));
+                        }
+                    },
+                    b"number:currency-symbol" => {
+                        if let (Some(text), Some(number)) = (number_text.take(), number.as_mut()) {
+                            if text == "$" {
+                                number.code.push('$');
+                            } else {
+                                number.code.push_str(&format!("\"{}\"", text));
+                            }
+                        }
+                    },
+                    b"number:number-style" | b"number:percentage-style" | b"number:currency-style" | b"number:date-style"
+                        | b"number:time-style" | b"number:text-style" | b"number:boolean-style" => {
+                        if let Some(number) = number.take() {
+                            if !number.code.is_empty() {
+                                self.formats.insert(number.name, number.code);
+                            }
+                        }
+                    },
+                    name if name == end => break,
+                    _ => (),
+                },
+                Ok(Event::Eof) => break,
+                Err(e) => return Err(xml_error(part, xml, e)),
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+    // "Default" style is the first one, cells without style use it
+    fn resolve(&self) -> (Vec<CellStyle>, HashMap<String, u32>) {
+        let by_name: HashMap<&str, &CellStyle> = self.cell_styles.iter().map(|(name, style)| (name.as_str(), style)).collect();
+        let mut names: Vec<&str> = vec!("Default");
+        names.extend(self.cell_styles.iter().map(|(name, _)| name.as_str()).filter(|name| *name != "Default"));
+
+        let mut styles = vec!();
+        let mut index = HashMap::new();
+        for name in names {
+            let mut chain = vec!();
+            let mut next = by_name.get(name);
+            while let Some(style) = next {
+                // parent styles are not expected to form a loop, but files can be broken
+                if chain.len() > 16 {
+                    break;
+                }
+                chain.push(*style);
+                next = style.parent.as_deref().and_then(|parent| by_name.get(parent));
+            }
+            let mut style = self.default_style.clone();
+            for parent in chain.iter().rev() {
+                style.extend(parent);
+            }
+            index.insert(name.to_string(), styles.len() as u32);
+            styles.push(style);
+        }
+        (styles, index)
+    }
+}
+
+fn read_text_properties<R>(xml: &XmlReader<R>, part: &str, e: &BytesStart, style: &mut Dict, with_position: bool) -> Result<(), XlsxError> {
+    for a in e.attributes() {
+        let att = a.map_err(|e| xml_error(part, xml, e))?;
+        let value = attr_value(xml, part, &att)?;
+        match att.key.as_ref() {
+            b"fo:font-size" => {
+                if let Some(size) = length_pt(&value) {
+                    style.insert(String::from("fontSize"), JsonValue::String((size / PT_COEF).to_string() + "px"));
+                }
+            },
+            b"style:font-name" | b"fo:font-family" => {
+                style.insert(String::from("fontFamily"), JsonValue::String(value.trim_matches('\'').to_string()));
+            },
+            b"fo:color" => {
+                if let Some(color) = css_color(&value) {
+                    style.insert(String::from("color"), JsonValue::String(color));
+                }
+            },
+            b"fo:font-weight" => {
+                if value == "bold" || value.parse::<u32>().map(|w| w >= 700).unwrap_or(false) {
+                    style.insert(String::from("fontWeight"), JsonValue::String(String::from("bold")));
+                } else {
+                    style.remove("fontWeight");
+                }
+            },
+            b"fo:font-style" => {
+                if value == "italic" || value == "oblique" {
+                    style.insert(String::from("fontStyle"), JsonValue::String(String::from("italic")));
+                } else {
+                    style.remove("fontStyle");
+                }
+            },
+            b"style:text-underline-style" if value != "none" => add_decoration(style, "underline"),
+            b"style:text-line-through-style" if value != "none" => add_decoration(style, "line-through"),
+            b"style:text-position" if with_position => {
+                if value.starts_with("super") {
+                    style.insert(String::from("verticalAlign"), JsonValue::String(String::from("super")));
+                } else if value.starts_with("sub") {
+                    style.insert(String::from("verticalAlign"), JsonValue::String(String::from("sub")));
+                }
+            },
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+fn add_decoration(style: &mut Dict, value: &str) {
+    let current = style.get("textDecoration").and_then(|v| v.as_str()).unwrap_or("");
+    if !current.is_empty() && current != value {
+        style.insert(String::from("textDecoration"), JsonValue::String(String::from("line-through underline")));
+    } else {
+        style.insert(String::from("textDecoration"), JsonValue::String(value.to_string()));
+    }
+}
+
+fn read_cell_properties<R>(xml: &XmlReader<R>, part: &str, e: &BytesStart, cell: &mut CellStyle) -> Result<(), XlsxError> {
+    for a in e.attributes() {
+        let att = a.map_err(|e| xml_error(part, xml, e))?;
+        let value = attr_value(xml, part, &att)?;
+        let positions: &[BorderPosition] = match att.key.as_ref() {
+            b"fo:border" => &[BorderPosition::Left, BorderPosition::Right, BorderPosition::Top, BorderPosition::Bottom],
+            b"fo:border-left" => &[BorderPosition::Left],
+            b"fo:border-right" => &[BorderPosition::Right],
+            b"fo:border-top" => &[BorderPosition::Top],
+            b"fo:border-bottom" => &[BorderPosition::Bottom],
+            b"style:diagonal-bl-tr" => &[BorderPosition::DiagonalUp],
+            b"style:diagonal-tl-br" => &[BorderPosition::DiagonalDown],
+            b"fo:background-color" => {
+                if let Some(color) = css_color(&value) {
+                    let mut fill = Fill::new();
+                    fill.set_pattern(String::from("solid"));
+                    fill.set_fg_color(color);
+                    cell.style.extend(fill.get_computed_style());
+                } else {
+                    cell.style.remove("background");
+                }
+                continue;
+            },
+            b"fo:wrap-option" => {
+                if value == "wrap" {
+                    cell.style.insert(String::from("wrapText"), JsonValue::Bool(true));
+                }
+                continue;
+            },
+            b"style:vertical-align" => {
+                let vertical = match value.as_str() {
+                    "top" => Some("top"),
+                    "middle" => Some("center"),
+                    "bottom" => Some("bottom"),
+                    _ => None,
+                };
+                if let Some(vertical) = vertical {
+                    cell.style.insert(String::from("verticalAlign"), JsonValue::String(String::from(vertical)));
+                }
+                continue;
+            },
+            _ => continue,
+        };
+        for position in positions {
+            cell.set_border(border(*position, &value));
+        }
+    }
+    Ok(())
+}
+
+// "0.74pt solid #000000" to the border with the closest xlsx style
+fn border(position: BorderPosition, value: &str) -> Border {
+    let mut width = 0.75;
+    let mut line = "none";
+    let mut color = None;
+    for token in value.split_whitespace() {
+        if token.starts_with('#') {
+            color = css_color(token);
+        } else if let Some(pt) = length_pt(token) {
+            width = pt;
+        } else {
+            line = token;
+        }
+    }
+    let medium = width > 1.0;
+    let style = match line {
+        "solid" if width > 2.0 => Some("thick"),
+        "solid" if medium => Some("medium"),
+        "solid" => Some("thin"),
+        "double" => Some("double"),
+        "dotted" => Some("dotted"),
+        "dashed" | "fine-dashed" if medium => Some("mediumDashed"),
+        "dashed" | "fine-dashed" => Some("dashed"),
+        "dash-dot" if medium => Some("mediumDashDot"),
+        "dash-dot" => Some("dashDot"),
+        "dash-dot-dot" if medium => Some("mediumDashDotDot"),
+        "dash-dot-dot" => Some("dashDotDot"),
+        _ => None,
+    };
+    let mut border = Border::new(position);
+    if let Some(style) = style {
+        border.set_style(String::from(style));
+        if let Some(color) = color {
+            border.set_color(color);
+        }
+    }
+    border
+}
+
+// excel format code for an element of number style
+fn format_part<R>(xml: &XmlReader<R>, part: &str, e: &BytesStart, name: &[u8], number: &mut NumberStyle) -> Result<Option<String>, XlsxError> {
+    let long = get_attr(xml, part, e, b"number:style")?.as_deref() == Some("long");
+    let digits = |key: &[u8], default: usize| -> Result<usize, XlsxError> {
+        Ok(get_attr(xml, part, e, key)?.and_then(|v| v.parse::<usize>().ok()).unwrap_or(default).min(30))
+    };
+    let code = match name {
+        b"number:number" | b"number:scientific-number" => {
+            let mut code = integer_code(digits(b"number:min-integer-digits", 1)?, get_attr(xml, part, e, b"number:grouping")?.as_deref() == Some("true"));
+            let decimals = digits(b"number:decimal-places", 0)?;
+            if decimals > 0 {
+                code.push('.');
+                code.push_str(&"0".repeat(decimals));
+            }
+            if name == b"number:scientific-number" {
+                code.push_str("E+");
+                code.push_str(&"0".repeat(digits(b"number:min-exponent-digits", 2)?.max(1)));
+            }
+            code
+        },
+        b"number:fraction" => {
+            let integer = digits(b"number:min-integer-digits", 0)?;
+            let numerator = "?".repeat(digits(b"number:min-numerator-digits", 1)?.max(1));
+            let denominator = match get_attr(xml, part, e, b"number:denominator-value")? {
+                Some(value) => value,
+                None => "?".repeat(digits(b"number:min-denominator-digits", 1)?.max(1)),
+            };
+            if integer > 0 {
+                format!("{} {}/{}", integer_code(integer, false), numerator, denominator)
+            } else {
+                format!("{}/{}", numerator, denominator)
+            }
+        },
+        b"number:year" => String::from(if long { "yyyy" } else { "yy" }),
+        b"number:month" => {
+            let textual = get_attr(xml, part, e, b"number:textual")?.as_deref() == Some("true");
+            String::from(match (textual, long) {
+                (true, true) => "mmmm",
+                (true, false) => "mmm",
+                (false, true) => "mm",
+                (false, false) => "m",
+            })
+        },
+        b"number:day" => String::from(if long { "dd" } else { "d" }),
+        b"number:day-of-week" => String::from(if long { "dddd" } else { "ddd" }),
+        b"number:hours" => {
+            let hours = if long { "hh" } else { "h" };
+            if std::mem::take(&mut number.elapsed) {
+                format!("[{}]", hours)
+            } else {
+                String::from(hours)
+            }
+        },
+        b"number:minutes" => String::from(if long { "mm" } else { "m" }),
+        b"number:seconds" => {
+            let mut code = String::from(if long { "ss" } else { "s" });
+            let decimals = digits(b"number:decimal-places", 0)?;
+            if decimals > 0 {
+                code.push('.');
+                code.push_str(&"0".repeat(decimals));
+            }
+            code
+        },
+        b"number:am-pm" => String::from("AM/PM"),
+        b"number:text-content" => String::from("@"),
+        b"number:boolean" => String::from("\"TRUE\";\"TRUE\";\"FALSE\""),
+        _ => return Ok(None),
+    };
+    Ok(Some(code))
+}
+
+// "0", "#,##0", "00"
+fn integer_code(digits: usize, grouping: bool) -> String {
+    let zeros = if digits == 0 { String::from("#") } else { "0".repeat(digits) };
+    if !grouping {
+        return zeros;
+    }
+    if zeros.len() >= 4 {
+        let (head, tail) = zeros.split_at(zeros.len() - 3);
+        return format!("{},{}", head, tail);
+    }
+    let padded = format!("{}{}", "#".repeat(4 - zeros.len()), zeros);
+    format!("{},{}", &padded[..1], &padded[1..])
+}
+
+// literal text of number format, symbols which don't need escaping are kept as is
+fn format_literal(text: &str) -> String {
+    if text.chars().all(|c| " -/:.,()$+%".contains(c)) {
+        text.to_string()
+    } else {
+        format!("\"{}\"", text.replace('"', ""))
+    }
+}
+
+// "of:=SUM([.A1:.B2];[Sheet2.C3])" -> "SUM(A1:B2,Sheet2!C3)"
+fn convert_formula(formula: &str) -> String {
+    let formula = formula.split_once(':').filter(|(ns, _)| !ns.contains('(') && !ns.contains('"')).map(|(_, f)| f).unwrap_or(formula);
+    let formula = formula.strip_prefix('=').unwrap_or(formula);
+    let mut result = String::new();
+    let mut chars = formula.chars();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                in_string = !in_string;
+                result.push(c);
+            },
+            ';' if !in_string => result.push(','),
+            '[' if !in_string => {
+                let reference: String = chars.by_ref().take_while(|c| *c != ']').collect();
+                let parts: Vec<String> = reference.split(':').map(convert_reference).collect();
+                result.push_str(&parts.join(":"));
+            },
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+// ".A1" -> "A1", "$Sheet2.A1" -> "Sheet2!A1"
+fn convert_reference(reference: &str) -> String {
+    let mut in_quotes = false;
+    let mut dot = None;
+    for (i, c) in reference.char_indices() {
+        match c {
+            '\'' => in_quotes = !in_quotes,
+            '.' if !in_quotes => dot = Some(i),
+            _ => (),
+        }
+    }
+    match dot {
+        Some(0) => reference[1..].to_string(),
+        Some(i) => format!("{}!{}", reference[..i].trim_start_matches('$'), &reference[i + 1..]),
+        None => reference.to_string(),
+    }
+}
+
+// reads content of <table:table-cell> up to its end tag
+// paragraphs are joined with line breaks, spans with own style are rich text runs
+fn read_cell_text<R: BufRead>(xml: &mut XmlReader<R>, part: &str, end: &[u8], text_styles: &HashMap<String, Dict>) -> Result<CellText, XlsxError> {
+    let mut buf = Vec::new();
+    let mut segments: Vec<(String, Option<String>)> = vec!();
+    let mut spans: Vec<Option<String>> = vec!();
+    let mut paragraphs = 0;
+    let mut in_text = false;
+    let mut link = None;
+    // comments are not a part of cell text
+    let mut annotation = 0;
+
+    let push = |segments: &mut Vec<(String, Option<String>)>, text: &str, style: Option<String>| {
+        match segments.last_mut() {
+            Some((last, last_style)) if *last_style == style => last.push_str(text),
+            _ => segments.push((text.to_string(), style)),
+        }
+    };
+
+    loop {
+        buf.clear();
+        match xml.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) if e.name().as_ref() == b"office:annotation" => annotation += 1,
+            Ok(Event::End(ref e)) if e.name().as_ref() == b"office:annotation" => annotation -= 1,
+            Ok(Event::Start(_)) | Ok(Event::End(_)) | Ok(Event::Text(_)) if annotation > 0 => (),
+            Ok(Event::Start(ref e)) => match e.name().as_ref() {
+                b"text:p" | b"text:h" => {
+                    if paragraphs > 0 {
+                        push(&mut segments, "\n", None);
+                    }
+                    paragraphs += 1;
+                    in_text = true;
+                },
+                b"text:span" => spans.push(get_attr(xml, part, e, b"text:style-name")?.filter(|name| text_styles.contains_key(name))),
+                b"text:s" => {
+                    let count = get_count(xml, part, e, b"text:c")? as usize;
+                    push(&mut segments, &" ".repeat(count.min(1000)), spans.last().cloned().flatten());
+                },
+                b"text:tab" => push(&mut segments, "\t", spans.last().cloned().flatten()),
+                b"text:line-break" => push(&mut segments, "\n", spans.last().cloned().flatten()),
+                b"text:a" => link = get_attr(xml, part, e, b"xlink:href")?,
+                _ => (),
+            },
+            Ok(Event::Text(ref e)) if in_text => {
+                let text = e.unescape().map_err(|e| xml_error(part, xml, e))?;
+                push(&mut segments, &text, spans.last().cloned().flatten());
+            },
+            Ok(Event::End(ref e)) => match e.name().as_ref() {
+                b"text:p" | b"text:h" => in_text = false,
+                b"text:span" => { spans.pop(); },
+                name if name == end => break,
+                _ => (),
+            },
+            Ok(Event::Eof) => return Err(xml_error(part, xml, "unexpected end of document")),
+            Err(e) => return Err(xml_error(part, xml, e)),
+            _ => (),
+        }
+    }
+
+    let text: String = segments.iter().map(|(text, _)| text.as_str()).collect();
+    let runs = if segments.iter().any(|(_, style)| style.is_some()) {
+        Some(segments.into_iter()
+            .map(|(text, style)| TextRun { text, style: style.and_then(|s| text_styles.get(&s).cloned()).unwrap_or_default() })
+            .collect())
+    } else {
+        None
+    };
+    Ok((text, runs, link))
+}
+
+pub(crate) struct Ods {
+    zip: Archive,
+    sheets: Vec<SheetDescriptor>,
+    defined_names: Vec<DefinedName>,
+    definitions: Styles,
+    // resolved cell styles, index in the list is `s` of cells
+    styles: Vec<CellStyle>,
+    style_index: HashMap<String, u32>,
+}
+
+impl Ods {
+    pub fn from_zip(zip: Archive) -> Result<Ods, XlsxError> {
+        let mut ods = Ods {
+            zip,
+            sheets: vec!(),
+            defined_names: vec!(),
+            definitions: Styles::default(),
+            styles: vec!(),
+            style_index: HashMap::new(),
+        };
+
+        let part = "styles.xml";
+        if let Some(xml) = xml_reader(&mut ods.zip, part) {
+            ods.definitions.read(&mut xml?, part, b"office:document-styles")?;
+        }
+        ods.read_content()?;
+        let (styles, style_index) = ods.definitions.resolve();
+        ods.styles = styles;
+        ods.style_index = style_index;
+        Ok(ods)
+    }
+    pub fn sheet_names(&self) -> Vec<String> {
+        self.sheets.iter().map(|sheet| sheet.name.clone()).collect()
+    }
+    pub fn sheets(&self) -> Vec<SheetDescriptor> {
+        self.sheets.clone()
+    }
+    pub fn defined_names(&self) -> Vec<DefinedName> {
+        self.defined_names.clone()
+    }
+    // automatic styles, names of sheets and named ranges
+    fn read_content(&mut self) -> Result<(), XlsxError> {
+        let part = "content.xml";
+        let mut xml = match xml_reader(&mut self.zip, part) {
+            None => return Err(XlsxError::MissingPart { part: String::from(part) }),
+            Some(x) => x?,
+        };
+        let mut buf = Vec::new();
+        // named ranges inside of a table are local to it
+        let mut table = None;
+        loop {
+            buf.clear();
+            match xml.read_event_into(&mut buf) {
+                Ok(Event::Start(ref e)) if e.name().as_ref() == b"office:automatic-styles" => {
+                    self.definitions.read(&mut xml, part, b"office:automatic-styles")?;
+                },
+                Ok(Event::Start(ref e)) if e.name().as_ref() == b"table:table" => {
+                    if let Some(name) = get_attr(&xml, part, e, b"table:name")? {
+                        let mut sheet = SheetDescriptor::new(name.clone(), self.sheets.len() as u32 + 1);
+                        let table_styles = &self.definitions.table_styles;
+                        let style = get_attr(&xml, part, e, b"table:style-name")?.and_then(|s| table_styles.get(&s));
+                        if let Some((state, color)) = style {
+                            sheet.state = *state;
+                            sheet.tab_color = color.clone();
+                        }
+                        self.sheets.push(sheet);
+                        table = Some(name);
+                    }
+                },
+                Ok(Event::End(ref e)) if e.name().as_ref() == b"table:table" => table = None,
+                Ok(Event::Start(ref e)) if e.name().as_ref() == b"table:named-range" || e.name().as_ref() == b"table:named-expression" => {
+                    let reference = match get_attr(&xml, part, e, b"table:cell-range-address")? {
+                        Some(range) => range.split(':').map(convert_reference).collect::<Vec<String>>().join(":"),
+                        None => convert_formula(&get_attr(&xml, part, e, b"table:expression")?.unwrap_or_default()),
+                    };
+                    self.defined_names.push(DefinedName {
+                        name: get_attr(&xml, part, e, b"table:name")?.unwrap_or_default(),
+                        scope: table.clone(),
+                        hidden: false,
+                        reference,
+                    });
+                },
+                Ok(Event::Eof) => break,
+                Err(e) => return Err(xml_error(part, &xml, e)),
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+    // number format of each cell style
+    fn cell_formats(&self) -> Vec<String> {
+        self.styles.iter().map(|style| self.format(style)).collect()
+    }
+    fn format(&self, style: &CellStyle) -> String {
+        style.data_style.as_ref()
+            .and_then(|name| self.definitions.formats.get(name))
+            .cloned()
+            .unwrap_or_else(|| String::from("General"))
+    }
+    pub fn styles_with(&self, flags: u32) -> Vec<Style> {
+        self.styles.iter().map(|cell_style| {
+            let mut style = cell_style.style.clone();
+            let mut details: HashMap<String, BorderInfo> = HashMap::new();
+            for border in cell_style.borders.iter().cloned() {
+                if flags & WITH_BORDER_DETAILS > 0 {
+                    if let Some(info) = border.get_info() {
+                        details.insert(border.get_name(), info);
+                    }
+                }
+                let (key, value) = border.get_computed_style();
+                if !value.is_empty() {
+                    style.insert(key, JsonValue::String(value));
+                }
+            }
+            if !details.is_empty() {
+                style.insert(String::from("borders"), serde_json::to_value(details).unwrap_or_default());
+            }
+            style.insert(String::from("format"), JsonValue::String(self.format(cell_style)));
+            style
+        }).collect()
+    }
+    pub fn sheet_with(&mut self, sheet_name: &str, flags: u32) -> Result<SheetData, XlsxError> {
+        Ok(self.read_sheet(sheet_name, flags)?.into_sheet(flags))
+    }
+    // all tables are in content.xml, the sheet is read at once
+    pub fn read_sheet(&mut self, sheet_name: &str, flags: u32) -> Result<ParsedSheet, XlsxError> {
+        if !self.sheets.iter().any(|sheet| sheet.name == sheet_name) {
+            return Err(XlsxError::UnknownSheet { name: sheet_name.to_string() });
+        }
+        let cell_formats = self.cell_formats();
+        let options = CellOptions::new(flags, false, &cell_formats, &Theme::default());
+        let definitions = &self.definitions;
+        let style_index = &self.style_index;
+
+        let part = "content.xml";
+        let mut xml = match xml_reader(&mut self.zip, part) {
+            None => return Err(XlsxError::MissingPart { part: String::from(part) }),
+            Some(x) => x?,
+        };
+        let mut buf = Vec::new();
+        let mut sheet = ParsedSheet::new(sheet_name.to_string());
+        let mut found = false;
+
+        // default styles of columns, for cells without own style
+        let mut column_styles: Vec<u32> = vec!();
+        let mut row = 0;
+        let mut row_repeat = 1;
+        let mut row_height = sheet.default_row_height;
+        let mut row_hidden = None;
+        let mut col = 0;
+        let mut row_cells: Vec<(u32, Cell)> = vec!();
+        // empty rows with own height or hidden: first row, count, height and visibility
+        // they are added only when there are cells below them
+        let mut empty_rows: Vec<(u32, u32, f32, Option<bool>)> = vec!();
+
+        loop {
+            buf.clear();
+            match xml.read_event_into(&mut buf) {
+                Ok(Event::Start(ref e)) if !found => {
+                    found = e.name().as_ref() == b"table:table" && get_attr(&xml, part, e, b"table:name")?.as_deref() == Some(sheet_name);
+                },
+                Ok(Event::Start(ref e)) if e.name().as_ref() == b"table:table-column" => {
+                    let repeat = get_count(&xml, part, e, b"table:number-columns-repeated")?;
+                    let width = get_attr(&xml, part, e, b"table:style-name")?
+                        .and_then(|name| definitions.column_widths.get(&name).copied())
+                        .unwrap_or(sheet.default_col_width);
+                    let hidden = match get_attr(&xml, part, e, b"table:visibility")?.as_deref() {
+                        Some("collapse") | Some("filter") => Some(true),
+                        _ => None,
+                    };
+                    let style = get_attr(&xml, part, e, b"table:default-cell-style-name")?
+                        .and_then(|name| style_index.get(&name).copied())
+                        .unwrap_or(0);
+                    for _ in 0..repeat.min(MAX_COLS - (sheet.data.cols.len() as u32).min(MAX_COLS)) {
+                        sheet.data.cols.push(ColumnData { width, hidden, index: None });
+                        column_styles.push(style);
+                    }
+                },
+                Ok(Event::Start(ref e)) if e.name().as_ref() == b"table:table-row" => {
+                    row_repeat = get_count(&xml, part, e, b"table:number-rows-repeated")?.min(MAX_ROWS.saturating_sub(row));
+                    row_height = get_attr(&xml, part, e, b"table:style-name")?
+                        .and_then(|name| definitions.row_heights.get(&name).copied())
+                        .unwrap_or(sheet.default_row_height);
+                    row_hidden = match get_attr(&xml, part, e, b"table:visibility")?.as_deref() {
+                        Some("collapse") | Some("filter") => Some(true),
+                        _ => None,
+                    };
+                    col = 0;
+                },
+                Ok(Event::Start(ref e)) if e.name().as_ref() == b"table:covered-table-cell" => {
+                    // covered cells are parts of merged ones, they only extend the sheet
+                    let repeat = get_count(&xml, part, e, b"table:number-columns-repeated")?;
+                    for i in 0..repeat.min(MAX_COLS.saturating_sub(col)) {
+                        row_cells.push((col + i, Cell::new()));
+                    }
+                    col = col.saturating_add(repeat.min(MAX_COLS));
+                    read_cell_text(&mut xml, part, b"table:covered-table-cell", &definitions.text_styles)?;
+                },
+                Ok(Event::Start(ref e)) if e.name().as_ref() == b"table:table-cell" => {
+                    let repeat = get_count(&xml, part, e, b"table:number-columns-repeated")?;
+                    let style = match get_attr(&xml, part, e, b"table:style-name")? {
+                        Some(name) => style_index.get(&name).copied().unwrap_or(0),
+                        None => column_styles.get(col as usize).copied().unwrap_or(0),
+                    };
+                    let mut cell = Cell { s: style, ..Cell::new() };
+
+                    let mut value_type = None;
+                    let mut value = None;
+                    let mut string_value = None;
+                    let mut formula = None;
+                    let (mut cols_spanned, mut rows_spanned) = (1, 1);
+                    for a in e.attributes() {
+                        let att = a.map_err(|e| xml_error(part, &xml, e))?;
+                        match att.key.as_ref() {
+                            b"office:value-type" => value_type = Some(attr_value(&xml, part, &att)?),
+                            b"office:value" => value = Some(attr_value(&xml, part, &att)?),
+                            b"office:date-value" => value = date::iso_to_serial(&attr_value(&xml, part, &att)?).map(number_text),
+                            b"office:time-value" => value = date::duration_to_serial(&attr_value(&xml, part, &att)?).map(number_text),
+                            b"office:boolean-value" => value = Some(String::from(if attr_value(&xml, part, &att)? == "true" { "1" } else { "0" })),
+                            b"office:string-value" => string_value = Some(attr_value(&xml, part, &att)?),
+                            b"table:formula" => formula = Some(attr_value(&xml, part, &att)?),
+                            b"table:number-columns-spanned" => cols_spanned = attr_value(&xml, part, &att)?.parse::<u32>().unwrap_or(1).clamp(1, MAX_COLS),
+                            b"table:number-rows-spanned" => rows_spanned = attr_value(&xml, part, &att)?.parse::<u32>().unwrap_or(1).clamp(1, MAX_ROWS),
+                            _ => (),
+                        }
+                    }
+                    let (text, runs, link) = read_cell_text(&mut xml, part, b"table:table-cell", &definitions.text_styles)?;
+
+                    if let Some(formula) = formula.filter(|_| flags & WITH_FORMULAS > 0) {
+                        cell.v = Some(CellValue::String("=".to_owned() + &convert_formula(&formula)));
+                    }
+                    let cell_type = match (value_type.as_deref(), value) {
+                        (Some("float"), Some(value)) | (Some("percentage"), Some(value)) | (Some("currency"), Some(value))
+                            | (Some("date"), Some(value)) | (Some("time"), Some(value)) => {
+                            options.set_value(&mut cell, value, CellType::Number, &cell_formats)
+                        },
+                        (Some("boolean"), Some(value)) => options.set_value(&mut cell, value, CellType::Boolean, &cell_formats),
+                        _ => {
+                            options.set_text(&mut cell, string_value.unwrap_or(text), runs, &cell_formats);
+                            CellType::String
+                        },
+                    };
+                    set_type(&mut cell, cell_type);
+                    cell.hyperlink = link;
+
+                    if cols_spanned > 1 || rows_spanned > 1 {
+                        sheet.data.merged.push(MergedCell {
+                            from: CellCoords { column: col, row },
+                            to: CellCoords { column: col.saturating_add(cols_spanned - 1), row: row.saturating_add(rows_spanned - 1) },
+                        });
+                    }
+                    // styled empty cells are repeated till the end of row in some files, so they are kept only when not repeated
+                    if cell.v.is_some() || (cell.s > 0 && repeat == 1 && row_repeat == 1) {
+                        for i in 0..repeat.min(MAX_COLS.saturating_sub(col)) {
+                            row_cells.push((col + i, cell.clone()));
+                        }
+                    }
+                    col = col.saturating_add(repeat.min(MAX_COLS));
+                },
+                Ok(Event::End(ref e)) if found && e.name().as_ref() == b"table:table-row" => {
+                    if row_cells.is_empty() {
+                        if row_hidden.is_some() || row_height != sheet.default_row_height {
+                            empty_rows.push((row, row_repeat, row_height, row_hidden));
+                        }
+                    } else {
+                        for (first, count, height, hidden) in empty_rows.drain(..) {
+                            for index in first..first + count {
+                                sheet.rows.insert(index, Row { index, height, hidden, cells: vec!() });
+                            }
+                        }
+                        for i in 0..row_repeat {
+                            let index = row + i;
+                            sheet.rows.insert(index, Row { index, height: row_height, hidden: row_hidden, cells: vec!() });
+                            for (c, cell) in row_cells.iter() {
+                                sheet.cells.push(SparseCell { r: index, c: *c, cell: cell.clone() });
+                            }
+                        }
+                        row_cells.clear();
+                    }
+                    row += row_repeat;
+                },
+                Ok(Event::End(ref e)) if found && e.name().as_ref() == b"table:table" => break,
+                Ok(Event::Eof) => break,
+                Err(e) => return Err(xml_error(part, &xml, e)),
+                _ => (),
+            }
+        }
+        Ok(sheet)
+    }
+}
+
+#[test]
+fn test_convert_formula() {
+    assert_eq!(convert_formula("of:=SUM([.A1:.B2];[$Sheet2.C3])"), "SUM(A1:B2,Sheet2!C3)");
+    assert_eq!(convert_formula("of:=IF([.A1]=\"a;b\";1;2)"), "IF(A1=\"a;b\",1,2)");
+    assert_eq!(convert_formula("of:=['My.Sheet'.A1]"), "'My.Sheet'!A1");
+}
+
+#[test]
+fn test_number_formats() {
+    assert_eq!(integer_code(1, true), "#,##0");
+    assert_eq!(integer_code(2, true), "#,#00");
+    assert_eq!(integer_code(0, false), "#");
+    assert_eq!(format_literal("%"), "%");
+    assert_eq!(format_literal(" pcs"), "\" pcs\"");
+}
+
+#[cfg(test)]
+fn build_ods() -> Vec<u8> {
+    let styles = br#"<office:document-styles><office:styles>
+        <style:default-style style:family="table-cell"><style:text-properties style:font-name="Liberation Sans" fo:font-size="10pt"/></style:default-style>
+        <style:style style:name="Default" style:family="table-cell"/>
+        <style:style style:name="Heading" style:family="table-cell" style:parent-style-name="Default"><style:text-properties fo:font-weight="bold"/></style:style>
+    </office:styles></office:document-styles>"#;
+    let content = br##"<office:document-content><office:automatic-styles>
+        <style:style style:name="co1" style:family="table-column"><style:table-column-properties style:column-width="1.5in"/></style:style>
+        <style:style style:name="ro1" style:family="table-row"><style:table-row-properties style:row-height="0.5in"/></style:style>
+        <number:percentage-style style:name="N1"><number:number number:decimal-places="1" number:min-integer-digits="1"/><number:text>%</number:text></number:percentage-style>
+        <style:style style:name="ce1" style:family="table-cell" style:parent-style-name="Heading" style:data-style-name="N1">
+            <style:table-cell-properties fo:background-color="#ffff00" fo:border-left="0.74pt solid #000000" fo:wrap-option="wrap"/>
+            <style:paragraph-properties fo:text-align="center"/>
+            <style:text-properties fo:color="#ff0000"/>
+        </style:style>
+        <style:style style:name="T1" style:family="text"><style:text-properties fo:font-weight="bold"/></style:style>
+        <style:style style:name="ta2" style:family="table"><style:table-properties table:display="false" tableooo:tab-color="#0000ff"/></style:style>
+    </office:automatic-styles><office:body><office:spreadsheet>
+        <table:table table:name="Data">
+            <table:table-column table:style-name="co1"/>
+            <table:table-column table:number-columns-repeated="3"/>
+            <table:table-row table:style-name="ro1">
+                <table:table-cell office:value-type="string"><text:p><text:a xlink:href="https://dhtmlx.com">hello</text:a></text:p></table:table-cell>
+                <table:table-cell table:style-name="ce1" office:value-type="percentage" office:value="0.25"><text:p>25.0%</text:p></table:table-cell>
+                <table:table-cell table:number-columns-spanned="2" office:value-type="string"><text:p>merged</text:p></table:table-cell>
+                <table:covered-table-cell/>
+            </table:table-row>
+            <table:table-row table:number-rows-repeated="2">
+                <table:table-cell office:value-type="string"><text:p><text:span text:style-name="T1">bold</text:span> text<office:annotation><text:p>note</text:p></office:annotation></text:p></table:table-cell>
+                <table:table-cell table:number-columns-repeated="2" office:value-type="float" office:value="7" table:formula="of:=SUM([.A1:.B1];1)"><text:p>7</text:p></table:table-cell>
+            </table:table-row>
+            <table:table-row table:number-rows-repeated="1048572"><table:table-cell table:number-columns-repeated="4"/></table:table-row>
+        </table:table>
+        <table:table table:name="Other" table:style-name="ta2"><table:table-row><table:table-cell office:value-type="boolean" office:boolean-value="true"/></table:table-row>
+            <table:named-expressions><table:named-range table:name="Flag" table:base-cell-address="$Other.$A$1" table:cell-range-address="$Other.$A$1"/></table:named-expressions>
+        </table:table>
+        <table:named-expressions>
+            <table:named-range table:name="Values" table:base-cell-address="$Data.$A$1" table:cell-range-address="$Data.$B$2:.$C$3"/>
+            <table:named-expression table:name="Double" table:base-cell-address="$Data.$A$1" table:expression="of:=[.$B$2]*2"/>
+        </table:named-expressions>
+    </office:spreadsheet></office:body></office:document-content>"##;
+    zip_ods(styles, content)
+}
+
+#[cfg(test)]
+fn zip_ods(styles: &[u8], content: &[u8]) -> Vec<u8> {
+    use std::io::Write;
+
+    let parts: Vec<(&str, &[u8])> = vec![
+        ("mimetype", b"application/vnd.oasis.opendocument.spreadsheet"),
+        ("styles.xml", styles),
+        ("content.xml", content),
+    ];
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(vec!()));
+    for (name, content) in parts.iter() {
+        zip.start_file(*name, zip::write::FileOptions::default()).unwrap();
+        zip.write_all(content).unwrap();
+    }
+    zip.finish().unwrap().into_inner()
+}
+
+#[test]
+fn test_ods_sheet() {
+    use crate::{Workbook, WITH_RICH_TEXT, WITH_TYPED_VALUES};
+
+    let mut ods = Workbook::open(&build_ods()).unwrap();
+    assert_eq!(ods.sheet_names(), vec![String::from("Data"), String::from("Other")]);
+
+    let data = ods.sheet_with("Data", WITH_RICH_TEXT | WITH_FORMULAS).unwrap();
+    let value = |row: usize, col: usize| data.cells[row][col].as_ref().and_then(|c| c.v.clone());
+    assert_eq!(data.cells.len(), 3);
+    assert_eq!(data.cells[0].len(), 4);
+    assert_eq!(value(0, 0), Some(CellValue::String(String::from("hello"))));
+    assert_eq!(data.cells[0][0].as_ref().unwrap().hyperlink.as_deref(), Some("https://dhtmlx.com"));
+    assert_eq!(value(0, 1), Some(CellValue::String(String::from("0.25"))));
+    assert_eq!(data.cells[0][1].as_ref().unwrap().s, 2);
+    assert_eq!(value(0, 2), Some(CellValue::String(String::from("merged"))));
+    assert!(data.cells[0][3].is_none());
+    assert_eq!(data.merged.len(), 1);
+    assert_eq!((data.merged[0].from.column, data.merged[0].to.column), (2, 3));
+
+    // repeated rows and columns are expanded
+    for row in 1..3 {
+        assert_eq!(value(row, 1), Some(CellValue::String(String::from("=SUM(A1:B1,1)"))));
+        assert_eq!(value(row, 2), Some(CellValue::String(String::from("=SUM(A1:B1,1)"))));
+        let rich = data.cells[row][0].as_ref().unwrap().rich.as_ref().unwrap();
+        assert_eq!(rich.iter().map(|r| r.text.as_str()).collect::<Vec<&str>>(), vec!["bold", " text"]);
+        assert_eq!(rich[0].style["fontWeight"], "bold");
+    }
+
+    assert_eq!(data.cols[0].width, 108.0 / 0.75 / 7.0 * crate::sheet::WIDTH_COEF);
+    assert_eq!(data.rows[0].height, 36.0 / crate::sheet::HEIGHT_COEF);
+
+    let styles = ods.styles().unwrap();
+    assert_eq!(styles.len(), 3);
+    assert_eq!(styles[0]["fontFamily"], "Liberation Sans");
+    assert_eq!(styles[0]["format"], "General");
+    assert_eq!(styles[1]["fontWeight"], "bold");
+    assert_eq!(styles[2]["fontWeight"], "bold");
+    assert_eq!(styles[2]["format"], "0.0%");
+    assert_eq!(styles[2]["align"], "center");
+    assert_eq!(styles[2]["wrapText"], true);
+    assert_eq!(styles[2]["color"], "rgba(255,0,0,1)");
+    assert_eq!(styles[2]["borderLeft"], "0.5px solid rgba(0,0,0,1)");
+    assert_eq!(styles[2]["background"], "rgba(255,255,0,1)");
+
+    let names = ods.defined_names();
+    assert_eq!(names.len(), 3);
+    assert_eq!((names[0].name.as_str(), names[0].scope.as_deref(), names[0].reference.as_str()), ("Flag", Some("Other"), "Other!$A$1"));
+    assert_eq!((names[1].name.as_str(), names[1].scope.as_deref(), names[1].reference.as_str()), ("Values", None, "Data!$B$2:$C$3"));
+    assert_eq!(names[2].reference, "$B$2*2");
+
+    let sheets = ods.sheets();
+    assert_eq!((sheets[0].id, sheets[0].state), (1, SheetState::Visible));
+    assert_eq!((sheets[1].id, sheets[1].state), (2, SheetState::Hidden));
+    assert_eq!(sheets[1].tab_color.as_deref(), Some("rgba(0,0,255,1)"));
+
+    let other = ods.sheet_with("Other", WITH_TYPED_VALUES).unwrap();
+    assert_eq!(other.cells[0][0].as_ref().unwrap().v, Some(CellValue::Bool(true)));
+    assert!(ods.sheet("Missing").is_err());
+    assert!(ods.differential_styles(0).unwrap().is_empty());
+}
+
+#[test]
+fn test_ods_huge_counts() {
+    use crate::Workbook;
+
+    let content = br#"<office:document-content><office:body><office:spreadsheet><table:table table:name="Data"><table:table-row>
+        <table:table-cell table:number-columns-repeated="4294967295"/>
+        <table:covered-table-cell table:number-columns-repeated="4294967295"/>
+        <table:table-cell table:number-columns-spanned="4294967295" table:number-rows-spanned="4294967295" office:value-type="string"><text:p>x</text:p></table:table-cell>
+        <table:table-cell table:number-columns-repeated="4294967295" office:value-type="string"><text:p>y</text:p></table:table-cell>
+    </table:table-row></table:table></office:spreadsheet></office:body></office:document-content>"#;
+    let mut ods = Workbook::open(&zip_ods(b"<office:document-styles/>", content)).unwrap();
+    let data = ods.sheet("Data").unwrap();
+    assert!(data.cells.len() <= MAX_ROWS as usize);
+    assert!(data.cells.iter().all(|row| row.len() <= MAX_COLS as usize));
+    assert!(data.merged.iter().all(|cell| cell.to.column >= cell.from.column && cell.to.row >= cell.from.row));
+}
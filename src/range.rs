@@ -1,3 +1,5 @@
+use crate::{CellCoords, MergedCell};
+
 pub struct Range {
     pub first: (u32, u32),
     pub last: (u32, u32)
@@ -5,13 +7,18 @@ pub struct Range {
 
 impl Range {
     pub fn new(dimension: String) -> Range {
-        let split = dimension.split(":");
-        let cells: Vec<String> = split.take(2).map(String::from).collect();
-
-        let first = cell_index_to_offsets(cells[0].clone());
-        let last = cell_index_to_offsets(cells[1].clone());
+        Range::parse(&dimension).unwrap()
+    }
+    // "A1:C5" or a single cell "B2", None for malformed references
+    pub fn parse(dimension: &str) -> Option<Range> {
+        let mut split = dimension.split(':');
+        let first = parse_cell_index(split.next()?)?;
+        let last = match split.next() {
+            Some(cell) => parse_cell_index(cell)?,
+            None => first,
+        };
 
-        Range { first, last }
+        Some(Range { first, last })
     }
     pub fn get_max_offsets(&self) -> (u32, u32) {
         (self.last.0 + 1, self.last.1 + 1)
@@ -20,16 +27,125 @@ impl Range {
 
 
 pub fn cell_index_to_offsets(s: String) -> (u32, u32) {
-    let mut alpha = vec!();
+    parse_cell_index(&s).unwrap()
+}
+
+// "AB12" -> (27, 11), None when the reference has no column or row part
+pub fn parse_cell_index(s: &str) -> Option<(u32, u32)> {
+    let mut alpha_part: u32 = 0;
     let mut number_part: u32 = 0;
-    for byte in s.into_bytes() {
-        if byte >= 65 && byte <= 90 { // A - Z
-            alpha.push(byte as u32 - 64);
-        } else { // 0 - 9
-            number_part = number_part * 10 + byte as u32 - 48;
+    let mut has_digits = false;
+    for byte in s.bytes() {
+        match byte {
+            b'A'..=b'Z' if !has_digits => {
+                alpha_part = alpha_part.checked_mul(26)?.checked_add((byte - b'A' + 1) as u32)?;
+            },
+            b'0'..=b'9' => {
+                has_digits = true;
+                number_part = number_part.checked_mul(10)?.checked_add((byte - b'0') as u32)?;
+            },
+            _ => return None,
+        }
+    }
+    if alpha_part == 0 || number_part == 0 {
+        return None;
+    }
+    Some((alpha_part - 1, number_part - 1))
+}
+
+// (27, 11) -> "AB12"
+pub fn cell_name(col: u32, row: u32) -> String {
+    let mut letters = vec!();
+    let mut col = col + 1;
+    while col > 0 {
+        letters.push((b'A' + ((col - 1) % 26) as u8) as char);
+        col = (col - 1) / 26;
+    }
+    letters.iter().rev().collect::<String>() + &(row + 1).to_string()
+}
+
+// "Sheet2!$A$1:$A$20", "'My sheet'!A:A" or "B2:B5" of the current sheet -> sheet name and range
+pub fn parse_reference(reference: &str) -> Option<(Option<String>, Range)> {
+    let reference = reference.trim().trim_start_matches('=');
+    let (sheet, range) = if let Some(quoted) = reference.strip_prefix('\'') {
+        // quotes inside of the name are doubled
+        let mut name = String::new();
+        let mut chars = quoted.char_indices().peekable();
+        let mut end = None;
+        while let Some((i, c)) = chars.next() {
+            if c == '\'' {
+                if chars.peek().map(|(_, next)| *next) == Some('\'') {
+                    chars.next();
+                } else {
+                    end = Some(i + 1);
+                    break;
+                }
+            }
+            name.push(c);
         }
+        (Some(name), quoted[end?..].strip_prefix('!')?)
+    } else {
+        match reference.rsplit_once('!') {
+            Some((sheet, range)) => (Some(sheet.to_string()), range),
+            None => (None, reference),
+        }
+    };
+    let range = range.replace('$', "");
+    if let Some(range) = Range::parse(&range) {
+        return Some((sheet, range));
     }
-    let len = alpha.len() as u32;
-    let alpha_part = alpha.iter().enumerate().fold(0, |s, (index, v)| s + 26u32.pow(len - index as u32 - 1) * v);
-    (alpha_part - 1, number_part - 1)
-}
\ No newline at end of file
+    // whole columns "A:C" or whole rows "1:3"
+    let (first, last) = range.split_once(':')?;
+    let range = if first.bytes().all(|b| b.is_ascii_uppercase()) && last.bytes().all(|b| b.is_ascii_uppercase()) {
+        Range { first: (parse_cell_index(&format!("{}1", first))?.0, 0), last: (parse_cell_index(&format!("{}1", last))?.0, 1048575) }
+    } else {
+        Range { first: (0, first.parse::<u32>().ok()?.checked_sub(1)?), last: (16383, last.parse::<u32>().ok()?.checked_sub(1)?) }
+    };
+    Some((sheet, range))
+}
+
+// space separated ranges of `sqref`, malformed ones are skipped
+pub(crate) fn sqref_ranges(sqref: &str) -> Vec<MergedCell> {
+    sqref.split_whitespace()
+        .filter_map(parse_reference)
+        .map(|(_, range)| MergedCell {
+            from: CellCoords { column: range.first.0, row: range.first.1 },
+            to: CellCoords { column: range.last.0, row: range.last.1 },
+        })
+        .collect()
+}
+
+#[test]
+fn test_parse_cell_index() {
+    assert_eq!(parse_cell_index("A1"), Some((0, 0)));
+    assert_eq!(parse_cell_index("XFD1048576"), Some((16383, 1048575)));
+    assert_eq!(parse_cell_index("A0"), None);
+    assert_eq!(parse_cell_index("12"), None);
+    assert_eq!(parse_cell_index("A1B"), None);
+
+    let range = Range::parse("B2").unwrap();
+    assert_eq!(range.first, range.last);
+    assert!(Range::parse("A1:").is_none());
+
+    assert_eq!(cell_name(27, 11), "AB12");
+    assert_eq!(cell_name(16383, 0), "XFD1");
+}
+
+#[test]
+fn test_parse_reference() {
+    let (sheet, range) = parse_reference("Sheet2!$A$1:$A$20").unwrap();
+    assert_eq!(sheet.as_deref(), Some("Sheet2"));
+    assert_eq!((range.first, range.last), ((0, 0), (0, 19)));
+
+    let (sheet, range) = parse_reference("='It''s data'!B:C").unwrap();
+    assert_eq!(sheet.as_deref(), Some("It's data"));
+    assert_eq!((range.first, range.last), ((1, 0), (2, 1048575)));
+
+    let (sheet, range) = parse_reference("B2").unwrap();
+    assert_eq!(sheet, None);
+    assert_eq!(range.first, range.last);
+
+    assert!(parse_reference("2:3").is_some());
+    assert!(parse_reference("Names").is_none());
+    assert!(parse_reference("'Sheet1!A1").is_none());
+}
@@ -0,0 +1,169 @@
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::reader::Reader as XmlReader;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::io::BufRead;
+
+use crate::theme::Theme;
+use crate::{attr_value, invalid_attribute, read_color, xml_error, Dict, XlsxError, PT_COEF};
+
+// part of rich text with own font, style uses the same keys as cell styles
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TextRun {
+    pub text: String,
+    pub style: Dict,
+}
+
+// reads content of <si> or <is> element, after its start tag and up to the `end` tag
+// returns full text and runs, runs are None for plain strings without <r> elements
+pub fn read_string_item<R: BufRead>(xml: &mut XmlReader<R>, part: &str, end: &[u8], theme: &Theme) -> Result<(String, Option<Vec<TextRun>>), XlsxError> {
+    let mut buf = Vec::new();
+    let mut text = String::new();
+    let mut runs: Vec<TextRun> = vec!();
+    let mut in_text = false;
+    let mut in_run_props = false;
+    // phonetic hints are not a part of displayed text
+    let mut ignore = false;
+
+    loop {
+        buf.clear();
+        match xml.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) if e.name().as_ref() == b"rPh" || e.name().as_ref() == b"phoneticPr" => {
+                ignore = true;
+            },
+            Ok(Event::End(ref e)) if e.name().as_ref() == b"rPh" || e.name().as_ref() == b"phoneticPr" => {
+                ignore = false;
+            },
+            Ok(Event::Start(ref e)) if !ignore && e.name().as_ref() == b"r" => {
+                runs.push(TextRun { text: String::new(), style: Dict::new() });
+            },
+            Ok(Event::Start(ref e)) if !ignore && e.name().as_ref() == b"rPr" => {
+                in_run_props = true;
+            },
+            Ok(Event::End(ref e)) if e.name().as_ref() == b"rPr" => {
+                in_run_props = false;
+            },
+            Ok(Event::Start(ref e)) if in_run_props => {
+                if let Some(run) = runs.last_mut() {
+                    read_run_property(xml, part, e, &mut run.style, theme)?;
+                }
+            },
+            Ok(Event::Start(ref e)) if !ignore && e.name().as_ref() == b"t" => {
+                in_text = true;
+            },
+            Ok(Event::End(ref e)) if e.name().as_ref() == b"t" => {
+                in_text = false;
+            },
+            Ok(Event::Text(ref e)) if in_text && !ignore => {
+                let value = e.unescape().map_err(|e| xml_error(part, xml, e))?;
+                text.push_str(value.as_ref());
+                if let Some(run) = runs.last_mut() {
+                    run.text.push_str(value.as_ref());
+                }
+            },
+            Ok(Event::End(ref e)) if e.name().as_ref() == end => break,
+            Ok(Event::Eof) => return Err(xml_error(part, xml, "unexpected end of file")),
+            Err(e) => return Err(xml_error(part, xml, e)),
+            _ => (),
+        }
+    }
+
+    if runs.is_empty() {
+        Ok((text, None))
+    } else {
+        Ok((text, Some(runs)))
+    }
+}
+
+fn read_run_property<R>(xml: &XmlReader<R>, part: &str, e: &BytesStart, style: &mut Dict, theme: &Theme) -> Result<(), XlsxError> {
+    let mut val: Option<String> = None;
+    for a in e.attributes() {
+        let att = a.map_err(|e| xml_error(part, xml, e))?;
+        if att.key.as_ref() == b"val" {
+            val = Some(attr_value(xml, part, &att)?);
+        }
+    }
+    // boolean properties are on without val, <b val="0"/> turns them off
+    let on = !matches!(val.as_deref(), Some("0") | Some("false") | Some("none"));
+
+    match e.name().as_ref() {
+        b"rFont" => {
+            if let Some(name) = val {
+                style.insert(String::from("fontFamily"), JsonValue::String(name));
+            }
+        },
+        b"sz" => {
+            if let Some(value) = val {
+                let size = value.parse::<f32>().map_err(|_| invalid_attribute(part, "val", &value))?;
+                style.insert(String::from("fontSize"), JsonValue::String((size / PT_COEF).to_string() + "px"));
+            }
+        },
+        b"color" => {
+            if let Some(color) = read_color(xml, part, e, theme)? {
+                style.insert(String::from("color"), JsonValue::String(color));
+            }
+        },
+        b"b" if on => {
+            style.insert(String::from("fontWeight"), JsonValue::String(String::from("bold")));
+        },
+        b"i" if on => {
+            style.insert(String::from("fontStyle"), JsonValue::String(String::from("italic")));
+        },
+        b"u" if on => add_decoration(style, "underline"),
+        b"strike" if on => add_decoration(style, "line-through"),
+        b"vertAlign" => match val.as_deref() {
+            Some("superscript") => {
+                style.insert(String::from("verticalAlign"), JsonValue::String(String::from("super")));
+            },
+            Some("subscript") => {
+                style.insert(String::from("verticalAlign"), JsonValue::String(String::from("sub")));
+            },
+            _ => (),
+        },
+        _ => (),
+    }
+    Ok(())
+}
+
+fn add_decoration(style: &mut Dict, value: &str) {
+    if style.contains_key("textDecoration") {
+        style.insert(String::from("textDecoration"), JsonValue::String(String::from("line-through underline")));
+    } else {
+        style.insert(String::from("textDecoration"), JsonValue::String(value.to_string()));
+    }
+}
+
+
+#[test]
+fn test_read_string_item() {
+    let source = concat!(
+        r#"<si><r><rPr><b/><sz val="12"/><color rgb="FFFF0000"/><rFont val="Arial"/></rPr><t>Bold</t></r>"#,
+        r#"<r><rPr><i/><color theme="4"/><b val="0"/><u/><strike/><vertAlign val="superscript"/></rPr><t xml:space="preserve"> rest</t></r>"#,
+        r#"<rPh sb="0" eb="1"><t>ignored</t></rPh></si>"#,
+    );
+    let mut xml = XmlReader::from_str(source);
+    xml.expand_empty_elements(true);
+    xml.read_event().unwrap();
+
+    let (text, runs) = read_string_item(&mut xml, "xl/sharedStrings.xml", b"si", &Theme::default()).unwrap();
+    let runs = runs.unwrap();
+    assert_eq!(text, "Bold rest");
+    assert_eq!(runs.len(), 2);
+    assert_eq!(runs[0].text, "Bold");
+    assert_eq!(runs[0].style["fontWeight"], "bold");
+    assert_eq!(runs[0].style["fontSize"], "16px");
+    assert_eq!(runs[0].style["color"], "rgba(255,0,0,1)");
+    assert_eq!(runs[0].style["fontFamily"], "Arial");
+    assert_eq!(runs[1].text, " rest");
+    assert_eq!(runs[1].style.get("fontWeight"), None);
+    assert_eq!(runs[1].style["fontStyle"], "italic");
+    assert_eq!(runs[1].style["color"], "rgba(68,114,196,1)");
+    assert_eq!(runs[1].style["textDecoration"], "line-through underline");
+    assert_eq!(runs[1].style["verticalAlign"], "super");
+
+    let mut xml = XmlReader::from_str("<si><t>plain</t></si>");
+    xml.read_event().unwrap();
+    let (text, runs) = read_string_item(&mut xml, "xl/sharedStrings.xml", b"si", &Theme::default()).unwrap();
+    assert_eq!(text, "plain");
+    assert!(runs.is_none());
+}
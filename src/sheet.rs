@@ -0,0 +1,819 @@
+// reading worksheet xml row by row
+use quick_xml::events::Event;
+use quick_xml::reader::Reader as XmlReader;
+use serde::Serialize;
+use zip::read::ZipFile;
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, BufReader};
+
+use crate::date::{self, DateKind};
+use crate::format;
+use crate::range::{Range, parse_cell_index};
+use crate::rich_text::{self, TextRun};
+use crate::theme::Theme;
+use crate::validation;
+use crate::conditional_format;
+use crate::workbook::{PartReader, Workbook};
+use crate::{Cell, CellCoords, CellType, CellValue, ColumnData, MergedCell, RowData, SheetData, SparseCell, XlsxError};
+use crate::{WITH_DATES, WITH_FORMATTED_TEXT, WITH_FORMULAS, WITH_RICH_TEXT, WITH_SPARSE_CELLS, WITH_TYPED_VALUES};
+use crate::{attr_parse, attr_value, invalid_attribute, xml_error};
+
+// default sheet settings
+pub(crate) const DEFAULT_CELL_WIDTH: f32 = 15.75;
+pub(crate) const DEFAULT_CELL_HEIGHT: f32 = 14.25;
+pub(crate) const WIDTH_COEF: f32 = 8.5;
+pub(crate) const HEIGHT_COEF: f32 = 0.75;
+
+struct SheetInfo {
+    cols_count: u32,
+    next_col: u32,
+    next_row: u32,
+    default_col_width: f32,
+    default_row_height: f32,
+    use_shared_string_for_next: bool,
+    cell_type: CellType,
+}
+
+impl SheetInfo {
+    pub fn new() -> SheetInfo {
+        SheetInfo {
+            cols_count: 0,
+            next_col: 0,
+            next_row: 0,
+            default_col_width: DEFAULT_CELL_WIDTH * WIDTH_COEF,
+            default_row_height: DEFAULT_CELL_HEIGHT / HEIGHT_COEF,
+            use_shared_string_for_next: false,
+            cell_type: CellType::Number,
+        }
+    }
+}
+
+// row of the sheet with populated cells only, `index` and cell coordinates start from 0
+#[derive(Serialize)]
+pub struct Row {
+    pub index: u32,
+    pub height: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hidden: Option<bool>,
+    pub cells: Vec<SparseCell>,
+}
+
+// workbook parts shared by all sheets
+pub(crate) struct CellContext<'a> {
+    pub shared_strings: &'a [String],
+    pub rich_strings: &'a HashMap<usize, Vec<TextRun>>,
+    pub theme: &'a Theme,
+    pub cell_formats: &'a [String],
+}
+
+// conversion of values, as they are stored in the file, to cell values
+pub(crate) struct CellOptions {
+    pub flags: u32,
+    date_formats: Vec<Option<DateKind>>,
+    date1904: bool,
+    // colors of number formats, like [Color10]
+    palette: Theme,
+}
+
+impl CellOptions {
+    pub fn new(flags: u32, date1904: bool, cell_formats: &[String], palette: &Theme) -> CellOptions {
+        let date_formats = if flags & WITH_DATES > 0 {
+            cell_formats.iter().map(|f| date::date_kind(f)).collect()
+        } else {
+            vec!()
+        };
+        CellOptions { flags, date_formats, date1904, palette: palette.clone() }
+    }
+    // sets `v` and `w` of the cell, returns type of the value, which is Date for numbers with date format
+    pub fn set_value(&self, cell: &mut Cell, value: String, cell_type: CellType, cell_formats: &[String]) -> CellType {
+        // formula cells have cached value of the formula, it is used for the text
+        if self.flags & WITH_FORMATTED_TEXT > 0 && !value.is_empty() {
+            let code = cell_formats.get(cell.s as usize).map(|f| f.as_str()).unwrap_or("General");
+            let formatted = format::format_cell(&value, cell_type, code, self.date1904, &self.palette);
+            cell.w = Some(formatted.text);
+            cell.text_color = formatted.color;
+        }
+        if cell.v.is_some() {
+            return cell_type;
+        }
+
+        let date = match self.date_formats.get(cell.s as usize) {
+            Some(Some(kind)) if cell_type == CellType::Number => value.trim().parse::<f64>().ok()
+                .and_then(|serial| date::serial_to_iso(serial, *kind, self.date1904)),
+            _ => None,
+        };
+        if cell_type == CellType::String {
+            if !value.is_empty() {
+                cell.v = Some(CellValue::String(value));
+            }
+        } else if let Some(iso) = date {
+            cell.v = Some(CellValue::String(iso));
+            return CellType::Date;
+        } else if self.flags & WITH_TYPED_VALUES > 0 {
+            cell.v = Some(CellValue::typed(value, cell_type));
+        } else {
+            cell.v = Some(CellValue::String(value));
+        }
+        cell_type
+    }
+    // sets `v`, `w` and `rich` of the cell with a string value
+    pub fn set_text(&self, cell: &mut Cell, text: String, runs: Option<Vec<TextRun>>, cell_formats: &[String]) {
+        if self.flags & WITH_FORMATTED_TEXT > 0 && !text.is_empty() {
+            let code = cell_formats.get(cell.s as usize).map(|f| f.as_str()).unwrap_or("General");
+            let formatted = format::format_text(&text, code, &self.palette);
+            cell.w = Some(formatted.text);
+            cell.text_color = formatted.color;
+        }
+        if !text.is_empty() && cell.v.is_none() {
+            if self.flags & WITH_RICH_TEXT > 0 {
+                cell.rich = runs;
+            }
+            cell.v = Some(CellValue::String(text));
+        }
+    }
+}
+
+// type is set only for cells with a value
+pub(crate) fn set_type(cell: &mut Cell, cell_type: CellType) {
+    if cell.v.is_some() {
+        cell.t = Some(cell_type);
+    }
+}
+
+pub(crate) fn number_text(value: f64) -> String {
+    value.to_string()
+}
+
+// parsing state of a single worksheet, the reader is pulled row by row
+pub(crate) struct SheetReader<R: BufRead> {
+    xml: XmlReader<R>,
+    part: String,
+    // uncompressed size of the part, for progress
+    size: u64,
+    options: CellOptions,
+    // hyperlink targets by relationship id
+    links: HashMap<String, String>,
+    // hyperlink targets by cell name, <hyperlinks> is placed after <sheetData>
+    hyperlinks: HashMap<String, String>,
+    info: SheetInfo,
+    // sheet properties, cells and rows are not stored here
+    data: SheetData,
+    row: Option<Row>,
+    cell: Cell,
+    col: u32,
+    cell_name: Option<String>,
+    mode: u8,
+    done: bool,
+}
+
+impl<R: BufRead> SheetReader<R> {
+    pub fn new(xml: XmlReader<R>, part: &str, size: u64, name: String, options: CellOptions) -> SheetReader<R> {
+        SheetReader {
+            xml,
+            part: part.to_string(),
+            size,
+            options,
+            links: HashMap::new(),
+            hyperlinks: HashMap::new(),
+            info: SheetInfo::new(),
+            data: SheetData::new(name),
+            row: None,
+            cell: Cell::new(),
+            col: 0,
+            cell_name: None,
+            mode: 0,
+            done: false,
+        }
+    }
+    pub fn set_links(&mut self, links: HashMap<String, String>) {
+        self.links = links;
+    }
+    // part of the sheet xml read so far, 0 - 1
+    pub fn progress(&self) -> f64 {
+        if self.done || self.size == 0 {
+            return 1.0;
+        }
+        (self.xml.buffer_position() as f64 / self.size as f64).min(1.0)
+    }
+    // reads till the end of the next <row>, None after the end of the sheet
+    pub fn next_row(&mut self, ctx: &CellContext) -> Result<Option<Row>, XlsxError> {
+        let with_rich = self.options.flags & WITH_RICH_TEXT > 0;
+        let part = self.part.as_str();
+        let xml = &mut self.xml;
+        let info = &mut self.info;
+        let data = &mut self.data;
+        let mut buf = Vec::new();
+
+        if self.done {
+            return Ok(None);
+        }
+
+        loop {
+            buf.clear();
+            match xml.read_event_into(&mut buf) {
+                Ok(Event::Start(ref e)) if e.name().as_ref() == b"sheetFormatPr" => {
+                    for a in e.attributes() {
+                        let att = a.map_err(|e| xml_error(part, xml, e))?;
+                        match att.key.as_ref() {
+                            b"tdefaultRowHeight" => {
+                                info.default_row_height = attr_parse::<f32, _>(xml, part, &att)? / HEIGHT_COEF;
+                            },
+                            b"defaultColWidth" => {
+                                info.default_col_width = attr_parse::<f32, _>(xml, part, &att)? * WIDTH_COEF;
+                            },
+                            _ => ()
+                        }
+                    }
+                },
+                Ok(Event::Start(ref e)) if e.name().as_ref() == b"col" => {
+                    let mut min = 0;
+                    let mut max = 0;
+                    let mut width = 0.0;
+                    let mut use_custom_width = false;
+                    let mut hidden: Option<bool> = None;
+
+                    for a in e.attributes() {
+                        let att = a.map_err(|e| xml_error(part, xml, e))?;
+                        match att.key.as_ref() {
+                            b"width" => {
+                                width = attr_parse::<f32, _>(xml, part, &att)? * WIDTH_COEF;
+                            },
+                            b"min" => {
+                                min = attr_parse::<usize, _>(xml, part, &att)?;
+                            },
+                            b"max" => {
+                                max = attr_parse::<usize, _>(xml, part, &att)?;
+                            },
+                            b"customWidth" => {
+                                let v = attr_value(xml, part, &att)?;
+                                use_custom_width = v == "1" || v == "true";
+                            },
+                            b"hidden" => {
+                                let v = attr_value(xml, part, &att)?;
+                                hidden = Some(v == "1" || v == "true");
+                            },
+                            _ => ()
+                        }
+                    }
+                    if use_custom_width {
+                        for i in data.cols.len()..max {
+                            if i >= min.saturating_sub(1) {
+                                data.cols.push(ColumnData {width, hidden, index: None});
+                            } else {
+                                data.cols.push(ColumnData {width: info.default_col_width, hidden: None, index: None});
+                            }
+                        }
+                    }
+                },
+                Ok(Event::Start(ref e)) if e.name().as_ref() == b"row" => {
+                    let mut height = 0.0;
+                    let mut index = None;
+                    let mut hidden: Option<bool> = None;
+
+                    for a in e.attributes() {
+                        let att = a.map_err(|e| xml_error(part, xml, e))?;
+                        match att.key.as_ref() {
+                            b"ht" => {
+                                height = attr_parse::<f32, _>(xml, part, &att)? / HEIGHT_COEF;
+                            },
+                            b"r" => {
+                                let value = attr_parse::<u32, _>(xml, part, &att)?;
+                                if value == 0 {
+                                    return Err(invalid_attribute(part, "r", "0"));
+                                }
+                                index = Some(value);
+                            },
+                            b"hidden" => {
+                                let v = attr_value(xml, part, &att)?;
+                                hidden = Some(v == "1" || v == "true");
+                            },
+                            _ => ()
+                        }
+                    }
+                    // row index is optional, in such case row follows the previous one
+                    let index = index.unwrap_or(info.next_row + 1);
+                    info.next_row = index;
+                    info.next_col = 0;
+
+                    let height = if height > 0.0 { height } else { info.default_row_height };
+                    self.row = Some(Row { index: index - 1, height, hidden, cells: vec!() });
+                },
+                Ok(Event::End(ref e)) if e.name().as_ref() == b"row" => {
+                    if let Some(row) = self.row.take() {
+                        return Ok(Some(row));
+                    }
+                },
+                Ok(Event::Start(ref e)) if e.name().as_ref() == b"c" => {
+                    info.use_shared_string_for_next = false;
+                    info.cell_type = CellType::Number;
+                    let mut col = None;
+
+                    for a in e.attributes() {
+                        let att = a.map_err(|e| xml_error(part, xml, e))?;
+                        match att.key.as_ref() {
+                            b"t" => {
+                                let t = attr_value(xml, part, &att)?;
+                                info.use_shared_string_for_next = t == "s";
+                                info.cell_type = CellType::from_xlsx(&t);
+                            },
+                            b"s" => {
+                                self.cell.s = attr_parse::<u32, _>(xml, part, &att)?;
+                            },
+                            b"r" => {
+                                let cell_name = attr_value(xml, part, &att)?;
+                                let (c, _) = parse_cell_index(&cell_name)
+                                    .ok_or_else(|| invalid_attribute(part, "r", &cell_name))?;
+                                col = Some(c);
+                                self.cell_name = Some(cell_name);
+                            },
+                            _ => ()
+                        }
+                    }
+                    if self.row.is_none() {
+                        return Err(xml_error(part, xml, "cell outside of a row"));
+                    }
+
+                    // cell reference is optional, in such case cell follows the previous one
+                    let col = col.unwrap_or(info.next_col);
+                    if col + 1 > info.cols_count {
+                        info.cols_count = col + 1;
+                    }
+                    info.next_col = col + 1;
+                    self.col = col;
+                },
+                Ok(Event::End(ref e)) if e.name().as_ref() == b"c" => {
+                    let mut cell = std::mem::take(&mut self.cell);
+                    let has_value = cell.v.is_some() || cell.s > 0;
+                    let hyperlinks = &self.hyperlinks;
+                    let link = self.cell_name.take().and_then(|name| hyperlinks.get(&name));
+
+                    if cell.v.is_some() {
+                        cell.t = Some(info.cell_type);
+                    }
+                    if has_value || link.is_some() {
+                        cell.hyperlink = link.cloned();
+                        if let Some(row) = self.row.as_mut() {
+                            row.cells.push(SparseCell { r: row.index, c: self.col, cell });
+                        }
+                    }
+                },
+                Ok(Event::Start(ref e)) if e.name().as_ref() == b"f" => {
+                    self.mode = 1
+                }
+                Ok(Event::Start(ref e)) if e.name().as_ref() == b"v" => {
+                    self.mode = 2
+                },
+                Ok(Event::Text(ref e)) if self.mode == 1 && self.options.flags & WITH_FORMULAS > 0 => {
+                    let value = e.unescape().map_err(|e| xml_error(part, xml, e))?.to_string();
+                    self.cell.v = Some(CellValue::String("=".to_owned() + &value));
+                }
+                Ok(Event::Text(ref e)) if self.mode == 2 => {
+                    let last_cell = &mut self.cell;
+                    let value = e.unescape().map_err(|e| xml_error(part, xml, e))?.to_string();
+                    let value = if info.use_shared_string_for_next {
+                        let index = value.trim().parse::<usize>().ok();
+                        let text = index.and_then(|index| ctx.shared_strings.get(index));
+                        if with_rich && last_cell.v.is_none() {
+                            last_cell.rich = index.and_then(|index| ctx.rich_strings.get(&index)).cloned();
+                        }
+                        match text {
+                            Some(text) => text.to_owned(),
+                            None => {
+                                let reason = format!("invalid shared string index {}", value);
+                                return Err(xml_error(part, xml, reason));
+                            }
+                        }
+                    } else {
+                        value
+                    };
+
+                    let cell_type = if info.use_shared_string_for_next { CellType::String } else { info.cell_type };
+                    info.cell_type = self.options.set_value(last_cell, value, cell_type, ctx.cell_formats);
+                },
+                Ok(Event::End(ref e)) if e.name().as_ref() == b"f" => {
+                    self.mode = 0
+                }
+                Ok(Event::End(ref e)) if e.name().as_ref() == b"v" => {
+                    self.mode = 0
+                },
+                // inline strings, plain <is><t> or rich text runs <is><r><t>
+                Ok(Event::Start(ref e)) if e.name().as_ref() == b"is" => {
+                    let (text, runs) = rich_text::read_string_item(xml, part, b"is", ctx.theme)?;
+                    self.options.set_text(&mut self.cell, text, runs, ctx.cell_formats);
+                },
+                Ok(Event::Start(ref e)) if e.name().as_ref() == b"mergeCell" => {
+                    for a in e.attributes() {
+                        let att = a.map_err(|e| xml_error(part, xml, e))?;
+                        if att.key.as_ref() == b"ref" {
+                            let raw_merged_cell = attr_value(xml, part, &att)?;
+                            let range = Range::parse(&raw_merged_cell)
+                                .ok_or_else(|| invalid_attribute(part, "ref", &raw_merged_cell))?;
+
+                            let from = range.first;
+                            let to = range.last;
+
+                            let merged_cell = MergedCell {
+                                from: CellCoords { column: from.0, row: from.1 },
+                                to: CellCoords { column: to.0, row: to.1 }
+                            };
+                            data.merged.push(merged_cell);
+                        }
+                    }
+                },
+                Ok(Event::End(ref e)) if e.name().as_ref() == b"worksheet" => {
+                    self.done = true;
+                    return Ok(None);
+                },
+                Ok(Event::Start(ref e)) if e.name().as_ref() == b"pane" => {
+                    for att in e.attributes().flatten() {
+                        match att.key.as_ref() {
+                            b"xSplit" => {
+                                if let Ok(val) = att.decode_and_unescape_value(xml) {
+                                    if let Ok(n) = val.parse::<f64>() {
+                                        data.frozen_cols = Some(n as u32);
+                                    }
+                                }
+                            },
+                            b"ySplit" => {
+                                if let Ok(val) = att.decode_and_unescape_value(xml) {
+                                    if let Ok(n) = val.parse::<f64>() {
+                                        data.frozen_rows = Some(n as u32);
+                                    }
+                                }
+                            },
+                            _ => (),
+                        }
+                    }
+                },
+                // x14:dataValidation of the extension list has the same attributes
+                Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"dataValidation" => {
+                    let validation = validation::read_data_validation(xml, part, e)?;
+                    if !validation.range.is_empty() {
+                        data.validations.push(validation);
+                    }
+                },
+                // x14:conditionalFormatting of the extension list is skipped, it has the same name
+                Ok(Event::Start(ref e)) if e.name().as_ref() == b"conditionalFormatting" => {
+                    let format = conditional_format::read_conditional_format(xml, part, e, ctx.theme)?;
+                    if !format.range.is_empty() && !format.rules.is_empty() {
+                        data.conditional_formats.push(format);
+                    }
+                },
+                Ok(Event::Start(ref e)) if e.name().as_ref() == b"hyperlink" => {
+                    let mut cell_ref = None;
+                    let mut rel_id = None;
+
+                    for a in e.attributes() {
+                        let att = a.map_err(|e| xml_error(part, xml, e))?;
+                        match att.key.as_ref() {
+                            b"ref" => {
+                                cell_ref = Some(attr_value(xml, part, &att)?);
+                            },
+                            b"r:id" => {
+                                rel_id = Some(attr_value(xml, part, &att)?);
+                            },
+                            _ => (),
+                        }
+                    }
+
+                    if let (Some(cell), Some(id)) = (cell_ref, rel_id) {
+                        if let Some(link) = self.links.get(&id) {
+                            self.hyperlinks.insert(cell, link.clone());
+                        }
+                    }
+                }
+                Ok(Event::Eof) => return Err(xml_error(part, xml, "unexpected end of document")),
+                Err(e) => return Err(xml_error(part, xml, e)),
+                _ => ()
+            }
+        }
+    }
+    // reads the whole sheet, see `SheetBuilder`
+    pub fn read_all(mut self, ctx: &CellContext) -> Result<SheetData, XlsxError> {
+        let mut builder = SheetBuilder::new(self.options.flags);
+        while let Some(row) = self.next_row(ctx)? {
+            builder.add_row(row);
+        }
+        let info = &self.info;
+        Ok(builder.finish(self.data, info.cols_count, info.default_col_width, info.default_row_height, self.hyperlinks))
+    }
+}
+
+// collects rows of the sheet, rows are padded with empty cells to the same length
+// with WITH_SPARSE_CELLS only populated cells are stored, in `sparse_cells`, and only custom rows and columns are listed
+pub(crate) struct SheetBuilder {
+    sparse: bool,
+    cells: Vec<Vec<Option<Cell>>>,
+    sparse_cells: Vec<SparseCell>,
+    // rows missed in the file are added with default height
+    rows: Vec<Option<RowData>>,
+    rows_count: usize,
+}
+
+impl SheetBuilder {
+    pub fn new(flags: u32) -> SheetBuilder {
+        SheetBuilder {
+            sparse: flags & WITH_SPARSE_CELLS > 0,
+            cells: vec!(),
+            sparse_cells: vec!(),
+            rows: vec!(),
+            rows_count: 0,
+        }
+    }
+    pub fn add_row(&mut self, row: Row) {
+        let index = row.index as usize;
+        for _ in self.rows.len()..index {
+            self.rows.push(None);
+        }
+        self.rows.push(Some(RowData { height: row.height, hidden: row.hidden, index: None }));
+        if !row.cells.is_empty() {
+            self.rows_count = self.rows_count.max(index + 1);
+        }
+
+        if self.sparse {
+            self.sparse_cells.extend(row.cells);
+            return;
+        }
+        for _ in self.cells.len()..index + 1 {
+            self.cells.push(vec!());
+        }
+        let cols = &mut self.cells[index];
+        for cell in row.cells {
+            for _ in cols.len()..cell.c as usize {
+                cols.push(None);
+            }
+            cols.push(Some(cell.cell));
+        }
+    }
+    // `data` has sheet properties, hyperlinks are by cell name
+    pub fn finish(self, mut data: SheetData, cols_count: u32, default_col_width: f32, default_row_height: f32, hyperlinks: HashMap<String, String>) -> SheetData {
+        let rows_count = self.rows_count;
+        let mut cells = self.cells;
+        // rows without cells at the end of the sheet
+        cells.truncate(rows_count);
+        for row in cells.iter_mut() {
+            let missed_cols_count = cols_count as usize - row.len();
+            if missed_cols_count != 0 {
+                row.extend((0..missed_cols_count).map(|_| None));
+            }
+        }
+        let rows: Vec<RowData> = if self.sparse {
+            // only rows and columns which differ from the default ones, with their index
+            data.cols = data.cols.into_iter()
+                .take(cols_count as usize)
+                .enumerate()
+                .filter(|(_, col)| col.width != default_col_width || col.hidden.is_some())
+                .map(|(i, col)| ColumnData {index: Some(i as u32), ..col})
+                .collect();
+            self.rows.into_iter()
+                .take(rows_count)
+                .enumerate()
+                .filter_map(|(i, row)| row.map(|row| RowData {index: Some(i as u32), ..row}))
+                .filter(|row| row.height != default_row_height || row.hidden.is_some())
+                .collect()
+        } else {
+            let mut rows: Vec<RowData> = self.rows.into_iter()
+                .take(rows_count)
+                .map(|row| row.unwrap_or(RowData {height: default_row_height, hidden: None, index: None}))
+                .collect();
+            rows.extend((rows.len()..rows_count).map(|_| RowData {height: default_row_height, hidden: None, index: None}));
+
+            let missed_col_data_count = cols_count as i32 - data.cols.len() as i32;
+            if missed_col_data_count < 0 {
+                data.cols.truncate(cols_count as usize);
+            } else {
+                data.cols.extend((0..missed_col_data_count).map(|_| ColumnData {width: default_col_width, hidden: None, index: None}));
+            }
+            rows
+        };
+
+        let mut links = HashMap::new();
+        for (cell_name, link) in hyperlinks {
+            if let Some(offsets) = parse_cell_index(&cell_name) {
+                links.insert(offsets, link);
+            }
+        }
+        if self.sparse {
+            let mut sparse_cells = self.sparse_cells;
+            for cell in sparse_cells.iter_mut() {
+                if let Some(link) = links.remove(&(cell.c, cell.r)) {
+                    cell.cell.hyperlink = Some(link);
+                }
+            }
+            data.sparse_cells = Some(sparse_cells);
+        } else {
+            for ((col, row), link) in links {
+                if let Some(Some(cell)) = cells.get_mut(row as usize).and_then(|r| r.get_mut(col as usize)) {
+                    cell.hyperlink = Some(link);
+                }
+            }
+        }
+        data.rows = rows;
+        data.cells = cells;
+        data
+    }
+}
+
+// rows of a sheet, read while iterating
+// hyperlinks are listed after all rows, so cells don't have them
+pub struct Rows<'a> {
+    source: RowSource<'a>,
+}
+
+enum RowSource<'a> {
+    Xml(Box<SheetReader<BufReader<ZipFile<'a>>>>, CellContext<'a>),
+    Parsed(Box<ParsedRows>),
+}
+
+// rows of formats which are read at once
+pub(crate) struct ParsedRows {
+    rows: std::vec::IntoIter<Row>,
+    count: usize,
+    data: SheetData,
+    size: ParsedSheetSize,
+    flags: u32,
+}
+
+impl ParsedRows {
+    fn new(data: SheetData, rows: Vec<Row>, size: ParsedSheetSize, flags: u32) -> ParsedRows {
+        ParsedRows { count: rows.len(), rows: rows.into_iter(), data, size, flags }
+    }
+    // the rows which are not taken yet, see `SheetBuilder`
+    fn into_sheet(self) -> SheetData {
+        let (cols_count, default_col_width, default_row_height) = self.size;
+        let mut builder = SheetBuilder::new(self.flags);
+        for row in self.rows {
+            builder.add_row(row);
+        }
+        builder.finish(self.data, cols_count, default_col_width, default_row_height, HashMap::new())
+    }
+    fn progress(&self) -> f64 {
+        if self.count == 0 {
+            return 1.0;
+        }
+        1.0 - self.rows.len() as f64 / self.count as f64
+    }
+}
+
+// sheet of binary formats, cells are collected first and grouped by rows at the end
+pub(crate) struct ParsedSheet {
+    pub data: SheetData,
+    pub cols_count: u32,
+    pub default_col_width: f32,
+    pub default_row_height: f32,
+    pub rows: BTreeMap<u32, Row>,
+    pub cells: Vec<SparseCell>,
+}
+
+impl ParsedSheet {
+    pub fn new(name: String) -> ParsedSheet {
+        ParsedSheet {
+            data: SheetData::new(name),
+            cols_count: 0,
+            default_col_width: DEFAULT_CELL_WIDTH * WIDTH_COEF,
+            default_row_height: DEFAULT_CELL_HEIGHT / HEIGHT_COEF,
+            rows: BTreeMap::new(),
+            cells: vec!(),
+        }
+    }
+    // rows with populated cells, ordered by index; blank cells only extend the sheet
+    fn into_rows(mut self) -> (SheetData, Vec<Row>, ParsedSheetSize) {
+        for cell in std::mem::take(&mut self.cells) {
+            self.cols_count = self.cols_count.max(cell.c + 1);
+            if cell.cell.v.is_none() && cell.cell.s == 0 {
+                continue;
+            }
+            let default_row_height = self.default_row_height;
+            let row = self.rows.entry(cell.r)
+                .or_insert_with(|| Row { index: cell.r, height: default_row_height, hidden: None, cells: vec!() });
+            row.cells.push(cell);
+        }
+        let rows = self.rows.into_values()
+            .map(|mut row| {
+                row.cells.sort_by_key(|cell| cell.c);
+                row
+            })
+            .collect();
+        (self.data, rows, (self.cols_count, self.default_col_width, self.default_row_height))
+    }
+    pub fn into_parsed_rows(self, flags: u32) -> ParsedRows {
+        let (data, rows, size) = self.into_rows();
+        ParsedRows::new(data, rows, size, flags)
+    }
+    pub fn into_sheet(self, flags: u32) -> SheetData {
+        self.into_parsed_rows(flags).into_sheet()
+    }
+}
+
+// columns count, default column width and row height
+type ParsedSheetSize = (u32, f32, f32);
+
+impl<'a> Rows<'a> {
+    pub(crate) fn xml(reader: SheetReader<BufReader<ZipFile<'a>>>, ctx: CellContext<'a>) -> Rows<'a> {
+        Rows { source: RowSource::Xml(Box::new(reader), ctx) }
+    }
+    pub(crate) fn parsed(rows: ParsedRows) -> Rows<'a> {
+        Rows { source: RowSource::Parsed(Box::new(rows)) }
+    }
+    // part of the sheet read so far, 0 - 1
+    pub fn progress(&self) -> f64 {
+        match &self.source {
+            RowSource::Xml(reader, _) => reader.progress(),
+            RowSource::Parsed(rows) => rows.progress(),
+        }
+    }
+    // merged cells, validations and other sheet properties, complete after the last row
+    pub fn sheet(&self) -> &SheetData {
+        match &self.source {
+            RowSource::Xml(reader, _) => &reader.data,
+            RowSource::Parsed(rows) => &rows.data,
+        }
+    }
+    // the whole sheet, see `SheetBuilder`
+    pub(crate) fn read_all(self) -> Result<SheetData, XlsxError> {
+        match self.source {
+            RowSource::Xml(reader, ctx) => reader.read_all(&ctx),
+            RowSource::Parsed(rows) => Ok(rows.into_sheet()),
+        }
+    }
+}
+
+impl<'a> Iterator for Rows<'a> {
+    type Item = Result<Row, XlsxError>;
+
+    fn next(&mut self) -> Option<Result<Row, XlsxError>> {
+        match &mut self.source {
+            RowSource::Xml(reader, ctx) => match reader.next_row(ctx) {
+                Ok(row) => row.map(Ok),
+                Err(e) => {
+                    // no way to continue after broken xml
+                    reader.done = true;
+                    Some(Err(e))
+                },
+            },
+            RowSource::Parsed(rows) => rows.rows.next().map(Ok),
+        }
+    }
+}
+
+// same as `Rows`, but doesn't borrow the workbook
+// xlsx sheets are inflated while rows are read, other formats are parsed when the cursor is opened
+pub struct RowCursor {
+    source: CursorSource,
+}
+
+enum CursorSource {
+    Xml(Box<SheetReader<BufReader<PartReader>>>),
+    Parsed(Box<ParsedRows>),
+}
+
+impl RowCursor {
+    pub(crate) fn xml(reader: SheetReader<BufReader<PartReader>>) -> RowCursor {
+        RowCursor { source: CursorSource::Xml(Box::new(reader)) }
+    }
+    pub(crate) fn parsed(rows: ParsedRows) -> RowCursor {
+        RowCursor { source: CursorSource::Parsed(Box::new(rows)) }
+    }
+    // up to `count` next rows, empty after the end of the sheet
+    pub fn next_rows(&mut self, workbook: &Workbook, count: usize) -> Result<Vec<Row>, XlsxError> {
+        let reader = match &mut self.source {
+            CursorSource::Xml(reader) => reader,
+            CursorSource::Parsed(rows) => return Ok(rows.rows.by_ref().take(count).collect()),
+        };
+        let ctx = match workbook.cell_context() {
+            Some(ctx) => ctx,
+            None => return Ok(vec!()),
+        };
+        let mut rows = vec!();
+        while rows.len() < count {
+            match reader.next_row(&ctx) {
+                Ok(Some(row)) => rows.push(row),
+                Ok(None) => break,
+                Err(e) => {
+                    reader.done = true;
+                    return Err(e);
+                },
+            }
+        }
+        Ok(rows)
+    }
+    pub fn progress(&self) -> f64 {
+        match &self.source {
+            CursorSource::Xml(reader) => reader.progress(),
+            CursorSource::Parsed(rows) => rows.progress(),
+        }
+    }
+    pub fn sheet(&self) -> &SheetData {
+        match &self.source {
+            CursorSource::Xml(reader) => &reader.data,
+            CursorSource::Parsed(rows) => &rows.data,
+        }
+    }
+}
+
+#[test]
+fn test_number_text() {
+    assert_eq!(number_text(0.5), "0.5");
+    assert_eq!(number_text(42.0), "42");
+}
@@ -0,0 +1,265 @@
+use quick_xml::events::Event;
+use quick_xml::reader::Reader as XmlReader;
+use std::io::BufRead;
+
+use crate::{attr_value, get_indexed_color, get_xlsx_rgb, invalid_attribute, xml_error, XlsxError};
+
+// colors of default office theme, used when workbook has no theme part
+const DEFAULT_COLORS: [(u8, u8, u8); 12] = [
+    (0xFF, 0xFF, 0xFF),
+    (0x00, 0x00, 0x00),
+    (0xE7, 0xE6, 0xE6),
+    (0x44, 0x54, 0x6A),
+    (0x44, 0x72, 0xC4),
+    (0xED, 0x7D, 0x31),
+    (0xA5, 0xA5, 0xA5),
+    (0xFF, 0xC0, 0x00),
+    (0x5B, 0x9B, 0xD5),
+    (0x70, 0xAD, 0x47),
+    (0x05, 0x63, 0xC1),
+    (0x95, 0x4F, 0x72),
+];
+
+// position of clrScheme children in the `theme` attribute of colors
+// light and dark colors are swapped, theme="0" is lt1 and theme="1" is dk1
+fn scheme_index(name: &[u8]) -> Option<usize> {
+    match name {
+        b"lt1" => Some(0),
+        b"dk1" => Some(1),
+        b"lt2" => Some(2),
+        b"dk2" => Some(3),
+        b"accent1" => Some(4),
+        b"accent2" => Some(5),
+        b"accent3" => Some(6),
+        b"accent4" => Some(7),
+        b"accent5" => Some(8),
+        b"accent6" => Some(9),
+        b"hlink" => Some(10),
+        b"folHlink" => Some(11),
+        _ => None,
+    }
+}
+
+// workbook colors: theme color scheme and custom palette for indexed colors
+#[derive(Clone)]
+pub struct Theme {
+    colors: Vec<(u8, u8, u8)>,
+    indexed: Vec<String>,
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme { colors: DEFAULT_COLORS.to_vec(), indexed: vec!() }
+    }
+}
+
+impl Theme {
+    // reads color scheme of xl/theme/theme1.xml, colors missing in the scheme keep default values
+    pub fn read<R: BufRead>(xml: &mut XmlReader<R>, part: &str) -> Result<Theme, XlsxError> {
+        let mut theme = Theme::default();
+        let mut buf = Vec::new();
+        let mut in_scheme = false;
+        let mut current: Option<usize> = None;
+
+        loop {
+            buf.clear();
+            match xml.read_event_into(&mut buf) {
+                Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"clrScheme" => {
+                    in_scheme = true;
+                },
+                Ok(Event::End(ref e)) if e.local_name().as_ref() == b"clrScheme" => break,
+                Ok(Event::Start(ref e)) if in_scheme && current.is_none() => {
+                    current = scheme_index(e.local_name().as_ref());
+                },
+                Ok(Event::End(ref e)) if current.is_some() && scheme_index(e.local_name().as_ref()) == current => {
+                    current = None;
+                },
+                // <a:srgbClr val="44546A"/> or <a:sysClr val="windowText" lastClr="000000"/>
+                Ok(Event::Start(ref e)) if current.is_some() => {
+                    let key: &[u8] = match e.local_name().as_ref() {
+                        b"srgbClr" => b"val",
+                        b"sysClr" => b"lastClr",
+                        _ => continue,
+                    };
+                    for a in e.attributes() {
+                        let att = a.map_err(|e| xml_error(part, xml, e))?;
+                        if att.key.as_ref() == key {
+                            if let (Some(index), Some(rgb)) = (current, parse_rgb(&attr_value(xml, part, &att)?)) {
+                                theme.colors[index] = rgb;
+                            }
+                        }
+                    }
+                },
+                Ok(Event::Eof) => break,
+                Err(e) => return Err(xml_error(part, xml, e)),
+                _ => (),
+            }
+        }
+        Ok(theme)
+    }
+    // reads <colors><indexedColors> palette of styles.xml, it replaces the default colors from the start
+    pub fn read_indexed_colors<R: BufRead>(&mut self, xml: &mut XmlReader<R>, part: &str) -> Result<(), XlsxError> {
+        let mut buf = Vec::new();
+        let mut in_palette = false;
+
+        loop {
+            buf.clear();
+            match xml.read_event_into(&mut buf) {
+                Ok(Event::Start(ref e)) if e.name().as_ref() == b"indexedColors" => {
+                    in_palette = true;
+                },
+                Ok(Event::End(ref e)) if e.name().as_ref() == b"indexedColors" => break,
+                Ok(Event::Start(ref e)) if in_palette && e.name().as_ref() == b"rgbColor" => {
+                    for a in e.attributes() {
+                        let att = a.map_err(|e| xml_error(part, xml, e))?;
+                        if att.key.as_ref() == b"rgb" {
+                            let value = attr_value(xml, part, &att)?;
+                            let color = get_xlsx_rgb(&value).ok_or_else(|| invalid_attribute(part, "rgb", &value))?;
+                            self.indexed.push(color);
+                        }
+                    }
+                },
+                Ok(Event::Eof) => break,
+                Err(e) => return Err(xml_error(part, xml, e)),
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+    // palette of .xls files, replaces the default colors from the start
+    pub fn set_indexed_colors(&mut self, colors: Vec<String>) {
+        self.indexed = colors;
+    }
+    // css color for `indexed` attribute, from the custom palette if the workbook has one
+    pub fn indexed_color(&self, index: &str) -> String {
+        match index.parse::<usize>().ok().and_then(|i| self.indexed.get(i)) {
+            Some(color) => color.clone(),
+            None => get_indexed_color(index),
+        }
+    }
+    // css color for theme index with tint applied
+    pub fn color(&self, index: usize, tint: f64) -> Option<String> {
+        let rgb = self.colors.get(index)?;
+        let (r, g, b) = apply_tint(*rgb, tint);
+        Some(format!("rgba({},{},{},1)", r, g, b))
+    }
+}
+
+// "RRGGBB" -> (r, g, b)
+pub fn parse_rgb(value: &str) -> Option<(u8, u8, u8)> {
+    if value.len() != 6 || !value.is_ascii() {
+        return None;
+    }
+    let r = u8::from_str_radix(&value[..2], 16).ok()?;
+    let g = u8::from_str_radix(&value[2..4], 16).ok()?;
+    let b = u8::from_str_radix(&value[4..6], 16).ok()?;
+    Some((r, g, b))
+}
+
+// tint in range -1..1 darkens or lightens the color, luminance is changed in HSL space
+pub fn apply_tint(rgb: (u8, u8, u8), tint: f64) -> (u8, u8, u8) {
+    if tint == 0.0 {
+        return rgb;
+    }
+    let (h, s, l) = rgb_to_hsl(rgb);
+    let l = if tint < 0.0 {
+        l * (1.0 + tint)
+    } else {
+        l * (1.0 - tint) + tint
+    };
+    hsl_to_rgb(h, s, l.clamp(0.0, 1.0))
+}
+
+fn rgb_to_hsl((r, g, b): (u8, u8, u8)) -> (f64, f64, f64) {
+    let r = r as f64 / 255.0;
+    let g = g as f64 / 255.0;
+    let b = b as f64 / 255.0;
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let l = (max + min) / 2.0;
+    if max == min {
+        return (0.0, 0.0, l);
+    }
+
+    let d = max - min;
+    let s = if l > 0.5 { d / (2.0 - max - min) } else { d / (max + min) };
+    let h = if max == r {
+        (g - b) / d + if g < b { 6.0 } else { 0.0 }
+    } else if max == g {
+        (b - r) / d + 2.0
+    } else {
+        (r - g) / d + 4.0
+    };
+    (h / 6.0, s, l)
+}
+
+fn hsl_to_rgb(h: f64, s: f64, l: f64) -> (u8, u8, u8) {
+    if s == 0.0 {
+        let v = (l * 255.0).round() as u8;
+        return (v, v, v);
+    }
+
+    let q = if l < 0.5 { l * (1.0 + s) } else { l + s - l * s };
+    let p = 2.0 * l - q;
+    let channel = |t: f64| {
+        let t = t.rem_euclid(1.0);
+        let v = if t < 1.0 / 6.0 {
+            p + (q - p) * 6.0 * t
+        } else if t < 0.5 {
+            q
+        } else if t < 2.0 / 3.0 {
+            p + (q - p) * (2.0 / 3.0 - t) * 6.0
+        } else {
+            p
+        };
+        (v * 255.0).round() as u8
+    };
+    (channel(h + 1.0 / 3.0), channel(h), channel(h - 1.0 / 3.0))
+}
+
+
+#[test]
+fn test_theme() {
+    let source = concat!(
+        r#"<a:theme xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main"><a:themeElements><a:clrScheme name="Office">"#,
+        r#"<a:dk1><a:sysClr val="windowText" lastClr="111111"/></a:dk1><a:lt1><a:sysClr val="window" lastClr="FEFEFE"/></a:lt1>"#,
+        r#"<a:dk2><a:srgbClr val="44546A"/></a:dk2><a:accent1><a:srgbClr val="5B9BD5"/></a:accent1>"#,
+        r#"</a:clrScheme></a:themeElements></a:theme>"#,
+    );
+    let mut xml = XmlReader::from_str(source);
+    xml.expand_empty_elements(true);
+    let theme = Theme::read(&mut xml, "xl/theme/theme1.xml").unwrap();
+
+    assert_eq!(theme.color(0, 0.0).unwrap(), "rgba(254,254,254,1)");
+    assert_eq!(theme.color(1, 0.0).unwrap(), "rgba(17,17,17,1)");
+    assert_eq!(theme.color(3, 0.0).unwrap(), "rgba(68,84,106,1)");
+    assert_eq!(theme.color(4, 0.0).unwrap(), "rgba(91,155,213,1)");
+    // not in the scheme, default accent2
+    assert_eq!(theme.color(5, 0.0).unwrap(), "rgba(237,125,49,1)");
+    assert!(theme.color(12, 0.0).is_none());
+
+    // accent1 with "lighter 40%" and "darker 25%" tints, as shown by excel
+    assert_eq!(apply_tint((0x5B, 0x9B, 0xD5), 0.3999755851924192), (0x9D, 0xC3, 0xE6));
+    assert_eq!(apply_tint((0x5B, 0x9B, 0xD5), -0.249977111117893), (0x2E, 0x75, 0xB6));
+    assert_eq!(apply_tint((0xFF, 0xFF, 0xFF), -0.499984740745262), (0x80, 0x80, 0x80));
+}
+
+#[test]
+fn test_indexed_colors() {
+    let source = concat!(
+        r#"<styleSheet><fonts><font><color indexed="1"/></font></fonts><colors><indexedColors>"#,
+        r#"<rgbColor rgb="FF112233"/><rgbColor rgb="FF445566"/></indexedColors></colors></styleSheet>"#,
+    );
+    let mut xml = XmlReader::from_str(source);
+    xml.expand_empty_elements(true);
+    let mut theme = Theme::default();
+    theme.read_indexed_colors(&mut xml, "xl/styles.xml").unwrap();
+
+    assert_eq!(theme.indexed_color("0"), "rgba(17,34,51,1)");
+    assert_eq!(theme.indexed_color("1"), "rgba(68,85,102,1)");
+    // not overridden
+    assert_eq!(theme.indexed_color("2"), "#FF0000");
+    assert_eq!(theme.indexed_color("64"), "#000000");
+    assert_eq!(theme.indexed_color("65"), "#FFFFFF");
+    assert_eq!(Theme::default().indexed_color("1"), "#FFFFFF");
+}
@@ -0,0 +1,173 @@
+// data validations of xlsx sheets
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::reader::Reader as XmlReader;
+
+use std::io::BufRead;
+
+use crate::{DataValidation, DataValidationErrorStyle, DataValidationOperator, DataValidationSource, DataValidationType, XlsxError};
+use crate::{attr_value, xml_error};
+use crate::range::sqref_ranges;
+
+// child of <dataValidation> which text is being read
+enum Target {
+    None,
+    Formula1,
+    Formula2,
+    Range,
+}
+
+// <dataValidation> or <x14:dataValidation> of the extension list, which has formulas in <xm:f> and the range in <xm:sqref>
+pub(crate) fn read_data_validation<R: BufRead>(xml: &mut XmlReader<R>, part: &str, e: &BytesStart) -> Result<DataValidation, XlsxError> {
+    let mut validation = DataValidation::new(String::new(), DataValidationType::Any);
+    for a in e.attributes() {
+        let att = a.map_err(|e| xml_error(part, xml, e))?;
+        let value = attr_value(xml, part, &att)?;
+        let flag = value == "1" || value == "true";
+        match att.key.as_ref() {
+            b"sqref" => validation.range = value,
+            b"type" => validation.kind = DataValidationType::from_xlsx(&value),
+            b"operator" => validation.operator = Some(DataValidationOperator::from_xlsx(&value)),
+            b"allowBlank" => validation.allow_blank = flag,
+            b"showDropDown" => validation.hide_drop_down = flag,
+            b"showInputMessage" => validation.show_input_message = flag,
+            b"showErrorMessage" => validation.show_error_message = flag,
+            b"errorStyle" => validation.error_style = DataValidationErrorStyle::from_xlsx(&value),
+            b"promptTitle" => validation.prompt_title = Some(value),
+            b"prompt" => validation.prompt = Some(value),
+            b"errorTitle" => validation.error_title = Some(value),
+            b"error" => validation.error = Some(value),
+            _ => (),
+        }
+    }
+
+    let end = e.name().as_ref().to_vec();
+    let mut target = Target::None;
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        match xml.read_event_into(&mut buf) {
+            Ok(Event::Start(ref inner)) => match inner.local_name().as_ref() {
+                b"formula1" => target = Target::Formula1,
+                b"formula2" => target = Target::Formula2,
+                b"sqref" => target = Target::Range,
+                _ => (),
+            },
+            Ok(Event::Text(t)) => {
+                let text = t.unescape().map_err(|e| xml_error(part, xml, e))?;
+                match target {
+                    Target::Formula1 => validation.formula1.get_or_insert_with(String::new).push_str(&text),
+                    Target::Formula2 => validation.formula2.get_or_insert_with(String::new).push_str(&text),
+                    Target::Range => validation.range.push_str(&text),
+                    Target::None => (),
+                }
+            },
+            Ok(Event::End(ref inner)) if inner.name().as_ref() == end.as_slice() => break,
+            Ok(Event::End(ref inner)) => {
+                if matches!(inner.local_name().as_ref(), b"formula1" | b"formula2" | b"sqref") {
+                    target = Target::None;
+                }
+            },
+            Ok(Event::Eof) => return Err(xml_error(part, xml, "unexpected end of document")),
+            Err(e) => return Err(xml_error(part, xml, e)),
+            _ => (),
+        }
+    }
+
+    if validation.kind.has_operator() && validation.operator.is_none() {
+        validation.operator = Some(DataValidationOperator::Between);
+    }
+    validation.ranges = sqref_ranges(&validation.range);
+    if validation.kind == DataValidationType::List {
+        validation.source = validation.formula1.as_deref().map(list_source);
+    }
+    Ok(validation)
+}
+
+// formula1 of list validation: a string literal of comma separated values or a reference to cells
+fn list_source(formula: &str) -> DataValidationSource {
+    match list_literal(formula) {
+        Some(list) => DataValidationSource::List(list),
+        None => DataValidationSource::RangeReference(formula.to_string()),
+    }
+}
+
+// quotes inside of the literal are doubled, `"a,""b"""` -> a and "b"
+// None when it is not a single literal, like a reference or `"a"&"b"`
+fn list_literal(formula: &str) -> Option<Vec<String>> {
+    let mut chars = formula.trim().strip_prefix('"')?.chars().peekable();
+    let mut list = Vec::new();
+    let mut value = String::new();
+    loop {
+        match chars.next()? {
+            '"' if chars.peek() == Some(&'"') => {
+                chars.next();
+                value.push('"');
+            },
+            '"' => break,
+            ',' => list.push(std::mem::take(&mut value)),
+            c => value.push(c),
+        }
+    }
+    if chars.next().is_some() {
+        return None;
+    }
+    list.push(value);
+    Some(list.into_iter().map(|s| s.trim().to_string()).collect())
+}
+
+#[test]
+fn test_read_data_validation() {
+    let read = |content: &str| {
+        let mut xml = crate::xml_reader_from(std::io::Cursor::new(content.as_bytes()));
+        let mut buf = Vec::new();
+        loop {
+            match xml.read_event_into(&mut buf) {
+                Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"dataValidation" => {
+                    let e = e.to_owned();
+                    return read_data_validation(&mut xml, "sheet1.xml", &e).unwrap();
+                },
+                Ok(Event::Eof) => panic!("no validation"),
+                _ => (),
+            }
+        }
+    };
+
+    let validation = read(r#"<dataValidation type="whole" operator="notBetween" errorStyle="warning" allowBlank="1" showErrorMessage="1" errorTitle="Wrong" error="1 &lt; x" sqref="A1:A5 C1"><formula1>1</formula1><formula2>10</formula2></dataValidation>"#);
+    assert_eq!(validation.range, "A1:A5 C1");
+    let ranges: Vec<_> = validation.ranges.iter().map(|r| ((r.from.column, r.from.row), (r.to.column, r.to.row))).collect();
+    assert_eq!(ranges, vec![((0, 0), (0, 4)), ((2, 0), (2, 0))]);
+    assert_eq!(validation.kind, DataValidationType::Whole);
+    assert_eq!(validation.operator, Some(DataValidationOperator::NotBetween));
+    assert_eq!(validation.error_style, DataValidationErrorStyle::Warning);
+    assert!(validation.allow_blank && validation.show_error_message && !validation.show_input_message);
+    assert_eq!((validation.formula1.as_deref(), validation.formula2.as_deref()), (Some("1"), Some("10")));
+    assert_eq!(validation.error.as_deref(), Some("1 < x"));
+    assert!(validation.source.is_none());
+
+    let validation = read(r#"<dataValidation type="date" promptTitle="Date" prompt="Any day" showInputMessage="1" sqref="B1"><formula1>45000</formula1></dataValidation>"#);
+    assert_eq!(validation.operator, Some(DataValidationOperator::Between));
+    assert_eq!(validation.prompt.as_deref(), Some("Any day"));
+
+    let validation = read(r#"<dataValidation type="custom" sqref="B1"><formula1>ISNUMBER(B1)</formula1></dataValidation>"#);
+    assert_eq!((validation.kind, validation.operator), (DataValidationType::Custom, None));
+
+    let validation = read(r#"<x14:dataValidations><x14:dataValidation type="list" showDropDown="1"><x14:formula1><xm:f>Lists!$A$1:$A$3</xm:f></x14:formula1><xm:sqref>D1:D9</xm:sqref></x14:dataValidation></x14:dataValidations>"#);
+    assert_eq!(validation.range, "D1:D9");
+    assert!(validation.hide_drop_down);
+    assert!(matches!(validation.source, Some(DataValidationSource::RangeReference(ref r)) if r == "Lists!$A$1:$A$3"));
+
+    let validation = read(r#"<dataValidation type="list" sqref="E1:E3 G:G"><formula1>"Yes, sir,""Maybe"",""No, thanks"""</formula1></dataValidation>"#);
+    assert_eq!(validation.ranges.len(), 2);
+    assert_eq!((validation.ranges[1].from.row, validation.ranges[1].to.row), (0, 1048575));
+    let expected = vec!["Yes", "sir", "\"Maybe\"", "\"No", "thanks\""];
+    assert!(matches!(validation.source, Some(DataValidationSource::List(ref list)) if *list == expected));
+}
+
+#[test]
+fn test_list_literal() {
+    assert_eq!(list_literal(r#""a,b , c""#), Some(vec!["a".to_string(), "b".to_string(), "c".to_string()]));
+    assert_eq!(list_literal(r#""say ""hi""""#), Some(vec!["say \"hi\"".to_string()]));
+    assert_eq!(list_literal(r#""a"&"b""#), None);
+    assert_eq!(list_literal(r#""open"#), None);
+    assert_eq!(list_literal("Lists!$A$1:$A$3"), None);
+}
@@ -0,0 +1,132 @@
+use wasm_bindgen::prelude::*;
+use gloo_utils::format::JsValueSerdeExt;
+
+use crate::error::ErrorInfo;
+use crate::utils;
+use crate::sheet::RowCursor;
+use crate::workbook::Workbook;
+use crate::{CsvOptions, ExportData, XlsxError, WITH_BORDER_DETAILS, WITH_DATES, WITH_FORMATTED_TEXT, WITH_FORMULAS, WITH_RICH_TEXT, WITH_SPARSE_CELLS, WITH_TYPED_VALUES, WITH_VALIDATION_VALUES};
+
+// js bindings, all conversion is done by Workbook
+#[wasm_bindgen]
+pub struct XLSX {
+    workbook: Workbook,
+    // rows of the sheet opened by `open_rows`
+    cursor: Option<RowCursor>,
+}
+
+#[wasm_bindgen]
+impl XLSX {
+    pub fn new(data: Vec<u8>) -> XLSX {
+        utils::set_panic_hook();
+        let workbook = Workbook::from_vec(data).unwrap_or_else(|e| panic!("{}", e));
+        XLSX { workbook, cursor: None }
+    }
+    // same as `new`, but throws an error object ({ kind, message, ... }) instead of panicking
+    pub fn try_new(data: Vec<u8>) -> Result<XLSX, JsValue> {
+        utils::set_panic_hook();
+        let workbook = Workbook::from_vec(data).map_err(js_error)?;
+        Ok(XLSX { workbook, cursor: None })
+    }
+    // csv or tsv file with a single sheet, delimiter is detected when empty
+    pub fn from_csv(data: Vec<u8>, delimiter: String, infer_types: bool) -> XLSX {
+        utils::set_panic_hook();
+        let options = CsvOptions { delimiter: delimiter.chars().next(), infer_types, ..CsvOptions::default() };
+        XLSX { workbook: Workbook::from_csv(data, options), cursor: None }
+    }
+    // xlsx file from the object returned by `convert`, { data, styles }
+    pub fn to_xlsx(data: JsValue) -> Result<Vec<u8>, JsValue> {
+        utils::set_panic_hook();
+        let data: ExportData = data.into_serde().map_err(|e| js_error(XlsxError::InvalidData { reason: e.to_string() }))?;
+        data.to_xlsx().map_err(js_error)
+    }
+    pub fn with_formulas() -> u32{
+        WITH_FORMULAS
+    }
+    pub fn with_typed_values() -> u32{
+        WITH_TYPED_VALUES
+    }
+    pub fn with_dates() -> u32{
+        WITH_DATES
+    }
+    pub fn with_formatted_text() -> u32{
+        WITH_FORMATTED_TEXT
+    }
+    pub fn with_rich_text() -> u32{
+        WITH_RICH_TEXT
+    }
+    pub fn with_border_details() -> u32{
+        WITH_BORDER_DETAILS
+    }
+    pub fn with_sparse_cells() -> u32{
+        WITH_SPARSE_CELLS
+    }
+    pub fn with_validation_values() -> u32{
+        WITH_VALIDATION_VALUES
+    }
+    pub fn get_styles(&mut self) -> JsValue {
+        self.try_get_styles().unwrap()
+    }
+    pub fn try_get_styles(&mut self) -> Result<JsValue, JsValue> {
+        self.try_get_styles_with(0)
+    }
+    // styles with extra details, flags are WITH_BORDER_DETAILS
+    pub fn try_get_styles_with(&mut self, flags: u32) -> Result<JsValue, JsValue> {
+        let styles = self.workbook.styles_with(flags).map_err(js_error)?;
+        Ok(JsValue::from_serde(&styles).unwrap())
+    }
+    // styles of conditional formatting rules, referenced by `dxf_id`
+    pub fn get_differential_styles(&mut self) -> JsValue {
+        self.try_get_differential_styles_with(0).unwrap()
+    }
+    pub fn try_get_differential_styles_with(&mut self, flags: u32) -> Result<JsValue, JsValue> {
+        let styles = self.workbook.differential_styles(flags).map_err(js_error)?;
+        Ok(JsValue::from_serde(&styles).unwrap())
+    }
+    pub fn get_sheets(&self) -> Vec<JsValue> {
+        self.workbook.sheet_names().iter().map(JsValue::from).collect()
+    }
+    // sheets with their visibility, type and tab color
+    pub fn get_sheet_descriptors(&self) -> JsValue {
+        JsValue::from_serde(&self.workbook.sheets()).unwrap()
+    }
+    pub fn get_defined_names(&self) -> JsValue {
+        JsValue::from_serde(&self.workbook.defined_names()).unwrap()
+    }
+    pub fn get_sheet_data(&mut self, sheet_name: String, flags: u32) -> JsValue {
+        let data = self.workbook.sheet_with(&sheet_name, flags).unwrap_or_else(|e| panic!("{}", e));
+        JsValue::from_serde(&data).unwrap()
+    }
+    pub fn try_get_sheet_data(&mut self, sheet_name: String, flags: u32) -> Result<JsValue, JsValue> {
+        let data = self.workbook.sheet_with(&sheet_name, flags).map_err(js_error)?;
+        Ok(JsValue::from_serde(&data).unwrap())
+    }
+    // starts reading the sheet row by row, rows are returned by `next_rows`
+    pub fn open_rows(&mut self, sheet_name: String, flags: u32) -> Result<(), JsValue> {
+        self.cursor = Some(self.workbook.row_cursor(&sheet_name, flags).map_err(js_error)?);
+        Ok(())
+    }
+    // up to `count` next rows of the opened sheet, empty array after the last row
+    pub fn next_rows(&mut self, count: usize) -> Result<JsValue, JsValue> {
+        let rows = match self.cursor.as_mut() {
+            Some(cursor) => cursor.next_rows(&self.workbook, count).map_err(js_error)?,
+            None => vec!(),
+        };
+        Ok(JsValue::from_serde(&rows).unwrap())
+    }
+    // part of the opened sheet read so far, 0 - 1
+    pub fn rows_progress(&self) -> f64 {
+        self.cursor.as_ref().map(|c| c.progress()).unwrap_or(1.0)
+    }
+    // sheet data without cells, merged cells and validations are complete after the last row
+    pub fn rows_sheet_data(&self) -> JsValue {
+        match self.cursor.as_ref() {
+            Some(cursor) => JsValue::from_serde(cursor.sheet()).unwrap(),
+            None => JsValue::NULL,
+        }
+    }
+}
+
+fn js_error(error: XlsxError) -> JsValue {
+    JsValue::from_serde(&ErrorInfo::new(&error)).unwrap_or_else(|_| JsValue::from(error.to_string()))
+}
@@ -0,0 +1,1588 @@
+// parsing xlsx (zip + xml)
+use quick_xml::events::Event;
+use quick_xml::reader::Reader as XmlReader;
+use flate2::read::DeflateDecoder;
+use zip::read::ZipArchive;
+use zip::result::ZipError;
+use zip::CompressionMethod;
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Cursor, Read, Take};
+use serde_json::Value as JsonValue;
+
+use crate::border::{Border, BorderInfo, BorderPosition};
+use crate::fill::{Fill, Gradient};
+use crate::rich_text::{self, TextRun};
+use crate::cfb;
+use crate::range;
+use crate::sheet::{CellContext, CellOptions, ParsedSheet, Row, RowCursor, Rows, SheetReader};
+use crate::theme::Theme;
+use crate::csv::{Csv, CsvOptions};
+use crate::ods::{self, Ods};
+use crate::xls::Xls;
+use crate::xlsb::{self, Xlsb};
+use crate::{CellValue, DataValidationSource, DefinedName, Dict, SheetData, SheetDescriptor, SheetKind, SheetState, Style, XlsxError};
+use crate::{WITH_BORDER_DETAILS, WITH_DATES, WITH_FORMATTED_TEXT, WITH_VALIDATION_VALUES, PT_COEF};
+use crate::{attr_parse, attr_value, get_format, invalid_attribute, lookup, read_color, xml_error, xml_reader, xml_reader_from, Archive, SharedBytes};
+
+// descriptor and path of the sheet
+type Sheet = (SheetDescriptor, String);
+// target and type by id of the relationship
+pub(crate) type TypedRelationships = HashMap<String, (String, String)>;
+
+#[derive(PartialEq)]
+enum StyleXMLPath {
+    Any,
+    Font,
+    Fill,
+    Border,
+    CellXfs,
+    Xf,
+    Dxfs,
+}
+
+// spreadsheet file opened for conversion, xlsx, xlsb, xls, ods or csv
+pub struct Workbook {
+    book: Book,
+}
+
+enum Book {
+    Xlsx(Xlsx),
+    Xlsb(Xlsb),
+    Xls(Xls),
+    Ods(Box<Ods>),
+    Csv(Csv),
+}
+
+impl Workbook {
+    // copies the data, use `from_vec` to pass the ownership
+    pub fn open(data: &[u8]) -> Result<Workbook, XlsxError> {
+        Workbook::from_vec(data.to_vec())
+    }
+    // format is detected by the content: compound file is xls, zip archive is ods by its mimetype, xlsb or xlsx by its content types
+    pub fn from_vec(data: Vec<u8>) -> Result<Workbook, XlsxError> {
+        if data.starts_with(&cfb::SIGNATURE) {
+            return Ok(Workbook { book: Book::Xls(Xls::from_vec(data)?) });
+        }
+        let data = SharedBytes::new(data);
+        let mut zip = ZipArchive::new(Cursor::new(data.clone())).map_err(|e| XlsxError::BadZip { reason: e.to_string() })?;
+        let book = if ods::is_ods(&mut zip) {
+            Book::Ods(Box::new(Ods::from_zip(zip)?))
+        } else if xlsb::is_xlsb(&mut zip)? {
+            Book::Xlsb(Xlsb::from_zip(zip)?)
+        } else {
+            Book::Xlsx(Xlsx::from_zip(zip, data)?)
+        };
+        Ok(Workbook { book })
+    }
+    // csv and tsv can't be detected reliably, so they are opened explicitly, the file has a single sheet
+    pub fn from_csv(data: Vec<u8>, options: CsvOptions) -> Workbook {
+        Workbook { book: Book::Csv(Csv::from_vec(data, options)) }
+    }
+    pub fn sheet_names(&self) -> Vec<String> {
+        match &self.book {
+            Book::Xlsx(xlsx) => xlsx.sheet_names(),
+            Book::Xlsb(xlsb) => xlsb.sheet_names(),
+            Book::Xls(xls) => xls.sheet_names(),
+            Book::Ods(ods) => ods.sheet_names(),
+            Book::Csv(csv) => csv.sheet_names(),
+        }
+    }
+    // sheets with their visibility, type and tab color, in order of the workbook
+    pub fn sheets(&self) -> Vec<SheetDescriptor> {
+        match &self.book {
+            Book::Xlsx(xlsx) => xlsx.sheets(),
+            Book::Xlsb(xlsb) => xlsb.sheets(),
+            Book::Xls(xls) => xls.sheets(),
+            Book::Ods(ods) => ods.sheets(),
+            Book::Csv(csv) => csv.sheets(),
+        }
+    }
+    // chart and dialog sheets are not read, they have no cells
+    fn without_cells(&self, sheet_name: &str) -> bool {
+        self.sheets().iter().any(|sheet| sheet.name == sheet_name && !sheet.has_cells())
+    }
+    pub fn sheet(&mut self, sheet_name: &str) -> Result<SheetData, XlsxError> {
+        self.sheet_with(sheet_name, 0)
+    }
+    // flags are WITH_FORMULAS, WITH_TYPED_VALUES, WITH_DATES, WITH_FORMATTED_TEXT, WITH_RICH_TEXT, WITH_SPARSE_CELLS and WITH_VALIDATION_VALUES
+    pub fn sheet_with(&mut self, sheet_name: &str, flags: u32) -> Result<SheetData, XlsxError> {
+        if self.without_cells(sheet_name) {
+            return Ok(ParsedSheet::new(sheet_name.to_string()).into_sheet(flags));
+        }
+        let mut data = match &mut self.book {
+            Book::Xlsx(xlsx) => xlsx.rows_with(sheet_name, flags)?.read_all(),
+            Book::Xlsb(xlsb) => xlsb.sheet_with(sheet_name, flags),
+            Book::Xls(xls) => xls.sheet_with(sheet_name, flags),
+            Book::Ods(ods) => ods.sheet_with(sheet_name, flags),
+            Book::Csv(csv) => csv.sheet_with(sheet_name, flags),
+        }?;
+        if flags & WITH_VALIDATION_VALUES > 0 {
+            self.resolve_validations(&mut data)?;
+        }
+        Ok(data)
+    }
+    // values of validations given by a range or a defined name, as they are shown in the dropdown
+    // references which are not ranges, like INDIRECT(...), are left without values
+    // names are read only from xlsx and ods, so names of xls and xlsb are left without values too
+    fn resolve_validations(&mut self, data: &mut SheetData) -> Result<(), XlsxError> {
+        let names = self.defined_names();
+        let current = data.name.clone();
+        // referenced sheet and range of each validation
+        let references: Vec<Option<(String, range::Range)>> = data.validations.iter().map(|validation| {
+            let formula = match &validation.source {
+                Some(DataValidationSource::RangeReference(formula)) => formula.trim_start_matches('='),
+                _ => return None,
+            };
+            // names are not case sensitive, local names hide global ones
+            let named = |scope: Option<&str>| names.iter().find(|name| name.name.eq_ignore_ascii_case(formula) && name.scope.as_deref() == scope);
+            let name = named(Some(current.as_str())).or_else(|| named(None));
+            let reference = name.map(|name| name.reference.as_str()).unwrap_or(formula);
+            range::parse_reference(reference).map(|(sheet_name, range)| (sheet_name.unwrap_or_else(|| current.clone()), range))
+        }).collect();
+
+        // each sheet is read once, up to the last referenced row
+        let mut last_rows: HashMap<&str, u32> = HashMap::new();
+        for (sheet_name, range) in references.iter().flatten() {
+            let last = last_rows.entry(sheet_name.as_str()).or_insert(0);
+            *last = (*last).max(range.last.1);
+        }
+        let mut sheets: HashMap<&str, Vec<Row>> = HashMap::new();
+        for (sheet_name, last) in last_rows {
+            let mut rows = match self.rows_with(sheet_name, WITH_FORMATTED_TEXT) {
+                Err(XlsxError::UnknownSheet { .. }) => continue,
+                result => result?,
+            };
+            let mut read = vec!();
+            for row in &mut rows {
+                let row = row?;
+                if row.index > last {
+                    break;
+                }
+                read.push(row);
+            }
+            sheets.insert(sheet_name, read);
+        }
+
+        for (validation, reference) in data.validations.iter_mut().zip(references.iter()) {
+            let (rows, range) = match reference {
+                Some((sheet_name, range)) => match sheets.get(sheet_name.as_str()) {
+                    Some(rows) => (rows, range),
+                    None => continue,
+                },
+                None => continue,
+            };
+            let mut values = vec!();
+            for row in rows.iter().filter(|row| row.index >= range.first.1 && row.index <= range.last.1) {
+                for cell in row.cells.iter().filter(|cell| cell.c >= range.first.0 && cell.c <= range.last.0) {
+                    // values are strings without WITH_TYPED_VALUES flag
+                    let value = match (&cell.cell.w, &cell.cell.v) {
+                        (Some(text), _) | (None, Some(CellValue::String(text))) => text.clone(),
+                        _ => continue,
+                    };
+                    if !value.is_empty() {
+                        values.push(value);
+                    }
+                }
+            }
+            validation.values = Some(values);
+        }
+        Ok(())
+    }
+    pub fn rows(&mut self, sheet_name: &str) -> Result<Rows<'_>, XlsxError> {
+        self.rows_with(sheet_name, 0)
+    }
+    // rows of the sheet one by one, without building the whole sheet in memory, flags are the same as for `sheet_with`
+    pub fn rows_with(&mut self, sheet_name: &str, flags: u32) -> Result<Rows<'_>, XlsxError> {
+        if self.without_cells(sheet_name) {
+            return Ok(Rows::parsed(ParsedSheet::new(sheet_name.to_string()).into_parsed_rows(flags)));
+        }
+        match &mut self.book {
+            Book::Xlsx(xlsx) => xlsx.rows_with(sheet_name, flags),
+            Book::Xlsb(xlsb) => Ok(Rows::parsed(xlsb.read_sheet(sheet_name, flags)?.into_parsed_rows(flags))),
+            Book::Xls(xls) => Ok(Rows::parsed(xls.read_sheet(sheet_name, flags)?.into_parsed_rows(flags))),
+            Book::Ods(ods) => Ok(Rows::parsed(ods.read_sheet(sheet_name, flags)?.into_parsed_rows(flags))),
+            Book::Csv(csv) => Ok(Rows::parsed(csv.read_sheet(sheet_name, flags)?.into_parsed_rows(flags))),
+        }
+    }
+    // same as `rows_with`, but the cursor doesn't borrow the workbook, so it can be stored next to it
+    pub fn row_cursor(&mut self, sheet_name: &str, flags: u32) -> Result<RowCursor, XlsxError> {
+        if self.without_cells(sheet_name) {
+            return Ok(RowCursor::parsed(ParsedSheet::new(sheet_name.to_string()).into_parsed_rows(flags)));
+        }
+        match &mut self.book {
+            Book::Xlsx(xlsx) => xlsx.row_cursor(sheet_name, flags),
+            Book::Xlsb(xlsb) => Ok(RowCursor::parsed(xlsb.read_sheet(sheet_name, flags)?.into_parsed_rows(flags))),
+            Book::Xls(xls) => Ok(RowCursor::parsed(xls.read_sheet(sheet_name, flags)?.into_parsed_rows(flags))),
+            Book::Ods(ods) => Ok(RowCursor::parsed(ods.read_sheet(sheet_name, flags)?.into_parsed_rows(flags))),
+            Book::Csv(csv) => Ok(RowCursor::parsed(csv.read_sheet(sheet_name, flags)?.into_parsed_rows(flags))),
+        }
+    }
+    pub fn styles(&mut self) -> Result<Vec<Style>, XlsxError> {
+        self.styles_with(0)
+    }
+    // flags are WITH_BORDER_DETAILS
+    pub fn styles_with(&mut self, flags: u32) -> Result<Vec<Style>, XlsxError> {
+        match &mut self.book {
+            Book::Xlsx(xlsx) => Ok(xlsx.read_style(flags)?.0),
+            Book::Xlsb(xlsb) => Ok(xlsb.styles_with(flags)),
+            Book::Xls(xls) => Ok(xls.styles_with(flags)),
+            Book::Ods(ods) => Ok(ods.styles_with(flags)),
+            Book::Csv(csv) => Ok(csv.styles_with(flags)),
+        }
+    }
+    // styles of conditional formatting rules by their `dxf_id`, flags are WITH_BORDER_DETAILS
+    // only xlsx has them, like `SheetData::conditional_formats`
+    pub fn differential_styles(&mut self, flags: u32) -> Result<Vec<Style>, XlsxError> {
+        match &mut self.book {
+            Book::Xlsx(xlsx) => Ok(xlsx.read_style(flags)?.1),
+            Book::Xlsb(_) | Book::Xls(_) | Book::Ods(_) | Book::Csv(_) => Ok(vec!()),
+        }
+    }
+    // named ranges and formulas, xlsb and xls names are not read as their formulas are binary
+    pub fn defined_names(&self) -> Vec<DefinedName> {
+        match &self.book {
+            Book::Xlsx(xlsx) => xlsx.defined_names.clone(),
+            Book::Ods(ods) => ods.defined_names(),
+            Book::Xlsb(_) | Book::Xls(_) | Book::Csv(_) => vec!(),
+        }
+    }
+    pub(crate) fn cell_context(&self) -> Option<CellContext<'_>> {
+        match &self.book {
+            Book::Xlsx(xlsx) => Some(xlsx.cell_context()),
+            Book::Xlsb(_) | Book::Xls(_) | Book::Ods(_) | Book::Csv(_) => None,
+        }
+    }
+}
+
+// xlsx file, zip archive with xml parts
+struct Xlsx {
+    shared_strings: Vec<String>,
+    // runs of shared strings with rich text, by index of the string
+    rich_strings: HashMap<usize, Vec<TextRun>>,
+    sheets: Vec<Sheet>,
+    defined_names: Vec<DefinedName>,
+    date1904: bool,
+    theme: Theme,
+    // number format of each cell style, loaded on demand
+    cell_formats: Option<Vec<String>>,
+    zip: Archive,
+    // content of the archive, parts are read from it directly by row cursors
+    data: SharedBytes,
+}
+
+impl Xlsx {
+    fn from_zip(zip: Archive, data: SharedBytes) -> Result<Xlsx, XlsxError> {
+        let mut xlsx = Xlsx {
+            shared_strings: vec!(),
+            rich_strings: HashMap::new(),
+            sheets: vec!(),
+            defined_names: vec!(),
+            date1904: false,
+            theme: Theme::default(),
+            cell_formats: None,
+            zip,
+            data,
+        };
+
+        let part = "xl/_rels/workbook.xml.rels";
+        let rels = match read_typed_relationships(&mut xlsx.zip, part)? {
+            Some(rels) => rels,
+            None => return Err(XlsxError::MissingPart { part: String::from(part) }),
+        };
+        xlsx.read_workbook(&rels)?;
+        xlsx.read_colors()?;
+        xlsx.read_tab_colors()?;
+        xlsx.read_shared_strings()?;
+
+        Ok(xlsx)
+    }
+    pub fn sheet_names(&self) -> Vec<String> {
+        self.sheets.iter().map(|(sheet, _)| sheet.name.clone()).collect()
+    }
+    pub fn sheets(&self) -> Vec<SheetDescriptor> {
+        self.sheets.iter().map(|(sheet, _)| sheet.clone()).collect()
+    }
+    pub fn rows_with(&mut self, sheet_name: &str, flags: u32) -> Result<Rows<'_>, XlsxError> {
+        let (name, path, links) = self.open_sheet(sheet_name, flags)?;
+        let xml = match xml_reader(&mut self.zip, &path) {
+            None => return Err(XlsxError::MissingPart { part: path }),
+            Some(x) => x?,
+        };
+        let size = xml.get_ref().get_ref().size();
+        let cell_formats = self.cell_formats.as_deref().unwrap_or(&[]);
+
+        let options = CellOptions::new(flags, self.date1904, cell_formats, &self.theme);
+        let mut reader = SheetReader::new(xml, &path, size, name, options);
+        reader.set_links(links);
+        let ctx = CellContext {
+            shared_strings: &self.shared_strings,
+            rich_strings: &self.rich_strings,
+            theme: &self.theme,
+            cell_formats,
+        };
+        Ok(Rows::xml(reader, ctx))
+    }
+    pub fn row_cursor(&mut self, sheet_name: &str, flags: u32) -> Result<RowCursor, XlsxError> {
+        let (name, path, links) = self.open_sheet(sheet_name, flags)?;
+        // position of the part in the archive, it is inflated from the shared content while rows are read
+        let (start, compressed_size, size, compression) = match self.zip.by_name(&path) {
+            Ok(file) => (file.data_start(), file.compressed_size(), file.size(), file.compression()),
+            Err(ZipError::FileNotFound) => return Err(XlsxError::MissingPart { part: path }),
+            Err(e) => return Err(XlsxError::BadZip { reason: e.to_string() }),
+        };
+        let mut raw = Cursor::new(self.data.clone());
+        raw.set_position(start);
+        let raw = raw.take(compressed_size);
+        let part = match compression {
+            CompressionMethod::Stored => PartReader::Stored(raw),
+            CompressionMethod::Deflated => PartReader::Deflated(DeflateDecoder::new(raw)),
+            method => return Err(XlsxError::BadZip { reason: format!("unsupported compression of {}: {}", path, method) }),
+        };
+
+        let options = CellOptions::new(flags, self.date1904, self.cell_formats.as_deref().unwrap_or(&[]), &self.theme);
+        let mut reader = SheetReader::new(xml_reader_from(BufReader::new(part)), &path, size, name, options);
+        reader.set_links(links);
+        Ok(RowCursor::xml(reader))
+    }
+    fn cell_formats(&mut self) -> Result<&[String], XlsxError> {
+        if self.cell_formats.is_none() {
+            let styles = match self.read_style(0) {
+                Ok((styles, _)) => styles,
+                Err(XlsxError::MissingPart { .. }) => vec!(),
+                Err(e) => return Err(e),
+            };
+            let formats = styles.iter()
+                .map(|xf| xf.get("format").and_then(|f| f.as_str()).unwrap_or("General").to_string())
+                .collect();
+            self.cell_formats = Some(formats);
+        }
+        Ok(self.cell_formats.as_deref().unwrap_or(&[]))
+    }
+    fn read_colors(&mut self) -> Result<(), XlsxError> {
+        let part = "xl/theme/theme1.xml";
+        if let Some(xml) = xml_reader(&mut self.zip, part) {
+            self.theme = Theme::read(&mut xml?, part)?;
+        }
+        // custom palette is at the end of styles.xml, so it is read before other styles
+        let part = "xl/styles.xml";
+        if let Some(xml) = xml_reader(&mut self.zip, part) {
+            self.theme.read_indexed_colors(&mut xml?, part)?;
+        }
+        Ok(())
+    }
+    fn read_shared_strings(&mut self) -> Result<(), XlsxError> {
+        let part = "xl/sharedStrings.xml";
+        let mut xml = match xml_reader(&mut self.zip, part) {
+            None => return Ok(()),
+            Some(x) => x?,
+        };
+        let mut buf = Vec::new();
+
+
+        loop {
+            buf.clear();
+            match xml.read_event_into(&mut buf) {
+                Ok(Event::Start(ref e)) if e.name().as_ref() == b"si" => {
+                    let (text, runs) = rich_text::read_string_item(&mut xml, part, b"si", &self.theme)?;
+                    if let Some(runs) = runs {
+                        self.rich_strings.insert(self.shared_strings.len(), runs);
+                    }
+                    self.shared_strings.push(text);
+                },
+                Ok(Event::End(ref e)) if e.name().as_ref() == b"sst" => break,
+                Ok(Event::Eof) => break,
+                Err(e) => return Err(xml_error(part, &xml, e)),
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+    // name and path of the sheet, hyperlinks of the sheet; loads number formats when flags need them
+    fn open_sheet(&mut self, sheet_name: &str, flags: u32) -> Result<(String, String, HashMap<String, String>), XlsxError> {
+        let (name, path) = match self.sheets.iter().find(|(sheet, _)| sheet.name == sheet_name) {
+            Some((sheet, path)) => (sheet.name.clone(), path.clone()),
+            None => return Err(XlsxError::UnknownSheet { name: sheet_name.to_string() }),
+        };
+        let links = read_relationships(&mut self.zip, &rels_path(&path))?.unwrap_or_default();
+        if flags & (WITH_DATES | WITH_FORMATTED_TEXT) > 0 {
+            self.cell_formats()?;
+        }
+        Ok((name, path, links))
+    }
+    fn cell_context(&self) -> CellContext<'_> {
+        CellContext {
+            shared_strings: &self.shared_strings,
+            rich_strings: &self.rich_strings,
+            theme: &self.theme,
+            cell_formats: self.cell_formats.as_deref().unwrap_or(&[]),
+        }
+    }
+    // color of the sheet tab is in <sheetPr> at the start of the sheet part
+    fn read_tab_colors(&mut self) -> Result<(), XlsxError> {
+        for (sheet, path) in self.sheets.iter_mut() {
+            let mut xml = match xml_reader(&mut self.zip, path) {
+                None => continue,
+                Some(x) => x?,
+            };
+            sheet.tab_color = read_tab_color(&mut xml, path, &self.theme)?;
+        }
+        Ok(())
+    }
+    fn read_workbook(&mut self, relationships: &TypedRelationships) -> Result<(), XlsxError> {
+        let part = "xl/workbook.xml";
+        let mut xml = match xml_reader(&mut self.zip, part) {
+            None => {
+                return Err(XlsxError::MissingPart { part: String::from(part) })
+            },
+            Some(x) => x?,
+        };
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            match xml.read_event_into(&mut buf) {
+                Ok(Event::Start(ref e)) if e.name().as_ref() == b"sheet" => {
+                    let mut sheet = SheetDescriptor::new(String::new(), self.sheets.len() as u32 + 1);
+                    let mut path = String::new();
+                    for a in e.attributes() {
+                        let att = a.map_err(|e| xml_error(part, &xml, e))?;
+                        match att.key.as_ref() {
+                            b"name" => {
+                                sheet.name = attr_value(&xml, part, &att)?;
+                            },
+                            b"sheetId" => {
+                                sheet.id = attr_parse(&xml, part, &att)?;
+                            },
+                            b"state" => {
+                                sheet.state = SheetState::from_xlsx(&attr_value(&xml, part, &att)?);
+                            },
+                            b"r:id" => {
+                                let value = attr_value(&xml, part, &att)?;
+                                path = match relationships.get(&value) {
+                                    Some((target, kind)) => {
+                                        sheet.kind = sheet_kind(kind);
+                                        part_path(target)
+                                    },
+                                    None => return Err(invalid_attribute(part, "r:id", &value)),
+                                };
+                            }
+                            _ => ()
+                        }
+                    }
+                    self.sheets.push((sheet, path));
+                },
+                Ok(Event::Start(ref e)) if e.name().as_ref() == b"workbookPr" => {
+                    for a in e.attributes() {
+                        let att = a.map_err(|e| xml_error(part, &xml, e))?;
+                        if att.key.as_ref() == b"date1904" {
+                            let v = attr_value(&xml, part, &att)?;
+                            self.date1904 = v == "1" || v == "true";
+                        }
+                    }
+                },
+                Ok(Event::Start(ref e)) if e.name().as_ref() == b"definedName" => {
+                    let mut name = String::new();
+                    let mut scope = None;
+                    let mut hidden = false;
+                    for a in e.attributes() {
+                        let att = a.map_err(|e| xml_error(part, &xml, e))?;
+                        match att.key.as_ref() {
+                            b"name" => name = attr_value(&xml, part, &att)?,
+                            b"localSheetId" => scope = Some(attr_parse::<usize, _>(&xml, part, &att)?),
+                            b"hidden" => {
+                                let v = attr_value(&xml, part, &att)?;
+                                hidden = v == "1" || v == "true";
+                            },
+                            _ => (),
+                        }
+                    }
+                    let mut reference = String::new();
+                    let mut inner_buf = Vec::new();
+                    loop {
+                        inner_buf.clear();
+                        match xml.read_event_into(&mut inner_buf) {
+                            Ok(Event::Text(t)) => reference += &t.unescape().map_err(|e| xml_error(part, &xml, e))?,
+                            Ok(Event::End(ref inner)) if inner.name().as_ref() == b"definedName" => break,
+                            Ok(Event::Eof) => return Err(xml_error(part, &xml, "unexpected end of document")),
+                            Err(e) => return Err(xml_error(part, &xml, e)),
+                            _ => (),
+                        }
+                    }
+                    // index of the sheet in the workbook, sheets are listed before names
+                    // names of unknown sheets are skipped, so they can't hide global ones
+                    let scope = match scope {
+                        Some(index) => match self.sheets.get(index) {
+                            Some((sheet, _)) => Some(sheet.name.clone()),
+                            None => continue,
+                        },
+                        None => None,
+                    };
+                    self.defined_names.push(DefinedName { name, scope, hidden, reference });
+                },
+                Ok(Event::End(ref e)) if e.name().as_ref() == b"workbook" => break,
+                Ok(Event::Eof) => break,
+                Err(e) => return Err(xml_error(part, &xml, e)),
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    // cell styles of <cellXfs> and differential styles of <dxfs>
+    fn read_style(&mut self, flags: u32) -> Result<(Vec<Dict>, Vec<Dict>), XlsxError> {
+        let part = "xl/styles.xml";
+        let mut xml = match xml_reader(&mut self.zip, part) {
+            None => {
+                return Err(XlsxError::MissingPart { part: String::from(part) })
+            },
+            Some(x) => x?,
+        };
+        let mut buf = Vec::new();
+
+        let mut xml_path: StyleXMLPath = StyleXMLPath::Any;
+        let mut xml_parent_path: StyleXMLPath = StyleXMLPath::Any;
+
+        let theme = &self.theme;
+        let mut fonts: Vec<Dict> = vec!();
+        let mut fills: Vec<Dict> = vec!();
+        let mut fill_struct = Fill::new();
+        let mut borders: Vec<Dict> = vec!();
+        let mut border_structs: Vec<Border> = vec!();
+        let mut diagonal_up = false;
+        let mut diagonal_down = false;
+        // color of skipped elements like <vertical> must not change the previous border
+        let mut skip_border_color = false;
+
+        let mut extra_formats: Dict = HashMap::new();
+
+        let mut styles: Vec<Dict> = vec!();
+        // fonts, fills and borders of <dxf> are merged into it
+        let mut dxfs: Vec<Dict> = vec!();
+
+        loop {
+            buf.clear();
+            match xml.read_event_into(&mut buf) {
+                Ok(Event::Start(ref e)) if xml_parent_path == StyleXMLPath::CellXfs && e.name().as_ref() == b"xf"  => {
+                    xml_path = StyleXMLPath::Xf;
+                    let mut xf = HashMap::new();
+                    for a in e.attributes() {
+                        let att = a.map_err(|e| xml_error(part, &xml, e))?;
+                        match att.key.as_ref() {
+                            b"fontId" => {
+                                let font_style = lookup(&fonts, &xml, part, &att)?;
+                                xf.extend(font_style);
+                            },
+                            b"borderId" => {
+                                let border_style = lookup(&borders, &xml, part, &att)?;
+                                xf.extend(border_style);
+                            },
+                            b"fillId" => {
+                                let fill_style = lookup(&fills, &xml, part, &att)?;
+                                xf.extend(fill_style);
+                            },
+                            b"numFmtId" => {
+                                let format_id = attr_value(&xml, part, &att)?;
+                                // declared formats replace built-in ones, like currency of the locale for ids 5-8
+                                // undeclared ids, like locale dependent built-in ones, are shown as General
+                                let format = match extra_formats.get(&format_id) {
+                                    Some(v) => v.to_owned(),
+                                    None => JsonValue::String(get_format(&format_id).unwrap_or_else(|| String::from("General"))),
+                                };
+                                xf.insert(String::from("format"), format);
+                            },
+                            _ => ()
+                        }
+                    }
+                    styles.push(xf);
+                },
+                Ok(Event::Start(ref e)) if (xml_path == StyleXMLPath::Xf && xml_parent_path == StyleXMLPath::CellXfs || xml_parent_path == StyleXMLPath::Dxfs) && e.name().as_ref() == b"alignment" => {
+                    let xf = match if xml_parent_path == StyleXMLPath::Dxfs { dxfs.last_mut() } else { styles.last_mut() } {
+                        Some(xf) => xf,
+                        None => continue,
+                    };
+                    for a in e.attributes() {
+                        let att = a.map_err(|e| xml_error(part, &xml, e))?;
+                        match att.key.as_ref() {
+                            b"vertical" => {
+                                let value = attr_value(&xml, part, &att)?;
+                                xf.insert(String::from("verticalAlign"), value.into());
+                            },
+                            b"horizontal" => {
+                                let value = attr_value(&xml, part, &att)?;
+                                xf.insert(String::from("align"), value.into());
+                            },
+                            b"wrapText" => {
+                                let value = attr_value(&xml, part, &att)?;
+                                if value == "1" || value == "true" {
+                                    xf.insert(String::from("wrapText"), JsonValue::Bool(true));
+                                }
+                            },
+                            _ => ()
+                        }
+                    }
+                },
+                Ok(Event::Start(ref e)) if e.name().as_ref() == b"numFmt" => {
+                    let mut format_code = String::from("");
+                    let mut format_id = String::from("");
+                    for a in e.attributes() {
+                        let att = a.map_err(|e| xml_error(part, &xml, e))?;
+                        match att.key.as_ref() {
+                            b"formatCode" => {
+                                format_code = attr_value(&xml, part, &att)?;
+                            },
+                            b"numFmtId" => {
+                                format_id = attr_value(&xml, part, &att)?;
+                            },
+                            _ => ()
+                        }
+                    }
+                    if xml_parent_path == StyleXMLPath::Dxfs {
+                        if let Some(dxf) = dxfs.last_mut() {
+                            let format = get_format(&format_id).unwrap_or_else(|| format_code.clone());
+                            dxf.insert(String::from("format"), JsonValue::String(format));
+                        }
+                    }
+                    extra_formats.insert(format_id, JsonValue::String(format_code));
+                },
+                Ok(Event::Start(ref e)) if e.name().as_ref() == b"font" => {
+                    xml_path = StyleXMLPath::Font;
+                    fonts.push(HashMap::new());
+                },
+                Ok(Event::Start(ref e)) if e.name().as_ref() == b"fill" => {
+                    xml_path = StyleXMLPath::Fill;
+                    fill_struct = Fill::new();
+                },
+                Ok(Event::Start(ref e)) if e.name().as_ref() == b"border" => {
+                    xml_path = StyleXMLPath::Border;
+                    diagonal_up = false;
+                    diagonal_down = false;
+                    for a in e.attributes() {
+                        let att = a.map_err(|e| xml_error(part, &xml, e))?;
+                        match att.key.as_ref() {
+                            b"diagonalUp" => {
+                                let v = attr_value(&xml, part, &att)?;
+                                diagonal_up = v == "1" || v == "true";
+                            },
+                            b"diagonalDown" => {
+                                let v = attr_value(&xml, part, &att)?;
+                                diagonal_down = v == "1" || v == "true";
+                            },
+                            _ => ()
+                        }
+                    }
+                },
+                Ok(Event::Start(ref e)) if e.name().as_ref() == b"cellXfs" => {
+                    xml_parent_path = StyleXMLPath::CellXfs;
+                },
+                Ok(Event::Start(ref e)) if e.name().as_ref() == b"dxfs" => {
+                    xml_parent_path = StyleXMLPath::Dxfs;
+                },
+                Ok(Event::Start(ref e)) if xml_parent_path == StyleXMLPath::Dxfs && e.name().as_ref() == b"dxf" => {
+                    dxfs.push(HashMap::new());
+                },
+                // font styles
+                Ok(Event::Start(ref e)) if xml_path == StyleXMLPath::Font && e.name().as_ref() == b"sz"  => {
+                    for a in e.attributes() {
+                        let att = a.map_err(|e| xml_error(part, &xml, e))?;
+                        if att.key.as_ref() == b"val" {
+                            let value = attr_parse::<f32, _>(&xml, part, &att)?;
+                            if let Some(font) = fonts.last_mut() {
+                                font.insert(String::from("fontSize"), JsonValue::String((value / PT_COEF).to_string() + "px"));
+                            }
+                        }
+                    }
+                },
+                Ok(Event::Start(ref e)) if xml_path == StyleXMLPath::Font && e.name().as_ref() ==  b"name" => {
+                    for a in e.attributes() {
+                        let att = a.map_err(|e| xml_error(part, &xml, e))?;
+                        if att.key.as_ref() == b"val" {
+                            let value = attr_value(&xml, part, &att)?;
+                            if let Some(font) = fonts.last_mut() {
+                                font.insert(String::from("fontFamily"), JsonValue::String(value));
+                            }
+                        }
+                    }
+                },
+                Ok(Event::Start(ref e)) if xml_path == StyleXMLPath::Font && e.name().as_ref() == b"color" => {
+                    let font = match fonts.last_mut() {
+                        Some(font) => font,
+                        None => continue,
+                    };
+                    if let Some(color) = read_color(&xml, part, e, theme)? {
+                        font.insert(String::from("color"), JsonValue::String(color));
+                    }
+                },
+                Ok(Event::Start(ref e)) if xml_path == StyleXMLPath::Font && e.name().as_ref() == b"b"  => {
+                    if let Some(font) = fonts.last_mut() {
+                        font.insert(String::from("fontWeight"), JsonValue::String(String::from("bold")));
+                    }
+                },
+                Ok(Event::Start(ref e)) if xml_path == StyleXMLPath::Font && e.name().as_ref() == b"i" => {
+                    if let Some(font) = fonts.last_mut() {
+                        font.insert(String::from("fontStyle"), JsonValue::String(String::from("italic")));
+                    }
+                },
+                Ok(Event::Start(ref e)) if xml_path == StyleXMLPath::Font && e.name().as_ref() == b"u" => {
+                    if let Some(font) = fonts.last_mut() {
+                        if font.contains_key("textDecoration") {
+                            font.insert(String::from("textDecoration"), JsonValue::String(String::from("line-through underline")));
+                        } else {
+                            font.insert(String::from("textDecoration"), JsonValue::String(String::from("underline")));
+                        }
+                    }
+                },
+                Ok(Event::Start(ref e)) if xml_path == StyleXMLPath::Font && e.name().as_ref() == b"strike"  => {
+                    if let Some(font) = fonts.last_mut() {
+                        if font.contains_key("textDecoration") {
+                            font.insert(String::from("textDecoration"), JsonValue::String(String::from("line-through underline")));
+                        } else {
+                            font.insert(String::from("textDecoration"), JsonValue::String(String::from("line-through")));
+                        }
+                    }
+                },
+                // borders styles
+                Ok(Event::Start(ref e)) if xml_path == StyleXMLPath::Border && e.name().as_ref() != b"color" => {
+                    let position = match e.name().as_ref() {
+                        b"left" | b"start" => BorderPosition::Left,
+                        b"right" | b"end" => BorderPosition::Right,
+                        b"top" => BorderPosition::Top,
+                        b"bottom" => BorderPosition::Bottom,
+                        // copied for the other direction on </border>, when both are set
+                        b"diagonal" if diagonal_up => BorderPosition::DiagonalUp,
+                        b"diagonal" if diagonal_down => BorderPosition::DiagonalDown,
+                        _ => {
+                            skip_border_color = true;
+                            continue;
+                        },
+                    };
+                    skip_border_color = false;
+                    let mut border = Border::new(position);
+
+                    for a in e.attributes() {
+                        let att = a.map_err(|e| xml_error(part, &xml, e))?;
+                        if att.key.as_ref() == b"style" {
+                            border.set_style(attr_value(&xml, part, &att)?);
+                        }
+                    }
+                    border_structs.push(border);
+                },
+                Ok(Event::Start(ref e)) if xml_path == StyleXMLPath::Border && e.name().as_ref() == b"color" && !skip_border_color => {
+                    let border = match border_structs.last_mut() {
+                        Some(border) => border,
+                        None => continue,
+                    };
+                    if let Some(color) = read_color(&xml, part, e, theme)? {
+                        border.set_color(color);
+                    }
+                },
+                // fills
+                Ok(Event::Start(ref e)) if xml_path == StyleXMLPath::Fill && e.name().as_ref() == b"patternFill" => {
+                    for a in e.attributes() {
+                        let att = a.map_err(|e| xml_error(part, &xml, e))?;
+                        if att.key.as_ref() == b"patternType" {
+                            fill_struct.set_pattern(attr_value(&xml, part, &att)?);
+                        }
+                    }
+                },
+                Ok(Event::Start(ref e)) if xml_path == StyleXMLPath::Fill && e.name().as_ref() == b"fgColor" => {
+                    if let Some(color) = read_color(&xml, part, e, theme)? {
+                        fill_struct.set_fg_color(color);
+                    }
+                },
+                Ok(Event::Start(ref e)) if xml_path == StyleXMLPath::Fill && e.name().as_ref() == b"bgColor" => {
+                    if let Some(color) = read_color(&xml, part, e, theme)? {
+                        fill_struct.set_bg_color(color);
+                    }
+                },
+                Ok(Event::Start(ref e)) if xml_path == StyleXMLPath::Fill && e.name().as_ref() == b"gradientFill" => {
+                    let mut gradient = Gradient::default();
+                    for a in e.attributes() {
+                        let att = a.map_err(|e| xml_error(part, &xml, e))?;
+                        match att.key.as_ref() {
+                            b"type" => gradient.path = attr_value(&xml, part, &att)? == "path",
+                            b"degree" => gradient.degree = attr_parse::<f64, _>(&xml, part, &att)?,
+                            b"left" => gradient.left = attr_parse::<f64, _>(&xml, part, &att)?,
+                            b"right" => gradient.right = attr_parse::<f64, _>(&xml, part, &att)?,
+                            b"top" => gradient.top = attr_parse::<f64, _>(&xml, part, &att)?,
+                            b"bottom" => gradient.bottom = attr_parse::<f64, _>(&xml, part, &att)?,
+                            _ => ()
+                        }
+                    }
+                    fill_struct.set_gradient(gradient);
+                },
+                Ok(Event::Start(ref e)) if xml_path == StyleXMLPath::Fill && e.name().as_ref() == b"stop" => {
+                    for a in e.attributes() {
+                        let att = a.map_err(|e| xml_error(part, &xml, e))?;
+                        if att.key.as_ref() == b"position" {
+                            fill_struct.add_stop(attr_parse::<f64, _>(&xml, part, &att)?);
+                        }
+                    }
+                },
+                Ok(Event::Start(ref e)) if xml_path == StyleXMLPath::Fill && e.name().as_ref() == b"color" => {
+                    if let Some(color) = read_color(&xml, part, e, theme)? {
+                        fill_struct.set_stop_color(color);
+                    }
+                },
+                Ok(Event::End(ref e)) if e.name().as_ref() == b"cellXfs" || e.name().as_ref() == b"dxfs" => {
+                    xml_parent_path = StyleXMLPath::Any;
+                },
+                Ok(Event::End(ref e)) if xml_parent_path == StyleXMLPath::CellXfs && e.name().as_ref() == b"xf" => {
+                    xml_path = StyleXMLPath::Any;
+                },
+                Ok(Event::End(ref e)) if e.name().as_ref() == b"font" => {
+                    xml_path = StyleXMLPath::Any;
+                    if xml_parent_path == StyleXMLPath::Dxfs {
+                        if let (Some(dxf), Some(font)) = (dxfs.last_mut(), fonts.pop()) {
+                            dxf.extend(font);
+                        }
+                    }
+                },
+                Ok(Event::End(ref e)) if e.name().as_ref() == b"fill" => {
+                    xml_path = StyleXMLPath::Any;
+                    let fill = std::mem::replace(&mut fill_struct, Fill::new());
+                    if xml_parent_path == StyleXMLPath::Dxfs {
+                        if let Some(dxf) = dxfs.last_mut() {
+                            dxf.extend(fill.get_differential_style());
+                        }
+                    } else {
+                        fills.push(fill.get_computed_style());
+                    }
+                },
+                Ok(Event::End(ref e)) if e.name().as_ref() == b"border" => {
+                    xml_path = StyleXMLPath::Any;
+                    let mut border = HashMap::new();
+                    let mut details: HashMap<String, BorderInfo> = HashMap::new();
+
+                    if let Some(diagonal) = border_structs.iter().find(|b| b.is_diagonal()) {
+                        if diagonal_up && diagonal_down {
+                            let mut down = diagonal.clone();
+                            down.set_position(BorderPosition::DiagonalDown);
+                            border_structs.push(down);
+                        }
+                    }
+                    while let Some(border_struct) = border_structs.pop() {
+                        if flags & WITH_BORDER_DETAILS > 0 {
+                            if let Some(info) = border_struct.get_info() {
+                                details.insert(border_struct.get_name(), info);
+                            }
+                        }
+                        let (key, value) = border_struct.get_computed_style();
+                        if !value.is_empty() {
+                            border.insert(key, JsonValue::String(value));
+                        }
+                    }
+                    if !details.is_empty() {
+                        let details = serde_json::to_value(details).map_err(|e| xml_error(part, &xml, e))?;
+                        border.insert(String::from("borders"), details);
+                    }
+                    match dxfs.last_mut() {
+                        Some(dxf) if xml_parent_path == StyleXMLPath::Dxfs => dxf.extend(border),
+                        _ => borders.push(border),
+                    }
+                },
+                Ok(Event::End(ref e)) if e.name().as_ref() == b"styleSheet" => break,
+                Ok(Event::Eof) => break,
+                Err(e) => return Err(xml_error(part, &xml, e)),
+                _ => (),
+            }
+        }
+        Ok((styles, dxfs))
+    }
+}
+
+// <sheetPr> is the first element of the sheet, so reading stops at any other one and the rest of the part is not inflated
+fn read_tab_color<R: BufRead>(xml: &mut XmlReader<R>, part: &str, theme: &Theme) -> Result<Option<String>, XlsxError> {
+    let mut depth = 0;
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        match xml.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) => {
+                if e.name().as_ref() == b"tabColor" {
+                    return read_color(xml, part, e, theme);
+                }
+                if depth == 1 && e.name().as_ref() != b"sheetPr" {
+                    return Ok(None);
+                }
+                depth += 1;
+            },
+            Ok(Event::End(ref e)) if e.name().as_ref() == b"sheetPr" => return Ok(None),
+            Ok(Event::End(_)) => depth -= 1,
+            Ok(Event::Eof) => return Ok(None),
+            Err(e) => return Err(xml_error(part, xml, e)),
+            _ => (),
+        }
+    }
+}
+
+// part of the archive which doesn't borrow it, inflated while it is read
+pub(crate) enum PartReader {
+    Stored(Take<Cursor<SharedBytes>>),
+    Deflated(DeflateDecoder<Take<Cursor<SharedBytes>>>),
+}
+
+impl Read for PartReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            PartReader::Stored(reader) => reader.read(buf),
+            PartReader::Deflated(reader) => reader.read(buf),
+        }
+    }
+}
+
+// targets of relationships by id, None if there is no such part
+pub(crate) fn read_relationships(zip: &mut Archive, part: &str) -> Result<Option<HashMap<String, String>>, XlsxError> {
+    let relationships = read_typed_relationships(zip, part)?;
+    Ok(relationships.map(|r| r.into_iter().map(|(id, (target, _))| (id, target)).collect()))
+}
+
+// target and type of each relationship, like ("worksheets/sheet1.xml", ".../relationships/worksheet")
+pub(crate) fn read_typed_relationships(zip: &mut Archive, part: &str) -> Result<Option<TypedRelationships>, XlsxError> {
+    let mut xml = match xml_reader(zip, part) {
+        None => return Ok(None),
+        Some(x) => x?,
+    };
+    let mut relationships = HashMap::new();
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        match xml.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) if e.name().as_ref() == b"Relationship" => {
+                let mut id = String::new();
+                let mut target = String::new();
+                let mut kind = String::new();
+                for a in e.attributes() {
+                    let att = a.map_err(|e| xml_error(part, &xml, e))?;
+                    match att.key.as_ref() {
+                        b"Id" => id = attr_value(&xml, part, &att)?,
+                        b"Target" => target = attr_value(&xml, part, &att)?,
+                        b"Type" => kind = attr_value(&xml, part, &att)?,
+                        _ => (),
+                    }
+                }
+                if !id.is_empty() && !target.is_empty() {
+                    relationships.insert(id, (target, kind));
+                }
+            }
+            Ok(Event::End(ref e)) if e.name().as_ref() == b"Relationships" => break,
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(xml_error(part, &xml, e)),
+            _ => (),
+        }
+    }
+    Ok(Some(relationships))
+}
+
+// kind of the sheet by the type of its relationship, worksheet when the type is unknown
+pub(crate) fn sheet_kind(relationship: &str) -> SheetKind {
+    match relationship.rsplit('/').next() {
+        Some("chartsheet") => SheetKind::Chartsheet,
+        Some("dialogsheet") => SheetKind::Dialogsheet,
+        Some("xlMacrosheet") | Some("xlIntlMacrosheet") => SheetKind::Macrosheet,
+        _ => SheetKind::Worksheet,
+    }
+}
+
+// path in the archive for the target of workbook relationship
+pub(crate) fn part_path(target: &str) -> String {
+    if let Some(stripped) = target.strip_prefix('/') {
+        stripped.to_string()
+    } else if target.starts_with("xl/") {
+        target.to_string()
+    } else {
+        format!("xl/{}", target)
+    }
+}
+
+// "xl/worksheets/sheet1.xml" -> "xl/worksheets/_rels/sheet1.xml.rels"
+pub(crate) fn rels_path(part: &str) -> String {
+    match part.rfind('/') {
+        Some(i) => format!("{}/_rels/{}.rels", &part[..i], &part[i + 1..]),
+        None => format!("_rels/{}.rels", part),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CellType, CellValue, WITH_FORMULAS, WITH_RICH_TEXT, WITH_SPARSE_CELLS, WITH_TYPED_VALUES};
+
+    fn read_example(name: &str) -> Vec<u8> {
+        std::fs::read(format!("./example/{}", name)).unwrap()
+    }
+
+    // minimal workbook with a single sheet, `sheet_data` is the content of <sheetData>
+    fn build_xlsx(sheet_data: &str) -> Vec<u8> {
+        build_xlsx_with(sheet_data, &[])
+    }
+
+    // same as above, with extra or replaced parts
+    fn build_xlsx_with(sheet_data: &str, extra: &[(&str, &str)]) -> Vec<u8> {
+        use std::io::Write;
+
+        let mut parts = vec![
+            ("xl/_rels/workbook.xml.rels", String::from(r#"<Relationships><Relationship Id="rId1" Target="worksheets/sheet1.xml"/></Relationships>"#)),
+            ("xl/workbook.xml", String::from(r#"<workbook><sheets><sheet name="Sheet1" sheetId="1" r:id="rId1"/></sheets></workbook>"#)),
+            ("xl/worksheets/sheet1.xml", format!("<worksheet><sheetData>{}</sheetData></worksheet>", sheet_data)),
+        ];
+        for (name, content) in extra.iter() {
+            parts.retain(|(n, _)| n != name);
+            parts.push((name, content.to_string()));
+        }
+        let mut zip = zip::ZipWriter::new(Cursor::new(vec!()));
+        for (name, content) in parts.iter() {
+            zip.start_file(*name, zip::write::FileOptions::default()).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn file_read() {
+        let now = std::time::Instant::now();
+        {
+            let mut workbook = Workbook::open(&read_example("file_example_XLSX_5000.xlsx")).unwrap();
+            let name = workbook.sheet_names()[0].clone();
+            let data = workbook.sheet(&name).unwrap();
+            assert_eq!(data.name, name);
+            assert!(data.cells.len() > 5000);
+            workbook.styles().unwrap();
+        }
+        let elapsed = now.elapsed();
+        let sec = (elapsed.as_secs() as f64) + (elapsed.subsec_nanos() as f64 / 1_000_000_000.0);
+        println!("time to read 5000 rows: {}",  sec);
+    }
+
+    #[test]
+    fn open_errors() {
+        match Workbook::from_vec(b"not a zip".to_vec()) {
+            Err(XlsxError::BadZip { .. }) => (),
+            _ => panic!("expected BadZip error"),
+        }
+
+        let mut zip = zip::ZipWriter::new(Cursor::new(vec!()));
+        zip.start_file("[Content_Types].xml", zip::write::FileOptions::default()).unwrap();
+        let data = zip.finish().unwrap().into_inner();
+        match Workbook::from_vec(data) {
+            Err(XlsxError::MissingPart { part }) => assert_eq!(part, "xl/_rels/workbook.xml.rels"),
+            _ => panic!("expected MissingPart error"),
+        }
+
+        let mut xlsx = Workbook::from_vec(read_example("file_example_XLSX_100.xlsx")).unwrap();
+        match xlsx.sheet_with("missing", 0) {
+            Err(XlsxError::UnknownSheet { name }) => assert_eq!(name, "missing"),
+            _ => panic!("expected UnknownSheet error"),
+        }
+    }
+
+    #[test]
+    fn typed_values() {
+        let mut xlsx = Workbook::from_vec(read_example("file_example_XLSX_100.xlsx")).unwrap();
+        let name = xlsx.sheet_names()[0].clone();
+
+        let data = xlsx.sheet_with(&name, 0).unwrap();
+        let cell = data.cells[1][5].as_ref().unwrap();
+        assert_eq!(cell.t, Some(CellType::Number));
+        assert_eq!(cell.v, Some(CellValue::String(String::from("32"))));
+
+        let data = xlsx.sheet_with(&name, WITH_TYPED_VALUES).unwrap();
+        let cell = data.cells[1][5].as_ref().unwrap();
+        assert_eq!(cell.v, Some(CellValue::Number(32.0)));
+        let cell = data.cells[1][1].as_ref().unwrap();
+        assert_eq!(cell.t, Some(CellType::String));
+        assert_eq!(cell.v, Some(CellValue::String(String::from("Dulce"))));
+    }
+
+    #[test]
+    fn inline_strings() {
+        let mut xlsx = Workbook::from_vec(build_xlsx(concat!(
+            r#"<row r="1">"#,
+            r#"<c r="A1" t="inlineStr"><is><t>plain</t></is></c>"#,
+            r#"<c r="B1" t="inlineStr"><is><r><t>rich </t></r><r><rPr><b/></rPr><t>text</t></r><rPh sb="0" eb="1"><t>x</t></rPh></is></c>"#,
+            r#"<c r="C1" t="inlineStr"><is><t/></is></c>"#,
+            r#"</row>"#,
+        ))).unwrap();
+        let data = xlsx.sheet_with("Sheet1", 0).unwrap();
+
+        let cell = data.cells[0][0].as_ref().unwrap();
+        assert_eq!(cell.v, Some(CellValue::String(String::from("plain"))));
+        assert_eq!(cell.t, Some(CellType::String));
+        let cell = data.cells[0][1].as_ref().unwrap();
+        assert_eq!(cell.v, Some(CellValue::String(String::from("rich text"))));
+        assert!(data.cells[0][2].is_none());
+    }
+
+    #[test]
+    fn rich_text() {
+        let shared = concat!(
+            r#"<sst><si><t>plain</t></si>"#,
+            r#"<si><r><rPr><b/></rPr><t>bold</t></r><r><t xml:space="preserve"> normal</t></r></si></sst>"#,
+        );
+        let sheet = concat!(
+            r#"<row r="1"><c r="A1" t="s"><v>0</v></c><c r="B1" t="s"><v>1</v></c>"#,
+            r#"<c r="C1" t="inlineStr"><is><r><rPr><i/></rPr><t>inline</t></r></is></c></row>"#,
+        );
+        let mut xlsx = Workbook::from_vec(build_xlsx_with(sheet, &[("xl/sharedStrings.xml", shared)])).unwrap();
+
+        let data = xlsx.sheet_with("Sheet1", WITH_RICH_TEXT).unwrap();
+        let row = &data.cells[0];
+        assert!(row[0].as_ref().unwrap().rich.is_none());
+        let cell = row[1].as_ref().unwrap();
+        assert_eq!(cell.v, Some(CellValue::String(String::from("bold normal"))));
+        let runs = cell.rich.as_ref().unwrap();
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0].text, "bold");
+        assert_eq!(runs[0].style["fontWeight"], "bold");
+        assert_eq!(runs[1].text, " normal");
+        assert!(runs[1].style.is_empty());
+        let runs = row[2].as_ref().unwrap().rich.as_ref().unwrap();
+        assert_eq!(runs[0].style["fontStyle"], "italic");
+
+        let data = xlsx.sheet_with("Sheet1", 0).unwrap();
+        assert!(data.cells[0][1].as_ref().unwrap().rich.is_none());
+    }
+
+    #[test]
+    fn theme_colors() {
+        let theme = concat!(
+            r#"<a:theme xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main"><a:themeElements><a:clrScheme name="Office">"#,
+            r#"<a:dk1><a:sysClr val="windowText" lastClr="000000"/></a:dk1><a:lt1><a:sysClr val="window" lastClr="FFFFFF"/></a:lt1>"#,
+            r#"<a:dk2><a:srgbClr val="44546A"/></a:dk2><a:lt2><a:srgbClr val="E7E6E6"/></a:lt2><a:accent1><a:srgbClr val="5B9BD5"/></a:accent1>"#,
+            r#"</a:clrScheme></a:themeElements></a:theme>"#,
+        );
+        let styles = concat!(
+            r#"<styleSheet><fonts><font><color theme="1"/></font><font><color theme="4" tint="-0.249977111117893"/></font><font><color indexed="1"/></font><font><color indexed="65"/></font></fonts>"#,
+            r#"<fills><fill><patternFill patternType="solid"><fgColor theme="4" tint="0.3999755851924192"/></patternFill></fill></fills>"#,
+            r#"<borders><border><left style="thin"><color theme="3"/></left></border></borders>"#,
+            r#"<cellXfs><xf numFmtId="0" fontId="1" fillId="0" borderId="0"/><xf numFmtId="0" fontId="2"/><xf numFmtId="0" fontId="3"/></cellXfs>"#,
+            r#"<colors><indexedColors><rgbColor rgb="FF000000"/><rgbColor rgb="FF00FF00"/></indexedColors></colors></styleSheet>"#,
+        );
+        let mut xlsx = Workbook::from_vec(build_xlsx_with("", &[("xl/styles.xml", styles), ("xl/theme/theme1.xml", theme)])).unwrap();
+        let styles = xlsx.styles_with(0).unwrap();
+
+        assert_eq!(styles[0]["color"], "rgba(46,117,182,1)");
+        assert_eq!(styles[0]["background"], "rgba(157,195,230,1)");
+        assert_eq!(styles[0]["borderLeft"], "0.5px solid rgba(68,84,106,1)");
+        assert_eq!(styles[1]["color"], "rgba(0,255,0,1)");
+        assert_eq!(styles[2]["color"], "#FFFFFF");
+    }
+
+    #[test]
+    fn border_styles() {
+        let styles = concat!(
+            r#"<styleSheet><fonts><font/></fonts><fills><fill/></fills><borders>"#,
+            r#"<border diagonalUp="1" diagonalDown="1"><left style="mediumDashDot"><color rgb="FFFF0000"/></left><right/><top/>"#,
+            r#"<bottom style="dashed"/><diagonal style="thin"><color rgb="FF00FF00"/></diagonal></border>"#,
+            r#"<border><bottom style="thick"/><diagonal style="thin"><color rgb="FF00FF00"/></diagonal></border></borders>"#,
+            r#"<cellXfs><xf numFmtId="0" borderId="0"/><xf numFmtId="0" borderId="1"/></cellXfs></styleSheet>"#,
+        );
+        let mut xlsx = Workbook::from_vec(build_xlsx_with("", &[("xl/styles.xml", styles)])).unwrap();
+
+        let styles = xlsx.styles_with(0).unwrap();
+        assert_eq!(styles[0]["borderLeft"], "1px dashed rgba(255,0,0,1)");
+        assert_eq!(styles[0]["borderBottom"], "0.5px dashed #000000");
+        assert_eq!(styles[0]["borderDiagonalUp"], "0.5px solid rgba(0,255,0,1)");
+        assert_eq!(styles[0]["borderDiagonalDown"], "0.5px solid rgba(0,255,0,1)");
+        assert!(!styles[0].contains_key("borders"));
+        // diagonal without direction is not shown and keeps color of the bottom border
+        assert_eq!(styles[1]["borderBottom"], "2px solid #000000");
+        assert!(!styles[1].contains_key("borderDiagonalUp"));
+
+        let styles = xlsx.styles_with(WITH_BORDER_DETAILS).unwrap();
+        let left = &styles[0]["borders"]["left"];
+        assert_eq!(left["width"], "1px");
+        assert_eq!(left["style"], "dashed");
+        assert_eq!(left["color"], "rgba(255,0,0,1)");
+        assert_eq!(left["xlsxStyle"], "mediumDashDot");
+        assert_eq!(styles[0]["borders"]["diagonalDown"]["xlsxStyle"], "thin");
+    }
+
+    #[test]
+    fn fill_styles() {
+        let styles = concat!(
+            r#"<styleSheet><fonts><font/></fonts><fills>"#,
+            r#"<fill><patternFill patternType="none"><fgColor rgb="FFFF0000"/></patternFill></fill>"#,
+            r#"<fill><patternFill patternType="solid"><fgColor theme="4"/><bgColor indexed="64"/></patternFill></fill>"#,
+            r#"<fill><patternFill patternType="lightTrellis"><fgColor rgb="FFFF0000"/><bgColor rgb="FFFFFF00"/></patternFill></fill>"#,
+            r#"<fill><gradientFill degree="45"><stop position="0"><color rgb="FFFFFFFF"/></stop><stop position="1"><color theme="4"/></stop></gradientFill></fill>"#,
+            r#"<fill><gradientFill type="path" left="0.5" right="0.5" top="0.5" bottom="0.5"><stop position="0"><color rgb="FFFFFFFF"/></stop><stop position="1"><color rgb="FF000000"/></stop></gradientFill></fill>"#,
+            r#"</fills><borders><border/></borders><cellXfs>"#,
+            r#"<xf numFmtId="0" fillId="0"/><xf numFmtId="0" fillId="1"/><xf numFmtId="0" fillId="2"/><xf numFmtId="0" fillId="3"/><xf numFmtId="0" fillId="4"/>"#,
+            r#"</cellXfs></styleSheet>"#,
+        );
+        let mut xlsx = Workbook::from_vec(build_xlsx_with("", &[("xl/styles.xml", styles)])).unwrap();
+        let styles = xlsx.styles_with(0).unwrap();
+
+        assert!(!styles[0].contains_key("background"));
+        assert_eq!(styles[1]["background"], "rgba(68,114,196,1)");
+        assert_eq!(styles[2]["background"], "rgba(255,255,0,1)");
+        assert_eq!(styles[2]["pattern"]["type"], "lightTrellis");
+        assert_eq!(styles[2]["pattern"]["fgColor"], "rgba(255,0,0,1)");
+        assert_eq!(styles[3]["background"], "linear-gradient(135deg, rgba(255,255,255,1) 0%, rgba(68,114,196,1) 100%)");
+        assert_eq!(styles[4]["background"], "radial-gradient(at 50% 50%, rgba(255,255,255,1) 0%, rgba(0,0,0,1) 100%)");
+    }
+
+    #[test]
+    fn dates() {
+        let styles = concat!(
+            r#"<styleSheet><numFmts><numFmt numFmtId="164" formatCode="yyyy\-mm\-dd\ hh:mm"/></numFmts>"#,
+            r#"<fonts><font/></fonts><fills><fill/></fills><borders><border/></borders>"#,
+            r#"<cellXfs><xf numFmtId="0"/><xf numFmtId="14"/><xf numFmtId="164"/><xf numFmtId="21"/></cellXfs></styleSheet>"#,
+        );
+        let sheet = concat!(
+            r#"<row r="1"><c r="A1" s="1"><v>45123</v></c><c r="B1" s="2"><v>45123.5</v></c>"#,
+            r#"<c r="C1" s="3"><v>0.5</v></c><c r="D1"><v>45123</v></c></row>"#,
+        );
+
+        let mut xlsx = Workbook::from_vec(build_xlsx_with(sheet, &[("xl/styles.xml", styles)])).unwrap();
+        let data = xlsx.sheet_with("Sheet1", WITH_DATES).unwrap();
+        let row = &data.cells[0];
+        assert_eq!(row[0].as_ref().unwrap().v, Some(CellValue::String(String::from("2023-07-16"))));
+        assert_eq!(row[0].as_ref().unwrap().t, Some(CellType::Date));
+        assert_eq!(row[1].as_ref().unwrap().v, Some(CellValue::String(String::from("2023-07-16T12:00:00"))));
+        assert_eq!(row[2].as_ref().unwrap().v, Some(CellValue::String(String::from("12:00:00"))));
+        assert_eq!(row[3].as_ref().unwrap().t, Some(CellType::Number));
+
+        let data = xlsx.sheet_with("Sheet1", 0).unwrap();
+        assert_eq!(data.cells[0][0].as_ref().unwrap().v, Some(CellValue::String(String::from("45123"))));
+
+        let workbook = r#"<workbook><workbookPr date1904="1"/><sheets><sheet name="Sheet1" sheetId="1" r:id="rId1"/></sheets></workbook>"#;
+        let mut xlsx = Workbook::from_vec(build_xlsx_with(sheet, &[("xl/styles.xml", styles), ("xl/workbook.xml", workbook)])).unwrap();
+        let data = xlsx.sheet_with("Sheet1", WITH_DATES).unwrap();
+        assert_eq!(data.cells[0][0].as_ref().unwrap().v, Some(CellValue::String(String::from("2027-07-17"))));
+    }
+
+    #[test]
+    fn formatted_text() {
+        let styles = concat!(
+            r#"<styleSheet><numFmts><numFmt numFmtId="164" formatCode="&quot;Name: &quot;@"/><numFmt numFmtId="165" formatCode="[Color1]0"/></numFmts>"#,
+            r#"<fonts><font/></fonts><fills><fill/></fills><borders><border/></borders>"#,
+            r#"<cellXfs><xf numFmtId="0"/><xf numFmtId="10"/><xf numFmtId="14"/><xf numFmtId="164"/><xf numFmtId="165"/><xf numFmtId="47"/></cellXfs>"#,
+            r#"<colors><indexedColors><rgbColor rgb="FF000000"/><rgbColor rgb="FF000000"/><rgbColor rgb="FF000000"/><rgbColor rgb="FF000000"/>"#,
+            r#"<rgbColor rgb="FF000000"/><rgbColor rgb="FF000000"/><rgbColor rgb="FF000000"/><rgbColor rgb="FF000000"/><rgbColor rgb="FF010203"/></indexedColors></colors></styleSheet>"#,
+        );
+        let sheet = concat!(
+            r#"<row r="1"><c r="A1" s="1"><v>0.1234</v></c><c r="B1" s="2"><v>45123</v></c>"#,
+            r#"<c r="C1" s="3" t="inlineStr"><is><t>Bob</t></is></c><c r="D1" t="b"><v>1</v></c>"#,
+            r#"<c r="E1" s="1"><f>A1*2</f><v>0.2468</v></c><c r="F1" s="4"><v>7</v></c>"#,
+            // 5 min 30 s
+            r#"<c r="G1" s="5"><v>0.00381944444444444</v></c></row>"#,
+        );
+
+        let mut xlsx = Workbook::from_vec(build_xlsx_with(sheet, &[("xl/styles.xml", styles)])).unwrap();
+        let data = xlsx.sheet_with("Sheet1", WITH_FORMATTED_TEXT | WITH_FORMULAS).unwrap();
+        let text: Vec<Option<String>> = data.cells[0].iter().map(|c| c.as_ref().unwrap().w.clone()).collect();
+        assert_eq!(text, vec![
+            Some(String::from("12.34%")),
+            Some(String::from("07-16-23")),
+            Some(String::from("Name: Bob")),
+            Some(String::from("TRUE")),
+            Some(String::from("24.68%")),
+            Some(String::from("7")),
+            Some(String::from("05:30.0")),
+        ]);
+        // [Color1] of the custom palette
+        assert_eq!(data.cells[0][5].as_ref().unwrap().text_color.as_deref(), Some("rgba(1,2,3,1)"));
+        assert!(data.cells[0][0].as_ref().unwrap().text_color.is_none());
+        assert_eq!(data.cells[0][4].as_ref().unwrap().v, Some(CellValue::String(String::from("=A1*2"))));
+
+        let data = xlsx.sheet_with("Sheet1", 0).unwrap();
+        assert!(data.cells[0][0].as_ref().unwrap().w.is_none());
+    }
+
+    #[test]
+    fn undeclared_formats() {
+        // 27 depends on the locale, 7 is declared with other currency
+        let styles = concat!(
+            r##"<styleSheet><numFmts><numFmt numFmtId="7" formatCode="#,##0.00 &quot;EUR&quot;"/></numFmts>"##,
+            r#"<fonts><font/></fonts><fills><fill/></fills><borders><border/></borders>"#,
+            r#"<cellXfs><xf numFmtId="0"/><xf numFmtId="5"/><xf numFmtId="27"/><xf numFmtId="7"/><xf numFmtId="39"/></cellXfs></styleSheet>"#,
+        );
+        let sheet = r#"<row r="1"><c r="A1" s="1"><v>1234</v></c><c r="B1" s="2"><v>45123</v></c><c r="C1" s="3"><v>1.5</v></c><c r="D1" s="4"><v>-2</v></c></row>"#;
+        let mut xlsx = Workbook::from_vec(build_xlsx_with(sheet, &[("xl/styles.xml", styles)])).unwrap();
+
+        let styles = xlsx.styles().unwrap();
+        let formats: Vec<&str> = styles.iter().map(|style| style["format"].as_str().unwrap()).collect();
+        assert_eq!(formats, vec!["General", "$#,##0_);($#,##0)", "General", "#,##0.00 \"EUR\"", "#,##0.00;(#,##0.00)"]);
+
+        let data = xlsx.sheet_with("Sheet1", WITH_FORMATTED_TEXT | WITH_DATES).unwrap();
+        let text: Vec<Option<String>> = data.cells[0].iter().map(|c| c.as_ref().unwrap().w.clone()).collect();
+        assert_eq!(text, vec![
+            Some(String::from("$1,234 ")),
+            Some(String::from("45123")),
+            Some(String::from("1.50 EUR")),
+            Some(String::from("(2.00)")),
+        ]);
+    }
+
+    #[test]
+    fn rows_iterator() {
+        let sheet = concat!(
+            r#"<row r="2" ht="30"><c r="B2"><v>1</v></c><c r="D2" t="inlineStr"><is><t>text</t></is></c></row>"#,
+            r#"<row><c r="A3" s="1"/></row><row r="7" hidden="1"></row>"#,
+        );
+        let mut xlsx = Workbook::from_vec(build_xlsx(sheet)).unwrap();
+
+        let mut rows = xlsx.rows_with("Sheet1", WITH_TYPED_VALUES).unwrap();
+        assert_eq!(rows.progress(), 0.0);
+        let row = rows.next().unwrap().unwrap();
+        assert_eq!(row.index, 1);
+        assert_eq!(row.height, 40.0);
+        let cells: Vec<(u32, u32)> = row.cells.iter().map(|cell| (cell.r, cell.c)).collect();
+        assert_eq!(cells, vec![(1, 1), (1, 3)]);
+        assert_eq!(row.cells[0].cell.v, Some(CellValue::Number(1.0)));
+
+        // row without index follows the previous one, styled cell without value is kept
+        let row = rows.next().unwrap().unwrap();
+        assert_eq!(row.index, 2);
+        assert_eq!(row.cells[0].c, 0);
+        assert_eq!(row.cells[0].cell.s, 1);
+
+        let row = rows.next().unwrap().unwrap();
+        assert_eq!(row.index, 6);
+        assert_eq!(row.hidden, Some(true));
+        assert!(row.cells.is_empty());
+        assert!(rows.next().is_none());
+        assert_eq!(rows.progress(), 1.0);
+        drop(rows);
+
+        assert!(matches!(xlsx.rows("Sheet2"), Err(XlsxError::UnknownSheet { .. })));
+        let broken = build_xlsx(r#"<c r="A1"><v>1</v></c>"#);
+        let mut xlsx = Workbook::from_vec(broken).unwrap();
+        let mut rows = xlsx.rows("Sheet1").unwrap();
+        assert!(matches!(rows.next(), Some(Err(XlsxError::MalformedXml { .. }))));
+        assert!(rows.next().is_none());
+    }
+
+    #[test]
+    fn tab_color_of_sheet_start() {
+        // rest of the sheet is broken, so reading it would fail
+        let rest = "<sheetData><row></sheetData>";
+        let read = |start: &str| {
+            let content = format!("{}{}", start, rest);
+            let mut xml = xml_reader_from(Cursor::new(content.as_bytes()));
+            let color = read_tab_color(&mut xml, "sheet1.xml", &Theme::default()).unwrap();
+            assert!(xml.buffer_position() <= start.len(), "{}", start);
+            color
+        };
+        assert_eq!(read(r#"<worksheet><sheetPr filterMode="1"><outlinePr/><tabColor rgb="FF0000FF"/></sheetPr>"#).as_deref(), Some("rgba(0,0,255,1)"));
+        assert_eq!(read(r#"<worksheet><sheetPr><outlinePr summaryBelow="0"/></sheetPr>"#), None);
+        assert_eq!(read(r#"<?xml version="1.0"?><worksheet><dimension ref="A1"/>"#), None);
+    }
+
+    #[test]
+    fn row_cursor() {
+        let sheet: String = (1..=5).map(|i| format!(r#"<row r="{}"><c r="A{}"><v>{}</v></c></row>"#, i, i, i)).collect();
+
+        let mut xlsx = Workbook::from_vec(build_xlsx(&sheet)).unwrap();
+        let mut cursor = xlsx.row_cursor("Sheet1", 0).unwrap();
+        let rows = cursor.next_rows(&xlsx, 2).unwrap();
+        assert_eq!(rows.iter().map(|r| r.index).collect::<Vec<u32>>(), vec![0, 1]);
+        assert!(cursor.progress() > 0.0 && cursor.progress() < 1.0);
+        assert_eq!(cursor.next_rows(&xlsx, 10).unwrap().len(), 3);
+        assert!(cursor.next_rows(&xlsx, 10).unwrap().is_empty());
+        assert_eq!(cursor.progress(), 1.0);
+
+        // same cells as in the dense form
+        let data = xlsx.sheet("Sheet1").unwrap();
+        assert_eq!(data.cells.len(), 5);
+        assert_eq!(data.cells[4][0].as_ref().unwrap().v, Some(CellValue::String(String::from("5"))));
+
+        // sheet part without compression
+        let mut zip = zip::ZipWriter::new(Cursor::new(vec!()));
+        let stored = zip::write::FileOptions::default().compression_method(CompressionMethod::Stored);
+        let mut source = ZipArchive::new(Cursor::new(build_xlsx(&sheet))).unwrap();
+        for i in 0..source.len() {
+            let mut file = source.by_index(i).unwrap();
+            let mut content = vec!();
+            file.read_to_end(&mut content).unwrap();
+            zip.start_file(file.name(), stored).unwrap();
+            std::io::Write::write_all(&mut zip, &content).unwrap();
+        }
+        let mut xlsx = Workbook::from_vec(zip.finish().unwrap().into_inner()).unwrap();
+        let mut cursor = xlsx.row_cursor("Sheet1", 0).unwrap();
+        assert_eq!(cursor.next_rows(&xlsx, 10).unwrap().len(), 5);
+
+        // rest of parsed rows
+        let mut csv = Workbook::from_csv(b"a\nb\nc".to_vec(), CsvOptions::default());
+        let mut rows = csv.rows("Sheet1").unwrap();
+        assert_eq!(rows.next().unwrap().unwrap().index, 0);
+        let data = rows.read_all().unwrap();
+        assert_eq!(data.cells.len(), 3);
+        assert!(data.cells[0][0].is_none());
+        assert_eq!(data.cells[2][0].as_ref().unwrap().v, Some(CellValue::String(String::from("c"))));
+    }
+
+    #[test]
+    fn sparse_cells() {
+        let rels = r#"<Relationships><Relationship Id="rId1" Target="https://dhtmlx.com" TargetMode="External"/></Relationships>"#;
+        let sheet = concat!(
+            r#"<worksheet><cols><col min="3" max="4" width="20" customWidth="1"/></cols><sheetData><row r="1"><c r="A1"><v>1</v></c><c r="B1"/></row>"#,
+            r#"<row r="3" ht="30" customHeight="1"><c r="XFD3" t="inlineStr"><is><t>far</t></is></c></row><row r="5"><c r="A5"/></row></sheetData>"#,
+            r#"<hyperlinks><hyperlink ref="XFD3" r:id="rId1"/></hyperlinks></worksheet>"#,
+        );
+        let xlsx = build_xlsx_with("", &[("xl/worksheets/sheet1.xml", sheet), ("xl/worksheets/_rels/sheet1.xml.rels", rels)]);
+        let mut xlsx = Workbook::from_vec(xlsx).unwrap();
+
+        let data = xlsx.sheet_with("Sheet1", WITH_SPARSE_CELLS).unwrap();
+        assert!(data.cells.is_empty());
+        // only custom columns and rows, with their index
+        assert_eq!(data.cols.iter().map(|col| col.index).collect::<Vec<Option<u32>>>(), vec![Some(2), Some(3)]);
+        assert_eq!(data.rows.iter().map(|row| row.index).collect::<Vec<Option<u32>>>(), vec![Some(2)]);
+        assert_eq!(serde_json::to_value(&data.rows[0]).unwrap(), serde_json::json!({ "height": 40.0, "index": 2 }));
+        let cells = data.sparse_cells.unwrap();
+        assert_eq!(cells.iter().map(|cell| (cell.r, cell.c)).collect::<Vec<(u32, u32)>>(), vec![(0, 0), (2, 16383)]);
+        assert_eq!(cells[1].cell.hyperlink.as_deref(), Some("https://dhtmlx.com"));
+
+        let json = serde_json::to_value(&cells[0]).unwrap();
+        assert_eq!(json, serde_json::json!({ "r": 0, "c": 0, "v": "1", "s": 0, "t": "number" }));
+
+        // dense form of the same sheet
+        let data = xlsx.sheet("Sheet1").unwrap();
+        assert!(data.sparse_cells.is_none());
+        assert_eq!(data.cells.len(), 3);
+        assert_eq!((data.rows.len(), data.cols.len()), (3, 16384));
+        assert!(data.cols.iter().all(|col| col.index.is_none()));
+        assert_eq!(data.cells[2][16383].as_ref().unwrap().hyperlink.as_deref(), Some("https://dhtmlx.com"));
+    }
+
+    #[test]
+    fn sheet_descriptors() {
+        let rels = r#"<Relationships>
+            <Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/>
+            <Relationship Id="rId2" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet2.xml"/>
+            <Relationship Id="rId3" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/chartsheet" Target="chartsheets/sheet1.xml"/>
+        </Relationships>"#;
+        let workbook = r#"<workbook><sheets>
+            <sheet name="Data" sheetId="4" r:id="rId1"/>
+            <sheet name="Lookup" sheetId="2" state="hidden" r:id="rId2"/>
+            <sheet name="Chart" sheetId="7" state="veryHidden" r:id="rId3"/>
+        </sheets></workbook>"#;
+        let lookup = r#"<worksheet><sheetPr><tabColor rgb="FFFF0000"/></sheetPr><sheetData><row r="1"><c r="A1"><v>1</v></c></row></sheetData></worksheet>"#;
+        let chart = r#"<chartsheet><sheetPr><tabColor rgb="FF00FF00"/></sheetPr><sheetViews><sheetView workbookViewId="0"/></sheetViews><drawing r:id="rId1"/></chartsheet>"#;
+        let mut xlsx = Workbook::from_vec(build_xlsx_with("", &[
+            ("xl/_rels/workbook.xml.rels", rels),
+            ("xl/workbook.xml", workbook),
+            ("xl/worksheets/sheet2.xml", lookup),
+            ("xl/chartsheets/sheet1.xml", chart),
+        ])).unwrap();
+
+        let sheets = xlsx.sheets();
+        assert_eq!(sheets[0], SheetDescriptor::new(String::from("Data"), 4));
+        assert_eq!((sheets[1].id, sheets[1].state, sheets[1].kind), (2, SheetState::Hidden, SheetKind::Worksheet));
+        assert_eq!(sheets[1].tab_color.as_deref(), Some("rgba(255,0,0,1)"));
+        assert_eq!((sheets[2].state, sheets[2].kind), (SheetState::VeryHidden, SheetKind::Chartsheet));
+        assert_eq!(sheets[2].tab_color.as_deref(), Some("rgba(0,255,0,1)"));
+        assert_eq!(sheets[0].tab_color, None);
+
+        assert_eq!(xlsx.sheet("Lookup").unwrap().cells.len(), 1);
+        // chart sheet is not read as a worksheet
+        let chart = xlsx.sheet_with("Chart", WITH_SPARSE_CELLS).unwrap();
+        assert_eq!(chart.name, "Chart");
+        assert!(chart.cells.is_empty());
+        assert_eq!(chart.sparse_cells.map(|cells| cells.len()), Some(0));
+        assert_eq!(xlsx.rows("Chart").unwrap().count(), 0);
+
+        let value = serde_json::to_value(&sheets[2]).unwrap();
+        assert_eq!(value["state"], "veryHidden");
+        assert_eq!(value["type"], "chartsheet");
+    }
+
+    #[test]
+    fn validation_values() {
+        let rels = r#"<Relationships><Relationship Id="rId1" Target="worksheets/sheet1.xml"/><Relationship Id="rId2" Target="worksheets/sheet2.xml"/></Relationships>"#;
+        let workbook = r#"<workbook><sheets><sheet name="Sheet1" sheetId="1" r:id="rId1"/><sheet name="My Lists" sheetId="2" r:id="rId2"/></sheets>
+            <definedNames><definedName name="Sizes">'My Lists'!$A$1:$A$5</definedName><definedName name="sizes" localSheetId="0">'My Lists'!$B$1:$B$2</definedName></definedNames></workbook>"#;
+        let sheet = r#"<worksheet><sheetData><row r="1"><c r="A1" t="inlineStr"><is><t>x</t></is></c><c r="B1"><v>7</v></c></row></sheetData><dataValidations count="5">
+            <dataValidation type="list" sqref="C1"><formula1>"a,b"</formula1></dataValidation>
+            <dataValidation type="list" sqref="C2"><formula1>'My Lists'!$A$1:$A$5</formula1></dataValidation>
+            <dataValidation type="list" sqref="C3"><formula1>SIZES</formula1></dataValidation>
+            <dataValidation type="list" sqref="C4"><formula1>$A$1:$B$1</formula1></dataValidation>
+            <dataValidation type="list" sqref="C5"><formula1>INDIRECT(A1)</formula1></dataValidation>
+        </dataValidations></worksheet>"#;
+        let lists = r#"<worksheet><sheetData>
+            <row r="1"><c r="A1" t="inlineStr"><is><t>S</t></is></c><c r="B1" t="inlineStr"><is><t>local</t></is></c></row>
+            <row r="2"><c r="A2" t="inlineStr"><is><t>M</t></is></c></row>
+            <row r="4"><c r="A4"><v>10</v></c></row>
+            <row r="7"><c r="A7"><v>11</v></c></row>
+            <row r="x"><c r="A9"><v>1</v></c></row>
+        </sheetData></worksheet>"#;
+        let mut xlsx = Workbook::from_vec(build_xlsx_with("", &[
+            ("xl/_rels/workbook.xml.rels", rels),
+            ("xl/workbook.xml", workbook),
+            ("xl/worksheets/sheet1.xml", sheet),
+            ("xl/worksheets/sheet2.xml", lists),
+        ])).unwrap();
+
+        let data = xlsx.sheet("Sheet1").unwrap();
+        assert!(data.validations.iter().all(|v| v.values.is_none()));
+
+        let data = xlsx.sheet_with("Sheet1", WITH_VALIDATION_VALUES).unwrap();
+        let values: Vec<Option<Vec<String>>> = data.validations.into_iter().map(|v| v.values).collect();
+        let list = |items: &[&str]| Some(items.iter().map(|s| s.to_string()).collect::<Vec<String>>());
+        assert_eq!(values[0], None);
+        assert_eq!(values[1], list(&["S", "M", "10"]));
+        // local name of the sheet
+        assert_eq!(values[2], list(&["local"]));
+        assert_eq!(values[3], list(&["x", "7"]));
+        assert_eq!(values[4], None);
+        // rows after the referenced ones are not read
+        assert!(xlsx.sheet("My Lists").is_err());
+    }
+
+    #[test]
+    fn conditional_formats() {
+        let styles = concat!(
+            r#"<styleSheet><fonts><font/></fonts><fills><fill/></fills><borders><border/></borders>"#,
+            r#"<cellXfs><xf numFmtId="0" fontId="0" fillId="0" borderId="0"/></cellXfs><dxfs count="2">"#,
+            r#"<dxf><font><b/><color rgb="FF9C0006"/></font><fill><patternFill><bgColor rgb="FFFFC7CE"/></patternFill></fill></dxf>"#,
+            r#"<dxf><numFmt numFmtId="164" formatCode="0.0%"/><border><bottom style="thin"><color rgb="FF000000"/></bottom></border><alignment horizontal="center"/></dxf>"#,
+            r#"</dxfs></styleSheet>"#,
+        );
+        let sheet = r#"<worksheet><sheetData/>
+            <conditionalFormatting sqref="A1:A10"><cfRule type="cellIs" dxfId="0" priority="1" operator="greaterThan"><formula>5</formula></cfRule></conditionalFormatting>
+            <conditionalFormatting sqref="B1:B10"><cfRule type="dataBar" priority="2"><dataBar><cfvo type="min"/><cfvo type="max"/><color rgb="FF638EC6"/></dataBar></cfRule></conditionalFormatting>
+            <extLst><ext><x14:conditionalFormattings><x14:conditionalFormatting><x14:cfRule type="iconSet" priority="3"/><xm:sqref>C1</xm:sqref></x14:conditionalFormatting></x14:conditionalFormattings></ext></extLst>
+        </worksheet>"#;
+        let mut xlsx = Workbook::from_vec(build_xlsx_with("", &[("xl/styles.xml", styles), ("xl/worksheets/sheet1.xml", sheet)])).unwrap();
+
+        let data = xlsx.sheet("Sheet1").unwrap();
+        assert_eq!(data.conditional_formats.len(), 2);
+        assert_eq!(data.conditional_formats[0].rules[0].dxf_id, Some(0));
+        assert_eq!(data.conditional_formats[1].ranges[0].to.row, 9);
+        assert!(data.conditional_formats[1].rules[0].data_bar.is_some());
+
+        assert_eq!(xlsx.styles().unwrap().len(), 1);
+        let dxfs = xlsx.differential_styles(0).unwrap();
+        assert_eq!(dxfs.len(), 2);
+        assert_eq!(dxfs[0]["fontWeight"], "bold");
+        assert_eq!(dxfs[0]["color"], "rgba(156,0,6,1)");
+        assert_eq!(dxfs[0]["background"], "rgba(255,199,206,1)");
+        assert_eq!(dxfs[1]["format"], "0.0%");
+        assert_eq!(dxfs[1]["borderBottom"], "0.5px solid rgba(0,0,0,1)");
+        assert_eq!(dxfs[1]["align"], "center");
+    }
+
+    #[test]
+    fn defined_names() {
+        let workbook = r#"<workbook><sheets><sheet name="Sheet1" sheetId="1" r:id="rId1"/><sheet name="Sheet2" sheetId="2" r:id="rId1"/></sheets>
+            <definedNames>
+                <definedName name="Total">Sheet1!$B$10</definedName>
+                <definedName name="_xlnm.Print_Area" localSheetId="1" hidden="1">Sheet2!$A$1:$F$40</definedName>
+                <definedName name="Rate" localSheetId="5">0.2</definedName>
+                <definedName name="Rate">0.1</definedName>
+            </definedNames></workbook>"#;
+        let xlsx = Workbook::from_vec(build_xlsx_with("", &[("xl/workbook.xml", workbook)])).unwrap();
+        let names = xlsx.defined_names();
+        assert_eq!(names.len(), 3);
+        assert_eq!(names[0], DefinedName { name: String::from("Total"), scope: None, hidden: false, reference: String::from("Sheet1!$B$10") });
+        assert_eq!(names[1].scope.as_deref(), Some("Sheet2"));
+        assert!(names[1].hidden);
+        assert_eq!(names[1].reference, "Sheet2!$A$1:$F$40");
+        // name of unknown sheet index is skipped
+        assert_eq!((names[2].scope.as_deref(), names[2].reference.as_str()), (None, "0.1"));
+
+        assert!(Workbook::from_vec(build_xlsx("")).unwrap().defined_names().is_empty());
+    }
+}
@@ -0,0 +1,840 @@
+// writing xlsx (zip + xml) from the same structures the reader produces
+use quick_xml::escape::escape;
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use zip::write::{FileOptions, ZipWriter};
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{Cursor, Write};
+
+use crate::date;
+use crate::range::cell_name;
+use crate::rich_text::TextRun;
+use crate::sheet::{number_text, DEFAULT_CELL_HEIGHT, DEFAULT_CELL_WIDTH, HEIGHT_COEF, WIDTH_COEF};
+use crate::{Cell, CellType, CellValue, ColumnData, DataValidationErrorStyle, DataValidationSource, RowData, SheetData, Style, XlsxError};
+use crate::{get_format, PT_COEF, WITH_FORMULAS};
+
+const XML_HEADER: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#;
+const MAIN_NS: &str = "http://schemas.openxmlformats.org/spreadsheetml/2006/main";
+const REL_NS: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+const PACKAGE_REL_NS: &str = "http://schemas.openxmlformats.org/package/2006/relationships";
+const CONTENT_TYPE: &str = "application/vnd.openxmlformats-officedocument.spreadsheetml";
+// first id of custom number formats
+const CUSTOM_FORMAT_ID: u32 = 164;
+
+// `convert` output, { data, styles }, styles are null when they were not exported
+#[derive(Deserialize)]
+pub struct ExportData {
+    pub data: Vec<SheetData>,
+    #[serde(default)]
+    pub styles: Option<Vec<Style>>,
+    // set for data converted with the `formulas` option, strings starting with "=" are formulas then
+    #[serde(default)]
+    pub formulas: bool,
+}
+
+impl ExportData {
+    pub fn to_xlsx(&self) -> Result<Vec<u8>, XlsxError> {
+        let flags = if self.formulas { WITH_FORMULAS } else { 0 };
+        write_xlsx_with(&self.data, self.styles.as_deref().unwrap_or(&[]), flags)
+    }
+}
+
+// xlsx file with the sheets, `s` of cells is an index in `styles`
+pub fn write_xlsx(sheets: &[SheetData], styles: &[Style]) -> Result<Vec<u8>, XlsxError> {
+    write_xlsx_with(sheets, styles, 0)
+}
+
+// flags are WITH_FORMULAS, strings starting with "=" are written as formulas then, as they are exported with this flag
+// without it such strings are text, like "=== Totals ==="
+pub fn write_xlsx_with(sheets: &[SheetData], styles: &[Style], flags: u32) -> Result<Vec<u8>, XlsxError> {
+    if sheets.is_empty() {
+        return Err(XlsxError::InvalidData { reason: String::from("workbook has no sheets") });
+    }
+    check_sheet_names(sheets)?;
+    let mut strings = SharedStrings::default();
+    let mut parts: Vec<(String, String)> = vec!();
+    for (i, sheet) in sheets.iter().enumerate() {
+        let (xml, rels) = write_sheet(sheet, &mut strings, flags & WITH_FORMULAS > 0);
+        parts.push((format!("xl/worksheets/sheet{}.xml", i + 1), xml));
+        if let Some(rels) = rels {
+            parts.push((format!("xl/worksheets/_rels/sheet{}.xml.rels", i + 1), rels));
+        }
+    }
+    parts.push((String::from("xl/styles.xml"), write_styles(styles)));
+    parts.push((String::from("xl/sharedStrings.xml"), strings.to_xml()));
+    parts.push((String::from("xl/workbook.xml"), write_workbook(sheets)));
+
+    let mut rels = format!(r#"{}<Relationships xmlns="{}">"#, XML_HEADER, PACKAGE_REL_NS);
+    for i in 1..=sheets.len() {
+        rels += &format!(r#"<Relationship Id="rId{}" Type="{}/worksheet" Target="worksheets/sheet{}.xml"/>"#, i, REL_NS, i);
+    }
+    rels += &format!(r#"<Relationship Id="rId{}" Type="{}/styles" Target="styles.xml"/>"#, sheets.len() + 1, REL_NS);
+    rels += &format!(r#"<Relationship Id="rId{}" Type="{}/sharedStrings" Target="sharedStrings.xml"/>"#, sheets.len() + 2, REL_NS);
+    rels += "</Relationships>";
+    parts.push((String::from("xl/_rels/workbook.xml.rels"), rels));
+    parts.push((String::from("_rels/.rels"), format!(
+        r#"{}<Relationships xmlns="{}"><Relationship Id="rId1" Type="{}/officeDocument" Target="xl/workbook.xml"/></Relationships>"#,
+        XML_HEADER, PACKAGE_REL_NS, REL_NS,
+    )));
+
+    let mut types = format!(
+        r#"{}<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/>"#,
+        XML_HEADER,
+    );
+    types += &format!(r#"<Override PartName="/xl/workbook.xml" ContentType="{}.sheet.main+xml"/>"#, CONTENT_TYPE);
+    for i in 1..=sheets.len() {
+        types += &format!(r#"<Override PartName="/xl/worksheets/sheet{}.xml" ContentType="{}.worksheet+xml"/>"#, i, CONTENT_TYPE);
+    }
+    types += &format!(r#"<Override PartName="/xl/styles.xml" ContentType="{}.styles+xml"/>"#, CONTENT_TYPE);
+    types += &format!(r#"<Override PartName="/xl/sharedStrings.xml" ContentType="{}.sharedStrings+xml"/>"#, CONTENT_TYPE);
+    types += "</Types>";
+    parts.insert(0, (String::from("[Content_Types].xml"), types));
+
+    let zip_error = |e: &dyn std::fmt::Display| XlsxError::InvalidData { reason: e.to_string() };
+    let mut zip = ZipWriter::new(Cursor::new(vec!()));
+    for (name, content) in parts.iter() {
+        zip.start_file(name.as_str(), FileOptions::default()).map_err(|e| zip_error(&e))?;
+        zip.write_all(content.as_bytes()).map_err(|e| zip_error(&e))?;
+    }
+    Ok(zip.finish().map_err(|e| zip_error(&e))?.into_inner())
+}
+
+// names which Excel accepts: 1-31 chars, without []:*?/\ and unique ignoring case
+fn check_sheet_names(sheets: &[SheetData]) -> Result<(), XlsxError> {
+    let mut names = HashSet::new();
+    for sheet in sheets {
+        let name = &sheet.name;
+        let reason = if name.is_empty() || name.chars().count() > 31 {
+            "must have 1-31 characters"
+        } else if name.contains(|c| "[]:*?/\\".contains(c)) {
+            "can't contain []:*?/\\"
+        } else if !names.insert(name.to_lowercase()) {
+            "is used by another sheet"
+        } else {
+            continue;
+        };
+        return Err(XlsxError::InvalidData { reason: format!("sheet name \"{}\" {}", name, reason) });
+    }
+    Ok(())
+}
+
+fn write_workbook(sheets: &[SheetData]) -> String {
+    let mut xml = format!(r#"{}<workbook xmlns="{}" xmlns:r="{}"><bookViews><workbookView/></bookViews><sheets>"#, XML_HEADER, MAIN_NS, REL_NS);
+    for (i, sheet) in sheets.iter().enumerate() {
+        xml += &format!(r#"<sheet name="{}" sheetId="{}" r:id="rId{}"/>"#, escape(&sheet.name), i + 1, i + 1);
+    }
+    // formulas have no cached values, so they are calculated when the file is opened
+    xml += r#"</sheets><calcPr calcId="0" fullCalcOnLoad="1"/></workbook>"#;
+    xml
+}
+
+#[derive(Default)]
+struct SharedStrings {
+    items: Vec<String>,
+    // plain strings are written once, rich ones are always added
+    index: HashMap<String, usize>,
+}
+
+impl SharedStrings {
+    fn add(&mut self, text: &str) -> usize {
+        if let Some(index) = self.index.get(text) {
+            return *index;
+        }
+        self.items.push(format!("<si>{}</si>", text_element(text)));
+        self.index.insert(text.to_string(), self.items.len() - 1);
+        self.items.len() - 1
+    }
+    fn add_rich(&mut self, runs: &[TextRun]) -> usize {
+        let mut xml = String::from("<si>");
+        for run in runs {
+            xml += "<r>";
+            if !run.style.is_empty() {
+                xml += &format!("<rPr>{}</rPr>", font_properties(&run.style, "rFont"));
+            }
+            xml += &text_element(&run.text);
+            xml += "</r>";
+        }
+        xml += "</si>";
+        self.items.push(xml);
+        self.items.len() - 1
+    }
+    fn to_xml(&self) -> String {
+        format!(r#"{}<sst xmlns="{}" count="{}" uniqueCount="{}">{}</sst>"#, XML_HEADER, MAIN_NS, self.items.len(), self.items.len(), self.items.concat())
+    }
+}
+
+// <t>, spaces at the ends are kept only with xml:space
+fn text_element(text: &str) -> String {
+    if text.starts_with(char::is_whitespace) || text.ends_with(char::is_whitespace) {
+        format!(r#"<t xml:space="preserve">{}</t>"#, escape(text))
+    } else {
+        format!("<t>{}</t>", escape(text))
+    }
+}
+
+// worksheet xml and its relationships, which are hyperlink targets
+fn write_sheet(sheet: &SheetData, strings: &mut SharedStrings, formulas: bool) -> (String, Option<String>) {
+    // cells by rows, from `sparse_cells` or `cells`
+    let mut cells: BTreeMap<u32, Vec<(u32, &Cell)>> = BTreeMap::new();
+    match &sheet.sparse_cells {
+        Some(sparse) => {
+            for cell in sparse.iter() {
+                cells.entry(cell.r).or_default().push((cell.c, &cell.cell));
+            }
+            for row in cells.values_mut() {
+                row.sort_by_key(|(c, _)| *c);
+            }
+        },
+        None => {
+            for (r, row) in sheet.cells.iter().enumerate() {
+                let row: Vec<(u32, &Cell)> = row.iter().enumerate()
+                    .filter_map(|(c, cell)| cell.as_ref().map(|cell| (c as u32, cell)))
+                    .collect();
+                if !row.is_empty() {
+                    cells.insert(r as u32, row);
+                }
+            }
+        },
+    }
+
+    // columns and rows by their position, sparse sheets list only some of them with `index`
+    let sheet_cols: Vec<(u32, &ColumnData)> = sheet.cols.iter().enumerate()
+        .map(|(i, col)| (col.index.unwrap_or(i as u32), col))
+        .collect();
+    let sheet_rows: HashMap<u32, &RowData> = sheet.rows.iter().enumerate()
+        .map(|(i, row)| (row.index.unwrap_or(i as u32), row))
+        .collect();
+
+    let cols_count = cells.values().filter_map(|row| row.last().map(|(c, _)| c + 1)).max().unwrap_or(0)
+        .max(sheet_cols.iter().map(|(i, _)| i + 1).max().unwrap_or(0));
+    let rows_count = cells.keys().next_back().map(|r| r + 1).unwrap_or(0)
+        .max(sheet_rows.keys().map(|i| i + 1).max().unwrap_or(0));
+
+    let mut xml = format!(r#"{}<worksheet xmlns="{}" xmlns:r="{}">"#, XML_HEADER, MAIN_NS, REL_NS);
+    if cols_count > 0 && rows_count > 0 {
+        xml += &format!(r#"<dimension ref="A1:{}"/>"#, cell_name(cols_count - 1, rows_count - 1));
+    }
+
+    let frozen_cols = sheet.frozen_cols.unwrap_or(0);
+    let frozen_rows = sheet.frozen_rows.unwrap_or(0);
+    if frozen_cols > 0 || frozen_rows > 0 {
+        let pane = match (frozen_cols > 0, frozen_rows > 0) {
+            (true, true) => "bottomRight",
+            (true, false) => "topRight",
+            _ => "bottomLeft",
+        };
+        xml += r#"<sheetViews><sheetView workbookViewId="0"><pane"#;
+        if frozen_cols > 0 {
+            xml += &format!(r#" xSplit="{}""#, frozen_cols);
+        }
+        if frozen_rows > 0 {
+            xml += &format!(r#" ySplit="{}""#, frozen_rows);
+        }
+        xml += &format!(r#" topLeftCell="{}" activePane="{}" state="frozen"/></sheetView></sheetViews>"#, cell_name(frozen_cols, frozen_rows), pane);
+    } else {
+        xml += r#"<sheetViews><sheetView workbookViewId="0"/></sheetViews>"#;
+    }
+    xml += &format!(r#"<sheetFormatPr defaultColWidth="{}" defaultRowHeight="{}"/>"#, DEFAULT_CELL_WIDTH, DEFAULT_CELL_HEIGHT);
+
+    // columns with the same size are written as one <col>
+    let default_width = DEFAULT_CELL_WIDTH * WIDTH_COEF;
+    let mut cols = String::new();
+    let mut i = 0;
+    while i < sheet_cols.len() {
+        let (first, col) = sheet_cols[i];
+        let mut last = i;
+        while last + 1 < sheet_cols.len() && sheet_cols[last + 1].0 == sheet_cols[last].0 + 1
+            && sheet_cols[last + 1].1.width == col.width && sheet_cols[last + 1].1.hidden == col.hidden {
+            last += 1;
+        }
+        if col.width != default_width || col.hidden.is_some() {
+            cols += &format!(r#"<col min="{}" max="{}" width="{}" customWidth="1"{}/>"#, first + 1, sheet_cols[last].0 + 1, col.width / WIDTH_COEF, hidden_attr(col.hidden));
+        }
+        i = last + 1;
+    }
+    if !cols.is_empty() {
+        xml += &format!("<cols>{}</cols>", cols);
+    }
+
+    let default_height = DEFAULT_CELL_HEIGHT / HEIGHT_COEF;
+    let mut links: Vec<(String, String)> = vec!();
+    xml += "<sheetData>";
+    for r in 0..rows_count {
+        let row_cells = cells.get(&r);
+        let row = sheet_rows.get(&r);
+        let height = row.map(|row| row.height).unwrap_or(default_height);
+        let hidden = row.and_then(|row| row.hidden);
+        if row_cells.is_none() && height == default_height && hidden.is_none() {
+            continue;
+        }
+        xml += &format!(r#"<row r="{}""#, r + 1);
+        if height != default_height {
+            xml += &format!(r#" ht="{}" customHeight="1""#, height * HEIGHT_COEF);
+        }
+        xml += &hidden_attr(hidden);
+        xml += ">";
+        for (c, cell) in row_cells.into_iter().flatten() {
+            let name = cell_name(*c, r);
+            if let Some(link) = &cell.hyperlink {
+                links.push((name.clone(), link.clone()));
+            }
+            xml += &write_cell(&name, cell, strings, formulas);
+        }
+        xml += "</row>";
+    }
+    xml += "</sheetData>";
+
+    if !sheet.merged.is_empty() {
+        xml += &format!(r#"<mergeCells count="{}">"#, sheet.merged.len());
+        for merged in sheet.merged.iter() {
+            let from = cell_name(merged.from.column, merged.from.row);
+            let to = cell_name(merged.to.column, merged.to.row);
+            xml += &format!(r#"<mergeCell ref="{}:{}"/>"#, from, to);
+        }
+        xml += "</mergeCells>";
+    }
+
+    if !sheet.validations.is_empty() {
+        xml += &format!(r#"<dataValidations count="{}">"#, sheet.validations.len());
+        for validation in sheet.validations.iter() {
+            // source of lists is written instead of the formula, so it can be edited in json
+            let formula1 = match &validation.source {
+                Some(DataValidationSource::List(values)) => {
+                    let values: Vec<_> = values.iter().map(|v| v.replace('"', "\"\"")).collect();
+                    Some(format!("\"{}\"", values.join(",")))
+                },
+                Some(DataValidationSource::RangeReference(reference)) => Some(reference.clone()),
+                None => validation.formula1.clone(),
+            };
+            let mut attrs = format!(r#" type="{}""#, validation.kind.to_xlsx());
+            if let Some(operator) = validation.operator {
+                attrs += &format!(r#" operator="{}""#, operator.to_xlsx());
+            }
+            if validation.error_style != DataValidationErrorStyle::Stop {
+                attrs += &format!(r#" errorStyle="{}""#, validation.error_style.to_xlsx());
+            }
+            let flags = [
+                ("allowBlank", validation.allow_blank),
+                ("showDropDown", validation.hide_drop_down),
+                ("showInputMessage", validation.show_input_message),
+                ("showErrorMessage", validation.show_error_message),
+            ];
+            for (name, _) in flags.iter().filter(|(_, value)| *value) {
+                attrs += &format!(r#" {}="1""#, name);
+            }
+            let messages = [
+                ("errorTitle", &validation.error_title),
+                ("error", &validation.error),
+                ("promptTitle", &validation.prompt_title),
+                ("prompt", &validation.prompt),
+            ];
+            for (name, value) in messages.iter() {
+                if let Some(value) = value {
+                    attrs += &format!(r#" {}="{}""#, name, escape(value));
+                }
+            }
+            // numeric ranges are used when json has no range string
+            let sqref = if validation.range.is_empty() {
+                validation.ranges.iter()
+                    .map(|r| format!("{}:{}", cell_name(r.from.column, r.from.row), cell_name(r.to.column, r.to.row)))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            } else {
+                validation.range.clone()
+            };
+            xml += &format!(r#"<dataValidation{} sqref="{}">"#, attrs, escape(&sqref));
+            for (name, formula) in [("formula1", formula1.as_ref()), ("formula2", validation.formula2.as_ref())] {
+                if let Some(formula) = formula {
+                    xml += &format!("<{}>{}</{}>", name, escape(formula), name);
+                }
+            }
+            xml += "</dataValidation>";
+        }
+        xml += "</dataValidations>";
+    }
+
+    // links to places of the workbook start with "#", others are external targets
+    let mut rels = vec!();
+    if !links.is_empty() {
+        xml += "<hyperlinks>";
+        for (cell, link) in links {
+            match link.strip_prefix('#') {
+                Some(location) => xml += &format!(r#"<hyperlink ref="{}" location="{}"/>"#, cell, escape(location)),
+                None => {
+                    rels.push(link);
+                    xml += &format!(r#"<hyperlink ref="{}" r:id="rId{}"/>"#, cell, rels.len());
+                },
+            }
+        }
+        xml += "</hyperlinks>";
+    }
+    xml += "</worksheet>";
+
+    if rels.is_empty() {
+        return (xml, None);
+    }
+    let mut rels_xml = format!(r#"{}<Relationships xmlns="{}">"#, XML_HEADER, PACKAGE_REL_NS);
+    for (i, target) in rels.iter().enumerate() {
+        rels_xml += &format!(r#"<Relationship Id="rId{}" Type="{}/hyperlink" Target="{}" TargetMode="External"/>"#, i + 1, REL_NS, escape(target));
+    }
+    rels_xml += "</Relationships>";
+    (xml, Some(rels_xml))
+}
+
+fn hidden_attr(hidden: Option<bool>) -> String {
+    match hidden {
+        Some(hidden) => format!(r#" hidden="{}""#, if hidden { 1 } else { 0 }),
+        None => String::new(),
+    }
+}
+
+// <c>, type of string values is taken from `t`, as values are strings without WITH_TYPED_VALUES flag
+fn write_cell(name: &str, cell: &Cell, strings: &mut SharedStrings, formulas: bool) -> String {
+    let style = if cell.s > 0 { format!(r#" s="{}""#, cell.s) } else { String::new() };
+    let value = match &cell.v {
+        None => return format!(r#"<c r="{}"{}/>"#, name, style),
+        Some(value) => value,
+    };
+    let (t, content) = match value {
+        CellValue::Bool(b) => ("b", format!("<v>{}</v>", if *b { 1 } else { 0 })),
+        CellValue::Number(n) => ("n", format!("<v>{}</v>", number_text(*n))),
+        // formulas have no cached value, but keep the type of their result
+        CellValue::String(text) if formulas && text.len() > 1 && text.starts_with('=') => {
+            let t = match cell.t {
+                Some(CellType::String) => "str",
+                Some(CellType::Boolean) => "b",
+                Some(CellType::Error) => "e",
+                _ => "n",
+            };
+            (t, format!("<f>{}</f>", escape(&text[1..])))
+        },
+        CellValue::String(text) => {
+            let typed = match cell.t {
+                Some(CellType::Number) => text.trim().parse::<f64>().ok().filter(|n| n.is_finite()).map(|_| ("n", text.trim().to_string())),
+                Some(CellType::Date) => date::iso_to_serial(text).map(|n| ("n", number_text(n))),
+                Some(CellType::Boolean) => match text.trim() {
+                    "1" | "true" | "TRUE" => Some(("b", String::from("1"))),
+                    "0" | "false" | "FALSE" => Some(("b", String::from("0"))),
+                    _ => None,
+                },
+                Some(CellType::Error) => Some(("e", escape(text).into_owned())),
+                _ => None,
+            };
+            match typed {
+                Some((t, value)) => (t, format!("<v>{}</v>", value)),
+                None => {
+                    let index = match &cell.rich {
+                        Some(runs) if !runs.is_empty() => strings.add_rich(runs),
+                        _ => strings.add(text),
+                    };
+                    ("s", format!("<v>{}</v>", index))
+                },
+            }
+        },
+    };
+    let t = if t == "n" { String::new() } else { format!(r#" t="{}""#, t) };
+    format!(r#"<c r="{}"{}{}>{}</c>"#, name, style, t, content)
+}
+
+// styles.xml, each style is a cell format (xf) with its own font, fill and border, identical parts are shared
+fn write_styles(styles: &[Style]) -> String {
+    let default_style = Style::new();
+    let styles: Vec<&Style> = if styles.is_empty() { vec!(&default_style) } else { styles.iter().collect() };
+
+    let mut formats: Vec<(u32, String)> = vec!();
+    let mut fonts = Unique::default();
+    let mut fills = Unique::default();
+    fills.add(String::from(r#"<fill><patternFill patternType="none"/></fill>"#));
+    fills.add(String::from(r#"<fill><patternFill patternType="gray125"/></fill>"#));
+    let mut borders = Unique::default();
+    borders.add(String::from("<border><left/><right/><top/><bottom/><diagonal/></border>"));
+
+    let mut xfs = String::new();
+    for style in styles.iter() {
+        let format = match get_str(style, "format") {
+            Some(code) => match (0..50).find(|id| get_format(&id.to_string()).as_deref() == Some(code)) {
+                Some(id) => id,
+                None => match formats.iter().find(|(_, c)| c == code) {
+                    Some((id, _)) => *id,
+                    None => {
+                        let id = CUSTOM_FORMAT_ID + formats.len() as u32;
+                        formats.push((id, code.to_string()));
+                        id
+                    },
+                },
+            },
+            None => 0,
+        };
+        let font = fonts.add(format!("<font>{}</font>", font_properties(style, "name")));
+        let fill = fill_xml(style).map(|xml| fills.add(xml)).unwrap_or(0);
+        let border = border_xml(style).map(|xml| borders.add(xml)).unwrap_or(0);
+
+        xfs += &format!(r#"<xf numFmtId="{}" fontId="{}" fillId="{}" borderId="{}" xfId="0""#, format, font, fill, border);
+        if format > 0 {
+            xfs += r#" applyNumberFormat="1""#;
+        }
+        let alignment = alignment_xml(style);
+        if alignment.is_empty() {
+            xfs += "/>";
+        } else {
+            xfs += &format!(r#" applyAlignment="1"><alignment{}/></xf>"#, alignment);
+        }
+    }
+
+    let mut xml = format!(r#"{}<styleSheet xmlns="{}">"#, XML_HEADER, MAIN_NS);
+    if !formats.is_empty() {
+        xml += &format!(r#"<numFmts count="{}">"#, formats.len());
+        for (id, code) in formats.iter() {
+            xml += &format!(r#"<numFmt numFmtId="{}" formatCode="{}"/>"#, id, escape(code));
+        }
+        xml += "</numFmts>";
+    }
+    xml += &fonts.to_xml("fonts");
+    xml += &fills.to_xml("fills");
+    xml += &borders.to_xml("borders");
+    xml += r#"<cellStyleXfs count="1"><xf numFmtId="0" fontId="0" fillId="0" borderId="0"/></cellStyleXfs>"#;
+    xml += &format!(r#"<cellXfs count="{}">{}</cellXfs>"#, styles.len(), xfs);
+    xml += r#"<cellStyles count="1"><cellStyle name="Normal" xfId="0" builtinId="0"/></cellStyles>"#;
+    xml += "</styleSheet>";
+    xml
+}
+
+// list of xml elements without duplicates
+#[derive(Default)]
+struct Unique {
+    items: Vec<String>,
+    index: HashMap<String, usize>,
+}
+
+impl Unique {
+    fn add(&mut self, xml: String) -> usize {
+        if let Some(index) = self.index.get(&xml) {
+            return *index;
+        }
+        self.items.push(xml.clone());
+        self.index.insert(xml, self.items.len() - 1);
+        self.items.len() - 1
+    }
+    fn to_xml(&self, tag: &str) -> String {
+        format!(r#"<{} count="{}">{}</{}>"#, tag, self.items.len(), self.items.concat(), tag)
+    }
+}
+
+fn get_str<'a>(style: &'a Style, key: &str) -> Option<&'a str> {
+    style.get(key).and_then(|v| v.as_str())
+}
+
+// content of <font> or <rPr>, elements are in the order of the schema
+fn font_properties(style: &Style, name_tag: &str) -> String {
+    let mut xml = String::new();
+    if get_str(style, "fontWeight") == Some("bold") {
+        xml += "<b/>";
+    }
+    if get_str(style, "fontStyle") == Some("italic") {
+        xml += "<i/>";
+    }
+    let decoration = get_str(style, "textDecoration").unwrap_or("");
+    if decoration.contains("line-through") {
+        xml += "<strike/>";
+    }
+    if decoration.contains("underline") {
+        xml += "<u/>";
+    }
+    match get_str(style, "verticalAlign") {
+        Some("super") => xml += r#"<vertAlign val="superscript"/>"#,
+        Some("sub") => xml += r#"<vertAlign val="subscript"/>"#,
+        _ => (),
+    }
+    let size = get_str(style, "fontSize").and_then(|size| size.trim_end_matches("px").parse::<f32>().ok());
+    if let Some(size) = size {
+        xml += &format!(r#"<sz val="{}"/>"#, round(size * PT_COEF));
+    }
+    if let Some(color) = get_str(style, "color").and_then(argb) {
+        xml += &format!(r#"<color rgb="{}"/>"#, color);
+    }
+    if let Some(family) = get_str(style, "fontFamily") {
+        xml += &format!(r#"<{} val="{}"/>"#, name_tag, escape(family));
+    }
+    xml
+}
+
+// <fill> of "background" color or "pattern", gradients are not written
+fn fill_xml(style: &Style) -> Option<String> {
+    if let Some(pattern) = style.get("pattern").filter(|p| p.is_object()) {
+        let kind = pattern.get("type").and_then(|t| t.as_str())?;
+        let mut xml = format!(r#"<fill><patternFill patternType="{}">"#, escape(kind));
+        if let Some(color) = pattern.get("fgColor").and_then(|c| c.as_str()).and_then(argb) {
+            xml += &format!(r#"<fgColor rgb="{}"/>"#, color);
+        }
+        if let Some(color) = pattern.get("bgColor").and_then(|c| c.as_str()).and_then(argb) {
+            xml += &format!(r#"<bgColor rgb="{}"/>"#, color);
+        }
+        xml += "</patternFill></fill>";
+        return Some(xml);
+    }
+    let color = get_str(style, "background").and_then(argb)?;
+    Some(format!(r#"<fill><patternFill patternType="solid"><fgColor rgb="{}"/><bgColor indexed="64"/></patternFill></fill>"#, color))
+}
+
+// <border>, xlsx styles are taken from `borders` details when they are exported, or from css otherwise
+fn border_xml(style: &Style) -> Option<String> {
+    let details = style.get("borders");
+    let side = |name: &str, key: &str| -> Option<(String, Option<String>)> {
+        if let Some(info) = details.and_then(|d| d.get(name)) {
+            let xlsx_style = info.get("xlsxStyle").and_then(|s| s.as_str())?;
+            let color = info.get("color").and_then(|c| c.as_str()).and_then(border_color);
+            return Some((xlsx_style.to_string(), color));
+        }
+        css_border(get_str(style, key)?)
+    };
+    let element = |tag: &str, side: &Option<(String, Option<String>)>| match side {
+        Some((line, Some(color))) => format!(r#"<{} style="{}"><color rgb="{}"/></{}>"#, tag, escape(line), color, tag),
+        Some((line, None)) => format!(r#"<{} style="{}"/>"#, tag, escape(line)),
+        None => format!("<{}/>", tag),
+    };
+
+    let left = side("left", "borderLeft");
+    let right = side("right", "borderRight");
+    let top = side("top", "borderTop");
+    let bottom = side("bottom", "borderBottom");
+    let up = side("diagonalUp", "borderDiagonalUp");
+    let down = side("diagonalDown", "borderDiagonalDown");
+    if [&left, &right, &top, &bottom, &up, &down].iter().all(|side| side.is_none()) {
+        return None;
+    }
+
+    let mut xml = String::from("<border");
+    if up.is_some() {
+        xml += r#" diagonalUp="1""#;
+    }
+    if down.is_some() {
+        xml += r#" diagonalDown="1""#;
+    }
+    xml += ">";
+    xml += &element("left", &left);
+    xml += &element("right", &right);
+    xml += &element("top", &top);
+    xml += &element("bottom", &bottom);
+    // xlsx has a single diagonal line style for both directions
+    xml += &element("diagonal", if up.is_some() { &up } else { &down });
+    xml += "</border>";
+    Some(xml)
+}
+
+// "0.5px solid rgba(0,0,0,1)" to xlsx style and color, see the table in border.rs
+fn css_border(css: &str) -> Option<(String, Option<String>)> {
+    let mut parts = css.splitn(3, ' ');
+    let width = parts.next()?;
+    let line = parts.next()?;
+    let color = parts.next().and_then(border_color);
+    let thin = width == "0.5px";
+    let style = match line {
+        "solid" if thin => "thin",
+        "solid" if width == "2px" => "thick",
+        "solid" => "medium",
+        "double" => "double",
+        "dotted" if thin => "dashDotDot",
+        "dotted" => "dotted",
+        "dashed" if thin => "dashed",
+        "dashed" => "mediumDashed",
+        _ => return None,
+    };
+    Some((style.to_string(), color))
+}
+
+// borders without color are black, as "#000000" is the default of the reader, the color is not written for it
+fn border_color(color: &str) -> Option<String> {
+    if color == "#000000" {
+        return None;
+    }
+    argb(color)
+}
+
+fn alignment_xml(style: &Style) -> String {
+    let mut xml = String::new();
+    if let Some(align) = get_str(style, "align") {
+        xml += &format!(r#" horizontal="{}""#, escape(align));
+    }
+    // super and sub are vertical positions of rich text runs, not of the cell
+    if let Some(vertical) = get_str(style, "verticalAlign").filter(|v| *v != "super" && *v != "sub") {
+        xml += &format!(r#" vertical="{}""#, escape(vertical));
+    }
+    if style.get("wrapText") == Some(&JsonValue::Bool(true)) {
+        xml += r#" wrapText="1""#;
+    }
+    xml
+}
+
+// "rgba(255,0,0,1)", "#FF0000" or "#F00" to "FFFF0000"
+fn argb(color: &str) -> Option<String> {
+    let color = color.trim();
+    if let Some(hex) = color.strip_prefix('#') {
+        let hex: String = match hex.len() {
+            3 => hex.chars().flat_map(|c| [c, c]).collect(),
+            6 => hex.to_string(),
+            _ => return None,
+        };
+        u32::from_str_radix(&hex, 16).ok()?;
+        return Some(format!("FF{}", hex.to_uppercase()));
+    }
+    let inner = color.strip_prefix("rgba(").or_else(|| color.strip_prefix("rgb("))?.strip_suffix(')')?;
+    let parts: Vec<&str> = inner.split(',').map(|p| p.trim()).collect();
+    if parts.len() < 3 {
+        return None;
+    }
+    let channel = |i: usize| parts[i].parse::<u8>().ok();
+    let alpha = match parts.get(3) {
+        Some(a) => (a.parse::<f32>().ok()?.clamp(0.0, 1.0) * 255.0).round() as u8,
+        None => 255,
+    };
+    Some(format!("{:02X}{:02X}{:02X}{:02X}", alpha, channel(0)?, channel(1)?, channel(2)?))
+}
+
+fn round(value: f32) -> f32 {
+    (value * 100.0).round() / 100.0
+}
+
+#[test]
+fn test_colors() {
+    assert_eq!(argb("rgba(255,0,16,1)").as_deref(), Some("FFFF0010"));
+    assert_eq!(argb("#00ff00").as_deref(), Some("FF00FF00"));
+    assert_eq!(argb("#f00").as_deref(), Some("FFFF0000"));
+    assert_eq!(argb("linear-gradient(90deg, #FFFFFF 0%)"), None);
+    assert_eq!(css_border("1px dashed rgba(0,0,0,1)"), Some((String::from("mediumDashed"), Some(String::from("FF000000")))));
+    assert_eq!(css_border("0.5px solid #000000"), Some((String::from("thin"), None)));
+}
+
+#[test]
+fn test_xlsx_round_trip() {
+    use crate::{Workbook, WITH_BORDER_DETAILS, WITH_RICH_TEXT};
+
+    let files = ["file_example_XLSX_100.xlsx", "file_example_styles.xlsx", "file_example_borders.xlsx", "file_example_merged.xlsx",
+        "file_example_links.xlsx", "file_example_frozen.xlsx", "file_example_data_validation.xlsx", "formats.xlsx"];
+    for file in files.iter() {
+        let mut workbook = Workbook::open(&std::fs::read(format!("./example/{}", file)).unwrap()).unwrap();
+        let sheets: Vec<SheetData> = workbook.sheet_names().iter()
+            .map(|name| workbook.sheet_with(name, WITH_FORMULAS | WITH_RICH_TEXT).unwrap())
+            .collect();
+        let styles = workbook.styles_with(WITH_BORDER_DETAILS).unwrap();
+
+        let mut copy = Workbook::open(&write_xlsx_with(&sheets, &styles, WITH_FORMULAS).unwrap()).unwrap();
+        assert_eq!(copy.sheet_names(), workbook.sheet_names(), "{}", file);
+        for sheet in sheets.iter() {
+            let data = copy.sheet_with(&sheet.name, WITH_FORMULAS | WITH_RICH_TEXT).unwrap();
+            assert_eq!(serde_json::to_value(&data).unwrap(), serde_json::to_value(sheet).unwrap(), "{} {}", file, sheet.name);
+        }
+        assert_eq!(copy.styles_with(WITH_BORDER_DETAILS).unwrap(), styles, "{}", file);
+    }
+}
+
+#[test]
+fn test_export_json() {
+    use crate::{Workbook, WITH_RICH_TEXT, WITH_TYPED_VALUES};
+
+    let json = r##"{
+        "data": [{
+            "name": "Sheet 1",
+            "cols": [{ "width": 170 }, { "width": 85, "index": 4 }],
+            "rows": [{ "height": 40, "hidden": true }, { "height": 30, "index": 2 }],
+            "cells": [],
+            "sparse_cells": [
+                { "r": 0, "c": 0, "v": "link", "s": 1, "hyperlink": "https://dhtmlx.com" },
+                { "r": 0, "c": 1, "v": 12.5, "s": 0, "t": "number" },
+                { "r": 1, "c": 0, "v": "2024-01-31", "s": 2, "t": "date" },
+                { "r": 1, "c": 1, "v": true, "s": 0 },
+                { "r": 2, "c": 0, "v": "bold text", "s": 0, "rich": [{ "text": "bold", "style": { "fontWeight": "bold" } }, { "text": " text", "style": {} }] },
+                { "r": 2, "c": 4, "v": "far", "s": 0 }
+            ],
+            "merged": [{ "from": { "column": 0, "row": 3 }, "to": { "column": 1, "row": 3 } }],
+            "validations": [
+                { "range": "B2:B5", "source": { "type": "List", "value": ["yes", "no"] } },
+                { "ranges": [{ "from": { "column": 2, "row": 0 }, "to": { "column": 2, "row": 9 } }], "source": { "type": "List", "value": ["say \"hi\"", "bye"] } }
+            ],
+            "frozen_rows": 1
+        }],
+        "styles": [
+            {},
+            { "color": "rgba(255,0,0,1)", "background": "#FFFF00", "borderLeft": "2px solid rgba(0,0,255,1)", "align": "center" },
+            { "format": "yyyy-mm-dd" }
+        ]
+    }"##;
+    let export: crate::ExportData = serde_json::from_str(json).unwrap();
+    let mut workbook = Workbook::open(&export.to_xlsx().unwrap()).unwrap();
+    assert_eq!(workbook.sheet_names(), vec![String::from("Sheet 1")]);
+
+    let data = workbook.sheet_with("Sheet 1", WITH_TYPED_VALUES | WITH_RICH_TEXT | crate::WITH_DATES).unwrap();
+    let cell = |row: usize, col: usize| data.cells[row][col].as_ref().unwrap();
+    assert_eq!(cell(0, 0).hyperlink.as_deref(), Some("https://dhtmlx.com"));
+    assert_eq!(cell(0, 1).v, Some(CellValue::Number(12.5)));
+    assert_eq!(cell(1, 0).v, Some(CellValue::String(String::from("2024-01-31"))));
+    assert_eq!(cell(1, 0).t, Some(CellType::Date));
+    assert_eq!(cell(1, 1).v, Some(CellValue::Bool(true)));
+    assert_eq!(cell(2, 0).rich.as_ref().unwrap()[0].style["fontWeight"], "bold");
+    assert_eq!(data.cols[0].width, 170.0);
+    assert_eq!((data.rows[0].height, data.rows[0].hidden), (40.0, Some(true)));
+    // columns and rows placed by their index
+    assert_eq!((data.cols.len(), data.cols[4].width), (5, 85.0));
+    assert_eq!(data.cols[3].width, data.cols[1].width);
+    assert_eq!(data.rows.iter().map(|row| row.height == 30.0).collect::<Vec<bool>>(), vec![false, false, true]);
+    assert_eq!(data.merged.len(), 1);
+    assert_eq!(data.validations[0].range, "B2:B5");
+    assert_eq!(data.validations[1].range, "C1:C10");
+    assert!(matches!(data.validations[1].source, Some(crate::DataValidationSource::List(ref list)) if list[0] == "say \"hi\""));
+    assert_eq!(data.frozen_rows, Some(1));
+
+    let styles = workbook.styles().unwrap();
+    assert_eq!(styles[1]["color"], "rgba(255,0,0,1)");
+    assert_eq!(styles[1]["background"], "rgba(255,255,0,1)");
+    assert_eq!(styles[1]["borderLeft"], "2px solid rgba(0,0,255,1)");
+    assert_eq!(styles[1]["align"], "center");
+    assert_eq!(styles[2]["format"], "yyyy-mm-dd");
+
+    assert!(crate::write_xlsx(&[], &[]).is_err());
+}
+
+#[test]
+fn test_export_formulas() {
+    use crate::{Workbook, WITH_FORMULAS};
+
+    let json = r#"{
+        "data": [{
+            "name": "Sheet1",
+            "cols": [{ "width": 85 }, { "width": 85 }, { "width": 85 }],
+            "rows": [{ "height": 20 }],
+            "cells": [[{ "v": "=== Totals ===", "s": 0 }, { "v": "=)", "s": 0 }, { "v": "=SUM(1,2)", "s": 0 }]]
+        }]
+    }"#;
+    let mut export: crate::ExportData = serde_json::from_str(json).unwrap();
+    assert!(!export.formulas);
+    let text = |data: &SheetData, col: usize| data.cells[0][col].as_ref().and_then(|cell| cell.v.clone());
+    // strings are text by default, even with "=" at the start
+    let mut workbook = Workbook::open(&export.to_xlsx().unwrap()).unwrap();
+    let data = workbook.sheet("Sheet1").unwrap();
+    assert_eq!(text(&data, 0), Some(CellValue::String(String::from("=== Totals ==="))));
+    assert_eq!(text(&data, 1), Some(CellValue::String(String::from("=)"))));
+    assert_eq!(text(&data, 2), Some(CellValue::String(String::from("=SUM(1,2)"))));
+
+    // formulas are written without values, so they are read with WITH_FORMULAS only
+    export.formulas = true;
+    let mut workbook = Workbook::open(&export.to_xlsx().unwrap()).unwrap();
+    assert!(workbook.sheet("Sheet1").unwrap().cells.is_empty());
+    let data = workbook.sheet_with("Sheet1", WITH_FORMULAS).unwrap();
+    assert_eq!(text(&data, 2), Some(CellValue::String(String::from("=SUM(1,2)"))));
+}
+
+#[test]
+fn test_sheet_names() {
+    let sheets = |names: &[&str]| names.iter().map(|name| SheetData::new(name.to_string())).collect::<Vec<SheetData>>();
+    let error = |names: &[&str]| match write_xlsx(&sheets(names), &[]) {
+        Err(XlsxError::InvalidData { reason }) => reason,
+        _ => String::new(),
+    };
+    assert!(write_xlsx(&sheets(&["Sheet1", "Totals 'Q1'", &"ä".repeat(31)]), &[]).is_ok());
+    assert_eq!(error(&[""]), "sheet name \"\" must have 1-31 characters");
+    assert_eq!(error(&[&"a".repeat(32)]), format!("sheet name \"{}\" must have 1-31 characters", "a".repeat(32)));
+    for name in &["a[1]", "a:b", "a*", "a?", "a/b", "a\\b"] {
+        assert!(error(&[name]).ends_with("can't contain []:*?/\\"));
+    }
+    assert_eq!(error(&["Sheet1", "SHEET1"]), "sheet name \"SHEET1\" is used by another sheet");
+}
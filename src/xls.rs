@@ -0,0 +1,1024 @@
+// parsing legacy xls (BIFF8 records inside of compound file)
+use serde_json::Value as JsonValue;
+
+use std::collections::HashMap;
+
+use crate::border::{Border, BorderInfo, BorderPosition};
+use crate::cfb::CompoundFile;
+use crate::fill::Fill;
+use crate::rich_text::TextRun;
+use crate::sheet::{number_text, set_type, CellOptions, ParsedSheet, Row, HEIGHT_COEF, WIDTH_COEF};
+use crate::theme::Theme;
+use crate::{Cell, CellCoords, CellType, ColumnData, Dict, MergedCell, SheetData, SheetDescriptor, SheetKind, SheetState, SparseCell, Style, XlsxError};
+use crate::{WITH_BORDER_DETAILS, PT_COEF};
+use crate::{get_format, get_indexed_color, get_xlsx_rgb};
+
+const STREAM: &str = "Workbook";
+
+// record types
+const BOF: u16 = 0x0809;
+const EOF: u16 = 0x000A;
+const CONTINUE: u16 = 0x003C;
+const FILEPASS: u16 = 0x002F;
+const BOUNDSHEET: u16 = 0x0085;
+const SST: u16 = 0x00FC;
+const FORMAT: u16 = 0x041E;
+const FONT: u16 = 0x0031;
+const XF: u16 = 0x00E0;
+const PALETTE: u16 = 0x0092;
+const DATEMODE: u16 = 0x0022;
+const DEFCOLWIDTH: u16 = 0x0055;
+const DEFAULTROWHEIGHT: u16 = 0x0225;
+const COLINFO: u16 = 0x007D;
+const ROW: u16 = 0x0208;
+const LABELSST: u16 = 0x00FD;
+const LABEL: u16 = 0x0204;
+const NUMBER: u16 = 0x0203;
+const RK: u16 = 0x027E;
+const MULRK: u16 = 0x00BD;
+const BLANK: u16 = 0x0201;
+const MULBLANK: u16 = 0x00BE;
+const BOOLERR: u16 = 0x0205;
+const FORMULA: u16 = 0x0006;
+const STRING: u16 = 0x0207;
+const MERGEDCELLS: u16 = 0x00E5;
+const WINDOW2: u16 = 0x023E;
+const PANE: u16 = 0x0041;
+const WSBOOL: u16 = 0x0081;
+const SHEETEXT: u16 = 0x0862;
+
+const BIFF8: u16 = 0x0600;
+const AUTO_COLOR: u16 = 0x7FFF;
+
+// formatting runs of a string: index of the first char and font index
+type Runs = Vec<(u16, u16)>;
+
+// record with its CONTINUE records, strings split between them start with a new flags byte
+struct Record<'a> {
+    kind: u16,
+    offset: usize,
+    parts: Vec<&'a [u8]>,
+}
+
+struct Records<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Records<'a> {
+    fn new(data: &'a [u8], pos: usize) -> Records<'a> {
+        Records { data, pos }
+    }
+    fn header(&self, pos: usize) -> Option<(u16, usize)> {
+        let header = self.data.get(pos..pos + 4)?;
+        Some((u16::from_le_bytes([header[0], header[1]]), u16::from_le_bytes([header[2], header[3]]) as usize))
+    }
+    fn next(&mut self) -> Result<Option<Record<'a>>, XlsxError> {
+        let (kind, len) = match self.header(self.pos) {
+            Some(header) => header,
+            None => return Ok(None),
+        };
+        let offset = self.pos;
+        let mut parts = vec!();
+        let mut pos = self.pos;
+        let mut len = len;
+        loop {
+            let data = self.data.get(pos + 4..pos + 4 + len)
+                .ok_or_else(|| malformed(pos, "record is out of stream"))?;
+            parts.push(data);
+            pos += 4 + len;
+            match self.header(pos) {
+                Some((CONTINUE, next_len)) => len = next_len,
+                _ => break,
+            }
+        }
+        self.pos = pos;
+        Ok(Some(Record { kind, offset, parts }))
+    }
+}
+
+fn malformed(offset: usize, reason: &str) -> XlsxError {
+    XlsxError::MalformedRecord { part: String::from(STREAM), offset, reason: reason.to_string() }
+}
+
+// sequential reading of record data
+struct RecordReader<'a> {
+    offset: usize,
+    parts: &'a [&'a [u8]],
+    part: usize,
+    pos: usize,
+}
+
+impl<'a> RecordReader<'a> {
+    fn new(record: &'a Record) -> RecordReader<'a> {
+        RecordReader { offset: record.offset, parts: &record.parts, part: 0, pos: 0 }
+    }
+    fn error(&self) -> XlsxError {
+        malformed(self.offset, "record is too short")
+    }
+    fn u8(&mut self) -> Result<u8, XlsxError> {
+        while self.part < self.parts.len() && self.pos >= self.parts[self.part].len() {
+            self.part += 1;
+            self.pos = 0;
+        }
+        let value = *self.parts.get(self.part).and_then(|p| p.get(self.pos)).ok_or_else(|| self.error())?;
+        self.pos += 1;
+        Ok(value)
+    }
+    fn u16(&mut self) -> Result<u16, XlsxError> {
+        Ok(u16::from_le_bytes([self.u8()?, self.u8()?]))
+    }
+    fn u32(&mut self) -> Result<u32, XlsxError> {
+        Ok(u32::from_le_bytes([self.u8()?, self.u8()?, self.u8()?, self.u8()?]))
+    }
+    fn f64(&mut self) -> Result<f64, XlsxError> {
+        let mut bytes = [0u8; 8];
+        for b in bytes.iter_mut() {
+            *b = self.u8()?;
+        }
+        Ok(f64::from_le_bytes(bytes))
+    }
+    fn skip(&mut self, count: usize) -> Result<(), XlsxError> {
+        for _ in 0..count {
+            self.u8()?;
+        }
+        Ok(())
+    }
+    fn remaining(&self) -> usize {
+        self.parts.iter().skip(self.part).map(|p| p.len()).sum::<usize>() - self.pos
+    }
+    // characters are 1 byte (latin1) or 2 bytes (utf-16), it can be switched in the next CONTINUE record
+    fn chars(&mut self, count: usize, mut high_byte: bool) -> Result<Vec<u16>, XlsxError> {
+        let mut chars = Vec::with_capacity(count);
+        while chars.len() < count {
+            if self.part < self.parts.len() && self.pos >= self.parts[self.part].len() && self.part + 1 < self.parts.len() {
+                self.part += 1;
+                self.pos = 0;
+                high_byte = self.u8()? & 1 == 1;
+            }
+            if high_byte {
+                chars.push(self.u16()?);
+            } else {
+                chars.push(self.u8()? as u16);
+            }
+        }
+        Ok(chars)
+    }
+    // XLUnicodeRichExtendedString, returns text and formatting runs (char index, font index)
+    fn rich_string(&mut self, count: usize) -> Result<(Vec<u16>, Runs), XlsxError> {
+        let flags = self.u8()?;
+        let runs_count = if flags & 0x08 > 0 { self.u16()? as usize } else { 0 };
+        let ext_size = if flags & 0x04 > 0 { self.u32()? as usize } else { 0 };
+        let chars = self.chars(count, flags & 1 == 1)?;
+        let mut runs = Vec::with_capacity(runs_count);
+        for _ in 0..runs_count {
+            runs.push((self.u16()?, self.u16()?));
+        }
+        self.skip(ext_size)?;
+        Ok((chars, runs))
+    }
+    fn string(&mut self) -> Result<String, XlsxError> {
+        let count = self.u16()? as usize;
+        let (chars, _) = self.rich_string(count)?;
+        Ok(String::from_utf16_lossy(&chars))
+    }
+    fn short_string(&mut self) -> Result<String, XlsxError> {
+        let count = self.u8()? as usize;
+        let high_byte = self.u8()? & 1 == 1;
+        Ok(String::from_utf16_lossy(&self.chars(count, high_byte)?))
+    }
+}
+
+struct Font {
+    height: u16,
+    italic: bool,
+    strike: bool,
+    color: u16,
+    weight: u16,
+    script: u16,
+    underline: u8,
+    name: String,
+}
+
+struct Xf {
+    font: u16,
+    format: u16,
+    align: u8,
+    vertical: u8,
+    wrap: bool,
+    // left, right, top, bottom: line style and color index
+    borders: [(u8, u16); 4],
+    diagonal: (u8, u16),
+    diagonal_down: bool,
+    diagonal_up: bool,
+    pattern: u8,
+    fg_color: u16,
+    bg_color: u16,
+}
+
+pub(crate) struct Xls {
+    stream: Vec<u8>,
+    // descriptor and offset of the BOF record of the sheet
+    sheets: Vec<(SheetDescriptor, usize)>,
+    strings: Vec<(String, Option<Vec<TextRun>>)>,
+    fonts: Vec<Font>,
+    xfs: Vec<Xf>,
+    formats: HashMap<u16, String>,
+    date1904: bool,
+    theme: Theme,
+}
+
+impl Xls {
+    pub fn from_vec(data: Vec<u8>) -> Result<Xls, XlsxError> {
+        let file = CompoundFile::open(data)?;
+        let stream = match file.stream(STREAM) {
+            Some(stream) => stream?,
+            None => {
+                if file.stream("Book").is_some() {
+                    return Err(XlsxError::BadCompoundFile { reason: String::from("only Excel 97 and later files are supported") });
+                }
+                return Err(XlsxError::MissingPart { part: String::from(STREAM) });
+            }
+        };
+
+        let mut xls = Xls {
+            stream: vec!(),
+            sheets: vec!(),
+            strings: vec!(),
+            fonts: vec!(),
+            xfs: vec!(),
+            formats: HashMap::new(),
+            date1904: false,
+            theme: Theme::default(),
+        };
+        xls.read_globals(&stream)?;
+        xls.stream = stream;
+        xls.read_sheet_properties()?;
+        Ok(xls)
+    }
+    pub fn sheet_names(&self) -> Vec<String> {
+        self.sheets.iter().map(|(sheet, _)| sheet.name.clone()).collect()
+    }
+    pub fn sheets(&self) -> Vec<SheetDescriptor> {
+        self.sheets.iter().map(|(sheet, _)| sheet.clone()).collect()
+    }
+    // tab color and dialog flag are in the sheet substream, embedded charts are nested substreams
+    fn read_sheet_properties(&mut self) -> Result<(), XlsxError> {
+        for (sheet, offset) in self.sheets.iter_mut() {
+            let mut records = Records::new(&self.stream, *offset);
+            let mut depth = 0;
+            while let Some(record) = records.next()? {
+                let mut r = RecordReader::new(&record);
+                match record.kind {
+                    BOF => depth += 1,
+                    EOF => {
+                        depth -= 1;
+                        if depth == 0 {
+                            break;
+                        }
+                    },
+                    WSBOOL if depth == 1 && r.u16()? & 0x0010 > 0 => sheet.kind = SheetKind::Dialogsheet,
+                    SHEETEXT if depth == 1 => {
+                        r.skip(16)?;
+                        let index = (r.u32()? & 0x7F) as u16;
+                        if index != 0x7F {
+                            sheet.tab_color = Some(self.theme.indexed_color(&index.to_string()));
+                        }
+                    },
+                    _ => (),
+                }
+            }
+        }
+        Ok(())
+    }
+    // number format of each cell style
+    fn cell_formats(&self) -> Vec<String> {
+        self.xfs.iter().map(|xf| self.format(xf.format)).collect()
+    }
+    fn format(&self, id: u16) -> String {
+        match self.formats.get(&id) {
+            Some(format) => format.clone(),
+            None => get_format(&id.to_string()).unwrap_or_else(|| String::from("General")),
+        }
+    }
+    fn read_globals(&mut self, stream: &[u8]) -> Result<(), XlsxError> {
+        let mut records = Records::new(stream, 0);
+        let mut palette: Vec<String> = vec!();
+
+        match records.next()? {
+            Some(record) if record.kind == BOF => {
+                let version = RecordReader::new(&record).u16()?;
+                if version != BIFF8 {
+                    return Err(XlsxError::BadCompoundFile { reason: String::from("only Excel 97 and later files are supported") });
+                }
+            },
+            _ => return Err(malformed(0, "missing BOF record")),
+        }
+
+        while let Some(record) = records.next()? {
+            let mut r = RecordReader::new(&record);
+            match record.kind {
+                EOF => break,
+                FILEPASS => return Err(XlsxError::BadCompoundFile { reason: String::from("encrypted files are not supported") }),
+                DATEMODE => {
+                    self.date1904 = r.u16()? == 1;
+                },
+                BOUNDSHEET => {
+                    let offset = r.u32()? as usize;
+                    let state = match r.u8()? & 0x03 {
+                        1 => SheetState::Hidden,
+                        2 => SheetState::VeryHidden,
+                        _ => SheetState::Visible,
+                    };
+                    let kind = match r.u8()? {
+                        0 => Some(SheetKind::Worksheet),
+                        1 => Some(SheetKind::Macrosheet),
+                        2 => Some(SheetKind::Chartsheet),
+                        // VBA modules are not sheets
+                        _ => None,
+                    };
+                    let name = r.short_string()?;
+                    if let Some(kind) = kind {
+                        let sheet = SheetDescriptor { state, kind, ..SheetDescriptor::new(name, self.sheets.len() as u32 + 1) };
+                        self.sheets.push((sheet, offset));
+                    }
+                },
+                SST => {
+                    r.skip(4)?;
+                    let count = r.u32()? as usize;
+                    for _ in 0..count {
+                        if r.remaining() == 0 {
+                            break;
+                        }
+                        let len = r.u16()? as usize;
+                        let (chars, runs) = r.rich_string(len)?;
+                        let runs = self.text_runs(&chars, &runs);
+                        self.strings.push((String::from_utf16_lossy(&chars), runs));
+                    }
+                },
+                FORMAT => {
+                    let id = r.u16()?;
+                    let code = r.string()?;
+                    self.formats.insert(id, code);
+                },
+                FONT => {
+                    let height = r.u16()?;
+                    let flags = r.u16()?;
+                    let color = r.u16()?;
+                    let weight = r.u16()?;
+                    let script = r.u16()?;
+                    let underline = r.u8()?;
+                    r.skip(3)?;
+                    let name = r.short_string()?;
+                    self.fonts.push(Font {
+                        height,
+                        italic: flags & 0x02 > 0,
+                        strike: flags & 0x08 > 0,
+                        color,
+                        weight,
+                        script,
+                        underline,
+                        name,
+                    });
+                },
+                XF => {
+                    let font = r.u16()?;
+                    let format = r.u16()?;
+                    r.skip(2)?;
+                    let alignment = r.u8()?;
+                    r.skip(3)?;
+                    let border = r.u32()?;
+                    let extra = r.u32()?;
+                    let colors = r.u16()?;
+                    let diagonal = (border >> 30) & 0x03;
+                    self.xfs.push(Xf {
+                        font,
+                        format,
+                        align: alignment & 0x07,
+                        vertical: (alignment >> 4) & 0x07,
+                        wrap: alignment & 0x08 > 0,
+                        borders: [
+                            ((border & 0x0F) as u8, ((border >> 16) & 0x7F) as u16),
+                            (((border >> 4) & 0x0F) as u8, ((border >> 23) & 0x7F) as u16),
+                            (((border >> 8) & 0x0F) as u8, (extra & 0x7F) as u16),
+                            (((border >> 12) & 0x0F) as u8, ((extra >> 7) & 0x7F) as u16),
+                        ],
+                        diagonal: (((extra >> 21) & 0x0F) as u8, ((extra >> 14) & 0x7F) as u16),
+                        diagonal_down: diagonal & 0x01 > 0,
+                        diagonal_up: diagonal & 0x02 > 0,
+                        pattern: ((extra >> 26) & 0x3F) as u8,
+                        fg_color: colors & 0x7F,
+                        bg_color: (colors >> 7) & 0x7F,
+                    });
+                },
+                // custom colors of the palette, from index 8
+                PALETTE => {
+                    let count = r.u16()?;
+                    for _ in 0..count {
+                        let (red, green, blue) = (r.u8()?, r.u8()?, r.u8()?);
+                        r.skip(1)?;
+                        palette.push(get_xlsx_rgb(&format!("{:02X}{:02X}{:02X}", red, green, blue)).unwrap_or_default());
+                    }
+                },
+                _ => (),
+            }
+        }
+
+        if !palette.is_empty() {
+            let mut colors: Vec<String> = (0..8).map(|i| get_indexed_color(&i.to_string())).collect();
+            colors.extend(palette);
+            self.theme.set_indexed_colors(colors);
+        }
+        Ok(())
+    }
+    // fonts with index 4 and above are shifted, as the font 4 is omitted in the file
+    fn font(&self, index: u16) -> Option<&Font> {
+        let index = if index >= 4 { index - 1 } else { index };
+        self.fonts.get(index as usize)
+    }
+    fn color(&self, index: u16) -> String {
+        self.theme.indexed_color(&index.to_string())
+    }
+    fn font_style(&self, font: &Font, with_script: bool) -> Dict {
+        let mut style = Dict::new();
+        let size = font.height as f32 / 20.0;
+        style.insert(String::from("fontSize"), JsonValue::String((size / PT_COEF).to_string() + "px"));
+        style.insert(String::from("fontFamily"), JsonValue::String(font.name.clone()));
+        if font.color != AUTO_COLOR {
+            style.insert(String::from("color"), JsonValue::String(self.color(font.color)));
+        }
+        if font.weight >= 700 {
+            style.insert(String::from("fontWeight"), JsonValue::String(String::from("bold")));
+        }
+        if font.italic {
+            style.insert(String::from("fontStyle"), JsonValue::String(String::from("italic")));
+        }
+        let decoration = match (font.underline > 0, font.strike) {
+            (true, true) => Some("line-through underline"),
+            (true, false) => Some("underline"),
+            (false, true) => Some("line-through"),
+            (false, false) => None,
+        };
+        if let Some(decoration) = decoration {
+            style.insert(String::from("textDecoration"), JsonValue::String(String::from(decoration)));
+        }
+        if with_script {
+            match font.script {
+                1 => { style.insert(String::from("verticalAlign"), JsonValue::String(String::from("super"))); },
+                2 => { style.insert(String::from("verticalAlign"), JsonValue::String(String::from("sub"))); },
+                _ => (),
+            }
+        }
+        style
+    }
+    fn text_runs(&self, chars: &[u16], runs: &[(u16, u16)]) -> Option<Vec<TextRun>> {
+        text_runs(chars, runs, |index| match self.font(index) {
+            Some(font) => self.font_style(font, true),
+            None => Dict::new(),
+        })
+    }
+    pub fn styles_with(&self, flags: u32) -> Vec<Style> {
+        self.xfs.iter().map(|xf| self.style(xf, flags)).collect()
+    }
+    fn style(&self, xf: &Xf, flags: u32) -> Style {
+        let mut style = Style::new();
+        if let Some(font) = self.font(xf.font) {
+            style.extend(self.font_style(font, false));
+        }
+
+        let mut borders = vec!();
+        let positions = [BorderPosition::Left, BorderPosition::Right, BorderPosition::Top, BorderPosition::Bottom];
+        for (position, (line, color)) in positions.iter().zip(xf.borders.iter()) {
+            borders.push(self.border(*position, *line, *color));
+        }
+        if xf.diagonal_up {
+            borders.push(self.border(BorderPosition::DiagonalUp, xf.diagonal.0, xf.diagonal.1));
+        }
+        if xf.diagonal_down {
+            borders.push(self.border(BorderPosition::DiagonalDown, xf.diagonal.0, xf.diagonal.1));
+        }
+        let mut details: HashMap<String, BorderInfo> = HashMap::new();
+        for border in borders {
+            if flags & WITH_BORDER_DETAILS > 0 {
+                if let Some(info) = border.get_info() {
+                    details.insert(border.get_name(), info);
+                }
+            }
+            let (key, value) = border.get_computed_style();
+            if !value.is_empty() {
+                style.insert(key, JsonValue::String(value));
+            }
+        }
+        if !details.is_empty() {
+            style.insert(String::from("borders"), serde_json::to_value(details).unwrap_or_default());
+        }
+
+        let mut fill = Fill::new();
+        if let Some(pattern) = pattern_name(xf.pattern) {
+            fill.set_pattern(String::from(pattern));
+            fill.set_fg_color(self.color(xf.fg_color));
+            fill.set_bg_color(self.color(xf.bg_color));
+        }
+        style.extend(fill.get_computed_style());
+
+        style.insert(String::from("format"), JsonValue::String(self.format(xf.format)));
+        if let Some(align) = align_name(xf.align) {
+            style.insert(String::from("align"), JsonValue::String(String::from(align)));
+        }
+        if let Some(vertical) = vertical_name(xf.vertical) {
+            style.insert(String::from("verticalAlign"), JsonValue::String(String::from(vertical)));
+        }
+        if xf.wrap {
+            style.insert(String::from("wrapText"), JsonValue::Bool(true));
+        }
+        style
+    }
+    fn border(&self, position: BorderPosition, line: u8, color: u16) -> Border {
+        let mut border = Border::new(position);
+        if let Some(name) = border_name(line) {
+            border.set_style(String::from(name));
+            border.set_color(self.color(color));
+        }
+        border
+    }
+    pub fn sheet_with(&self, sheet_name: &str, flags: u32) -> Result<SheetData, XlsxError> {
+        Ok(self.read_sheet(sheet_name, flags)?.into_sheet(flags))
+    }
+    // cells of xls are not ordered, so all the sheet is read at once
+    pub fn read_sheet(&self, sheet_name: &str, flags: u32) -> Result<ParsedSheet, XlsxError> {
+        let (name, offset) = match self.sheets.iter().find(|(sheet, _)| sheet.name == sheet_name) {
+            Some((sheet, offset)) => (sheet.name.clone(), *offset),
+            None => return Err(XlsxError::UnknownSheet { name: sheet_name.to_string() }),
+        };
+        let cell_formats = self.cell_formats();
+        let options = CellOptions::new(flags, self.date1904, &cell_formats, &self.theme);
+
+        let mut sheet = ParsedSheet::new(name);
+        let cells = &mut sheet.cells;
+        // formula with string result, the string is in the next STRING record
+        let mut pending: Option<SparseCell> = None;
+        let mut frozen = false;
+
+        let mut records = Records::new(&self.stream, offset);
+        match records.next()? {
+            Some(record) if record.kind == BOF => (),
+            _ => return Err(malformed(offset, "missing BOF record of the sheet")),
+        }
+
+        while let Some(record) = records.next()? {
+            let mut r = RecordReader::new(&record);
+            if record.kind != STRING {
+                if let Some(cell) = pending.take() {
+                    cells.push(cell);
+                }
+            }
+            match record.kind {
+                EOF => break,
+                DEFCOLWIDTH => {
+                    sheet.default_col_width = r.u16()? as f32 * WIDTH_COEF;
+                },
+                DEFAULTROWHEIGHT => {
+                    r.skip(2)?;
+                    sheet.default_row_height = r.u16()? as f32 / 20.0 / HEIGHT_COEF;
+                },
+                COLINFO => {
+                    let first = r.u16()? as usize;
+                    let last = r.u16()? as usize;
+                    let width = r.u16()? as f32 / 256.0 * WIDTH_COEF;
+                    r.skip(2)?;
+                    let hidden = if r.u16()? & 0x01 > 0 { Some(true) } else { None };
+                    for i in sheet.data.cols.len()..=last.min(255) {
+                        if i >= first {
+                            sheet.data.cols.push(ColumnData { width, hidden, index: None });
+                        } else {
+                            sheet.data.cols.push(ColumnData { width: sheet.default_col_width, hidden: None, index: None });
+                        }
+                    }
+                },
+                ROW => {
+                    let index = r.u16()? as u32;
+                    r.skip(4)?;
+                    let height = r.u16()?;
+                    r.skip(4)?;
+                    let flags = r.u32()?;
+                    // the highest bit is set for rows with default height
+                    let height = if height & 0x8000 == 0 { height as f32 / 20.0 / HEIGHT_COEF } else { sheet.default_row_height };
+                    let hidden = if flags & 0x20 > 0 { Some(true) } else { None };
+                    sheet.rows.insert(index, Row { index, height, hidden, cells: vec!() });
+                },
+                NUMBER => {
+                    let (row, col, xf) = (r.u16()?, r.u16()?, r.u16()?);
+                    let value = r.f64()?;
+                    cells.push(self.cell(row, col, xf, Some((number_text(value), CellType::Number)), &options, &cell_formats));
+                },
+                RK => {
+                    let (row, col, xf) = (r.u16()?, r.u16()?, r.u16()?);
+                    let value = rk_number(r.u32()?);
+                    cells.push(self.cell(row, col, xf, Some((number_text(value), CellType::Number)), &options, &cell_formats));
+                },
+                MULRK => {
+                    let (row, first) = (r.u16()?, r.u16()?);
+                    let count = r.remaining().saturating_sub(2) / 6;
+                    for i in 0..count {
+                        let xf = r.u16()?;
+                        let value = rk_number(r.u32()?);
+                        cells.push(self.cell(row, first + i as u16, xf, Some((number_text(value), CellType::Number)), &options, &cell_formats));
+                    }
+                },
+                BLANK => {
+                    let (row, col, xf) = (r.u16()?, r.u16()?, r.u16()?);
+                    cells.push(self.cell(row, col, xf, None, &options, &cell_formats));
+                },
+                MULBLANK => {
+                    let (row, first) = (r.u16()?, r.u16()?);
+                    let count = r.remaining().saturating_sub(2) / 2;
+                    for i in 0..count {
+                        let xf = r.u16()?;
+                        cells.push(self.cell(row, first + i as u16, xf, None, &options, &cell_formats));
+                    }
+                },
+                LABELSST => {
+                    let (row, col, xf) = (r.u16()?, r.u16()?, r.u16()?);
+                    let index = r.u32()? as usize;
+                    let (text, runs) = self.strings.get(index)
+                        .ok_or_else(|| malformed(record.offset, &format!("invalid shared string index {}", index)))?;
+                    let mut cell = self.cell(row, col, xf, None, &options, &cell_formats);
+                    options.set_text(&mut cell.cell, text.clone(), runs.clone(), &cell_formats);
+                    set_type(&mut cell.cell, CellType::String);
+                    cells.push(cell);
+                },
+                LABEL => {
+                    let (row, col, xf) = (r.u16()?, r.u16()?, r.u16()?);
+                    let text = r.string()?;
+                    let mut cell = self.cell(row, col, xf, None, &options, &cell_formats);
+                    options.set_text(&mut cell.cell, text, None, &cell_formats);
+                    set_type(&mut cell.cell, CellType::String);
+                    cells.push(cell);
+                },
+                BOOLERR => {
+                    let (row, col, xf) = (r.u16()?, r.u16()?, r.u16()?);
+                    let value = r.u8()?;
+                    let value = if r.u8()? == 1 {
+                        (error_text(value), CellType::Error)
+                    } else {
+                        (value.to_string(), CellType::Boolean)
+                    };
+                    cells.push(self.cell(row, col, xf, Some(value), &options, &cell_formats));
+                },
+                // formulas are not decompiled, cached values are used
+                FORMULA => {
+                    let (row, col, xf) = (r.u16()?, r.u16()?, r.u16()?);
+                    let mut result = [0u8; 8];
+                    for b in result.iter_mut() {
+                        *b = r.u8()?;
+                    }
+                    let value = if result[6] == 0xFF && result[7] == 0xFF {
+                        match result[0] {
+                            0 => {
+                                pending = Some(self.cell(row, col, xf, None, &options, &cell_formats));
+                                continue;
+                            },
+                            1 => Some((result[2].to_string(), CellType::Boolean)),
+                            2 => Some((error_text(result[2]), CellType::Error)),
+                            _ => None,
+                        }
+                    } else {
+                        Some((number_text(f64::from_le_bytes(result)), CellType::Number))
+                    };
+                    cells.push(self.cell(row, col, xf, value, &options, &cell_formats));
+                },
+                STRING => {
+                    if let Some(mut cell) = pending.take() {
+                        let text = r.string()?;
+                        options.set_text(&mut cell.cell, text, None, &cell_formats);
+                        set_type(&mut cell.cell, CellType::String);
+                        cells.push(cell);
+                    }
+                },
+                MERGEDCELLS => {
+                    let count = r.u16()?;
+                    for _ in 0..count {
+                        let (first_row, last_row, first_col, last_col) = (r.u16()?, r.u16()?, r.u16()?, r.u16()?);
+                        sheet.data.merged.push(MergedCell {
+                            from: CellCoords { column: first_col as u32, row: first_row as u32 },
+                            to: CellCoords { column: last_col as u32, row: last_row as u32 },
+                        });
+                    }
+                },
+                WINDOW2 => {
+                    frozen = r.u16()? & 0x08 > 0;
+                },
+                PANE => {
+                    let (cols, rows) = (r.u16()?, r.u16()?);
+                    if frozen && cols > 0 {
+                        sheet.data.frozen_cols = Some(cols as u32);
+                    }
+                    if frozen && rows > 0 {
+                        sheet.data.frozen_rows = Some(rows as u32);
+                    }
+                },
+                BOF => return Err(malformed(record.offset, "unexpected BOF record inside of the sheet")),
+                _ => (),
+            }
+        }
+        if let Some(cell) = pending.take() {
+            cells.push(cell);
+        }
+        Ok(sheet)
+    }
+    fn cell(&self, row: u16, col: u16, xf: u16, value: Option<(String, CellType)>, options: &CellOptions, cell_formats: &[String]) -> SparseCell {
+        let mut cell = Cell { s: xf as u32, ..Cell::new() };
+        if let Some((value, cell_type)) = value {
+            let cell_type = options.set_value(&mut cell, value, cell_type, cell_formats);
+            set_type(&mut cell, cell_type);
+        }
+        SparseCell { r: row as u32, c: col as u32, cell }
+    }
+}
+
+// runs start at the char index and last till the next one, text before the first run uses the cell font
+pub(crate) fn text_runs(chars: &[u16], runs: &[(u16, u16)], font_style: impl Fn(u16) -> Dict) -> Option<Vec<TextRun>> {
+    if runs.is_empty() {
+        return None;
+    }
+    let mut result = vec!();
+    let mut bounds: Vec<(usize, Option<u16>)> = vec!((0, None));
+    bounds.extend(runs.iter().map(|(start, font)| ((*start as usize).min(chars.len()), Some(*font))));
+    for (i, (start, font)) in bounds.iter().enumerate() {
+        let end = bounds.get(i + 1).map(|b| b.0).unwrap_or(chars.len());
+        if end <= *start {
+            continue;
+        }
+        let style = font.map(&font_style).unwrap_or_default();
+        result.push(TextRun { text: String::from_utf16_lossy(&chars[*start..end]), style });
+    }
+    Some(result)
+}
+
+// RK is a compressed number, integer or the highest 30 bits of a float, optionally multiplied by 100
+pub(crate) fn rk_number(rk: u32) -> f64 {
+    let value = if rk & 0x02 > 0 {
+        ((rk as i32) >> 2) as f64
+    } else {
+        f64::from_bits(((rk & 0xFFFFFFFC) as u64) << 32)
+    };
+    if rk & 0x01 > 0 { value / 100.0 } else { value }
+}
+
+pub(crate) fn error_text(code: u8) -> String {
+    let text = match code {
+        0x00 => "#NULL!",
+        0x07 => "#DIV/0!",
+        0x0F => "#VALUE!",
+        0x17 => "#REF!",
+        0x1D => "#NAME?",
+        0x24 => "#NUM!",
+        0x2A => "#N/A",
+        0x2B => "#GETTING_DATA",
+        _ => "#ERROR!",
+    };
+    String::from(text)
+}
+
+pub(crate) fn border_name(line: u8) -> Option<&'static str> {
+    match line {
+        1 => Some("thin"),
+        2 => Some("medium"),
+        3 => Some("dashed"),
+        4 => Some("dotted"),
+        5 => Some("thick"),
+        6 => Some("double"),
+        7 => Some("hair"),
+        8 => Some("mediumDashed"),
+        9 => Some("dashDot"),
+        10 => Some("mediumDashDot"),
+        11 => Some("dashDotDot"),
+        12 => Some("mediumDashDotDot"),
+        13 => Some("slantDashDot"),
+        _ => None,
+    }
+}
+
+pub(crate) fn pattern_name(pattern: u8) -> Option<&'static str> {
+    match pattern {
+        1 => Some("solid"),
+        2 => Some("mediumGray"),
+        3 => Some("darkGray"),
+        4 => Some("lightGray"),
+        5 => Some("darkHorizontal"),
+        6 => Some("darkVertical"),
+        7 => Some("darkDown"),
+        8 => Some("darkUp"),
+        9 => Some("darkGrid"),
+        10 => Some("darkTrellis"),
+        11 => Some("lightHorizontal"),
+        12 => Some("lightVertical"),
+        13 => Some("lightDown"),
+        14 => Some("lightUp"),
+        15 => Some("lightGrid"),
+        16 => Some("lightTrellis"),
+        17 => Some("gray125"),
+        18 => Some("gray0625"),
+        _ => None,
+    }
+}
+
+// "general" alignment is not exported, as in xlsx where it is the default
+pub(crate) fn align_name(align: u8) -> Option<&'static str> {
+    match align {
+        1 => Some("left"),
+        2 => Some("center"),
+        3 => Some("right"),
+        4 => Some("fill"),
+        5 => Some("justify"),
+        6 => Some("centerContinuous"),
+        7 => Some("distributed"),
+        _ => None,
+    }
+}
+
+// "bottom" is the default
+pub(crate) fn vertical_name(vertical: u8) -> Option<&'static str> {
+    match vertical {
+        0 => Some("top"),
+        1 => Some("center"),
+        3 => Some("justify"),
+        4 => Some("distributed"),
+        _ => None,
+    }
+}
+
+#[test]
+fn rk_numbers() {
+    assert_eq!(rk_number((42 << 2) | 2), 42.0);
+    assert_eq!(rk_number((150 << 2) | 3), 1.5);
+    assert_eq!(rk_number(((-5i32 << 2) | 2) as u32), -5.0);
+    assert_eq!(rk_number((0.5f64.to_bits() >> 32) as u32), 0.5);
+}
+
+#[cfg(test)]
+fn record(kind: u16, data: &[u8]) -> Vec<u8> {
+    let mut result = kind.to_le_bytes().to_vec();
+    result.extend_from_slice(&(data.len() as u16).to_le_bytes());
+    result.extend_from_slice(data);
+    result
+}
+
+#[cfg(test)]
+fn cell_record(kind: u16, row: u16, col: u16, xf: u16, value: &[u8]) -> Vec<u8> {
+    let mut data = [row.to_le_bytes(), col.to_le_bytes(), xf.to_le_bytes()].concat();
+    data.extend_from_slice(value);
+    record(kind, &data)
+}
+
+// xls workbook with a single sheet, wrapped into compound file with one FAT sector
+#[cfg(test)]
+fn build_xls() -> Vec<u8> {
+    let sheet = [
+        record(0x0809, &[0x00, 0x06, 0x10, 0x00, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+        record(0x007D, &[0, 0, 1, 0, 0x00, 0x14, 0, 0, 0, 0, 0, 0]),
+        record(0x0208, &[0, 0, 0, 0, 2, 0, 0x58, 0x02, 0, 0, 0, 0, 0, 0, 0, 0]),
+        cell_record(0x00FD, 0, 0, 0, &0u32.to_le_bytes()),
+        cell_record(0x0203, 0, 1, 1, &0.25f64.to_le_bytes()),
+        cell_record(0x00FD, 1, 0, 0, &1u32.to_le_bytes()),
+        cell_record(0x027E, 2, 0, 0, &((42u32 << 2) | 2).to_le_bytes()),
+        cell_record(0x00BD, 2, 1, 0, &[&((150u32 << 2) | 3).to_le_bytes()[..], &[0, 0], &((7u32 << 2) | 2).to_le_bytes(), &[2, 0]].concat()),
+        cell_record(0x0205, 3, 0, 0, &[1, 0]),
+        cell_record(0x0006, 3, 1, 0, &[0, 0, 0, 0, 0, 0, 0xFF, 0xFF, 0, 0, 0, 0, 0, 0, 0, 0]),
+        record(0x0207, &[8, 0, 0, b'f', b'o', b'r', b'm', b'u', b'l', b'a', b'!']),
+        record(0x00E5, &[1, 0, 3, 0, 3, 0, 1, 0, 2, 0]),
+        record(0x0862, &[&[0x62, 0x08][..], &[0; 10], &0x14u32.to_le_bytes(), &10u32.to_le_bytes()].concat()),
+        record(0x000A, &[]),
+    ].concat();
+
+    let xf = |format: u16, alignment: u8, border: u32, extra: u32, colors: u16| {
+        let mut data = [0u16.to_le_bytes(), format.to_le_bytes(), [0, 0]].concat();
+        data.extend_from_slice(&[alignment, 0, 0, 0]);
+        data.extend_from_slice(&border.to_le_bytes());
+        data.extend_from_slice(&extra.to_le_bytes());
+        data.extend_from_slice(&colors.to_le_bytes());
+        record(0x00E0, &data)
+    };
+    let sst = [
+        &[2, 0, 0, 0, 2, 0, 0, 0][..],
+        &[5, 0, 0], b"hello",
+        &[9, 0, 0x08, 1, 0], b"bold text", &[5, 0, 0, 0],
+    ].concat();
+    let mut globals = [
+        record(0x0809, &[0x00, 0x06, 0x05, 0x00, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+        record(0x0031, &[0xDC, 0, 0, 0, 0xFF, 0x7F, 0xBC, 0x02, 0, 0, 0, 0, 0, 0, 5, 0, b'A', b'r', b'i', b'a', b'l']),
+        record(0x041E, &[0xA4, 0, 4, 0, 0, b'0', b'.', b'0', b'%']),
+        xf(0, 0, 0, 0, 0),
+        xf(0xA4, 0x02, 0x0008_0001, 1 << 26, 10 | (64 << 7)),
+        record(0x00FC, &sst),
+    ].concat();
+    let boundsheet_len = 4 + 8 + 6;
+    let end_len = 4;
+    let offset = (globals.len() + boundsheet_len + end_len) as u32;
+    globals.extend(record(0x0085, &[&offset.to_le_bytes()[..], &[1, 0, 6, 0], b"Sheet1"].concat()));
+    globals.extend(record(0x000A, &[]));
+
+    let mut stream = [globals, sheet].concat();
+    stream.resize(4096, 0);
+
+    let mut header = crate::cfb::SIGNATURE.to_vec();
+    header.resize(512, 0);
+    header[0x18..0x22].copy_from_slice(&[0x3E, 0, 3, 0, 0xFE, 0xFF, 9, 0, 6, 0]);
+    header[0x2C..0x30].copy_from_slice(&1u32.to_le_bytes());
+    header[0x30..0x34].copy_from_slice(&1u32.to_le_bytes());
+    header[0x38..0x3C].copy_from_slice(&4096u32.to_le_bytes());
+    header[0x3C..0x40].copy_from_slice(&0xFFFFFFFEu32.to_le_bytes());
+    header[0x44..0x48].copy_from_slice(&0xFFFFFFFEu32.to_le_bytes());
+    for i in 0..109 {
+        let sector: u32 = if i == 0 { 0 } else { 0xFFFFFFFF };
+        header[0x4C + i * 4..0x50 + i * 4].copy_from_slice(&sector.to_le_bytes());
+    }
+
+    let stream_sectors = stream.len() / 512;
+    let mut fat = vec![0xFFFFFFFDu32, 0xFFFFFFFE];
+    fat.extend((0..stream_sectors as u32).map(|i| if i + 1 < stream_sectors as u32 { i + 3 } else { 0xFFFFFFFE }));
+    fat.resize(128, 0xFFFFFFFF);
+    let fat: Vec<u8> = fat.iter().flat_map(|s| s.to_le_bytes()).collect();
+
+    let entry = |name: &str, kind: u8, start: u32, size: u32| {
+        let mut data: Vec<u8> = name.encode_utf16().flat_map(|c| c.to_le_bytes()).collect();
+        data.resize(128, 0);
+        data[64..66].copy_from_slice(&((name.len() as u16 + 1) * 2).to_le_bytes());
+        data[66] = kind;
+        data[68..80].copy_from_slice(&[0xFF; 12]);
+        data[116..120].copy_from_slice(&start.to_le_bytes());
+        data[120..124].copy_from_slice(&size.to_le_bytes());
+        data
+    };
+    let mut directory = [entry("Root Entry", 5, 0xFFFFFFFE, 0), entry("Workbook", 2, 2, stream.len() as u32)].concat();
+    directory.resize(512, 0);
+
+    [header, fat, directory, stream].concat()
+}
+
+#[test]
+fn test_xls_sheet() {
+    use crate::{CellValue, Workbook, WITH_RICH_TEXT};
+
+    let mut xls = Workbook::open(&build_xls()).unwrap();
+    assert_eq!(xls.sheet_names(), vec![String::from("Sheet1")]);
+    let sheets = xls.sheets();
+    assert_eq!((sheets[0].id, sheets[0].state, sheets[0].kind), (1, SheetState::Hidden, SheetKind::Worksheet));
+    assert_eq!(sheets[0].tab_color.as_deref(), Some("#FF0000"));
+
+    let data = xls.sheet_with("Sheet1", WITH_RICH_TEXT).unwrap();
+    let value = |row: usize, col: usize| data.cells[row][col].as_ref().and_then(|c| c.v.clone());
+    assert_eq!(data.cells.len(), 4);
+    assert_eq!(value(0, 0), Some(CellValue::String(String::from("hello"))));
+    assert_eq!(value(0, 1), Some(CellValue::String(String::from("0.25"))));
+    assert_eq!(data.cells[0][1].as_ref().unwrap().s, 1);
+    assert_eq!(value(2, 0), Some(CellValue::String(String::from("42"))));
+    assert_eq!(value(2, 1), Some(CellValue::String(String::from("1.5"))));
+    assert_eq!(value(2, 2), Some(CellValue::String(String::from("7"))));
+    assert_eq!(data.cells[3][0].as_ref().unwrap().t, Some(CellType::Boolean));
+    assert_eq!(value(3, 1), Some(CellValue::String(String::from("formula!"))));
+
+    let rich = data.cells[1][0].as_ref().unwrap().rich.as_ref().unwrap();
+    assert_eq!(rich.iter().map(|r| r.text.as_str()).collect::<Vec<&str>>(), vec!["bold ", "text"]);
+    assert_eq!(rich[1].style["fontWeight"], "bold");
+
+    assert_eq!(data.cols[0].width, 20.0 * crate::sheet::WIDTH_COEF);
+    assert_eq!(data.rows[0].height, 30.0 / crate::sheet::HEIGHT_COEF);
+    assert_eq!(data.merged.len(), 1);
+    assert_eq!((data.merged[0].from.row, data.merged[0].to.column), (3, 2));
+
+    let styles = xls.styles().unwrap();
+    assert_eq!(styles[1]["format"], "0.0%");
+    assert_eq!(styles[1]["align"], "center");
+    assert_eq!(styles[1]["fontWeight"], "bold");
+    assert_eq!(styles[1]["borderLeft"], "0.5px solid #000000");
+    assert!(styles[1].contains_key("background"));
+
+    let rows: Vec<u32> = xls.rows("Sheet1").unwrap().map(|row| row.unwrap().index).collect();
+    assert_eq!(rows, vec![0, 1, 2, 3]);
+}
+
+#[test]
+fn test_xls_errors() {
+    use crate::Workbook;
+
+    let mut data = build_xls();
+    // rename the stream, so the workbook is not found
+    data[1024 + 128] = b'X';
+    match Workbook::from_vec(data) {
+        Err(XlsxError::MissingPart { part }) => assert_eq!(part, "Workbook"),
+        _ => panic!("expected MissingPart error"),
+    }
+
+    // mini stream cutoff other than 4096
+    let mut data = build_xls();
+    data[0x38..0x3C].copy_from_slice(&0xFFFFFFFFu32.to_le_bytes());
+    assert!(matches!(Workbook::from_vec(data), Err(XlsxError::BadCompoundFile { .. })));
+
+    let mut data = crate::cfb::SIGNATURE.to_vec();
+    data.resize(100, 0);
+    match Workbook::from_vec(data) {
+        Err(XlsxError::BadCompoundFile { .. }) => (),
+        _ => panic!("expected BadCompoundFile error"),
+    }
+}
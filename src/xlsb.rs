@@ -0,0 +1,861 @@
+// parsing xlsb (zip + BIFF12 binary records)
+use quick_xml::events::Event;
+use serde_json::Value as JsonValue;
+
+use std::collections::HashMap;
+use std::io::Read;
+
+use crate::border::{Border, BorderInfo, BorderPosition};
+use crate::fill::{Fill, Gradient};
+use crate::rich_text::TextRun;
+use crate::sheet::{number_text, set_type, CellOptions, ParsedSheet, Row, HEIGHT_COEF, WIDTH_COEF};
+use crate::theme::Theme;
+use crate::workbook::{part_path, read_relationships, read_typed_relationships, rels_path, sheet_kind, TypedRelationships};
+use crate::xls::{align_name, border_name, error_text, pattern_name, rk_number, text_runs, vertical_name};
+use crate::{Cell, CellCoords, CellType, ColumnData, Dict, MergedCell, SheetData, SheetDescriptor, SheetKind, SheetState, SparseCell, Style, XlsxError};
+use crate::{WITH_BORDER_DETAILS, PT_COEF};
+use crate::{get_format, get_xlsx_rgb, xml_error, xml_reader, Archive};
+
+const MAIN_CONTENT_TYPE: &str = "application/vnd.ms-excel.sheet.binary.macroEnabled.main";
+
+// record types
+const ROW_HDR: u32 = 0;
+const CELL_BLANK: u32 = 1;
+const CELL_RK: u32 = 2;
+const CELL_ERROR: u32 = 3;
+const CELL_BOOL: u32 = 4;
+const CELL_REAL: u32 = 5;
+const CELL_ST: u32 = 6;
+const CELL_ISST: u32 = 7;
+const FMLA_STRING: u32 = 8;
+const FMLA_NUM: u32 = 9;
+const FMLA_BOOL: u32 = 10;
+const FMLA_ERROR: u32 = 11;
+const SST_ITEM: u32 = 19;
+const FONT: u32 = 43;
+const FMT: u32 = 44;
+const FILL: u32 = 45;
+const BORDER: u32 = 46;
+const XF: u32 = 47;
+const COL_INFO: u32 = 60;
+const CELL_RSTRING: u32 = 62;
+const WS_PROP: u32 = 147;
+const PANE: u32 = 151;
+const WB_PROP: u32 = 153;
+const BUNDLE_SH: u32 = 156;
+const MERGE_CELL: u32 = 176;
+const WS_FMT_INFO: u32 = 485;
+const HLINK: u32 = 494;
+const BEGIN_CELL_XFS: u32 = 617;
+const END_CELL_XFS: u32 = 618;
+
+const GRADIENT_FILL: u32 = 0x28;
+
+// formatting runs of a string: index of the first char and font index
+type Runs = Vec<(u16, u16)>;
+
+// workbook part of xlsb is listed in [Content_Types].xml with its own content type
+pub(crate) fn is_xlsb(zip: &mut Archive) -> Result<bool, XlsxError> {
+    let part = "[Content_Types].xml";
+    let mut xml = match xml_reader(zip, part) {
+        None => return Ok(false),
+        Some(x) => x?,
+    };
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        match xml.read_event_into(&mut buf) {
+            Ok(Event::Start(ref e)) if e.name().as_ref() == b"Override" => {
+                let binary = e.attributes().flatten()
+                    .any(|a| a.key.as_ref() == b"ContentType" && a.value.as_ref() == MAIN_CONTENT_TYPE.as_bytes());
+                if binary {
+                    return Ok(true);
+                }
+            },
+            Ok(Event::Eof) => return Ok(false),
+            Err(e) => return Err(xml_error(part, &xml, e)),
+            _ => (),
+        }
+    }
+}
+
+fn read_part(zip: &mut Archive, path: &str) -> Option<Result<Vec<u8>, XlsxError>> {
+    let mut file = zip.by_name(path).ok()?;
+    let mut data = Vec::with_capacity(file.size() as usize);
+    Some(file.read_to_end(&mut data).map(|_| data).map_err(|e| XlsxError::BadZip { reason: e.to_string() }))
+}
+
+fn malformed(part: &str, offset: usize, reason: &str) -> XlsxError {
+    XlsxError::MalformedRecord { part: part.to_string(), offset, reason: reason.to_string() }
+}
+
+// record type and size are variable length numbers, 7 bits in each byte
+struct Records<'a> {
+    part: &'a str,
+    data: &'a [u8],
+    pos: usize,
+}
+
+struct Record<'a> {
+    kind: u32,
+    offset: usize,
+    data: &'a [u8],
+}
+
+impl<'a> Records<'a> {
+    fn new(part: &'a str, data: &'a [u8]) -> Records<'a> {
+        Records { part, data, pos: 0 }
+    }
+    fn number(&mut self, max_bytes: usize) -> Result<u32, XlsxError> {
+        let mut value = 0;
+        for i in 0..max_bytes {
+            let byte = *self.data.get(self.pos).ok_or_else(|| malformed(self.part, self.pos, "record header is out of part"))?;
+            self.pos += 1;
+            value |= ((byte & 0x7F) as u32) << (7 * i);
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+        Ok(value)
+    }
+    fn next(&mut self) -> Result<Option<Record<'a>>, XlsxError> {
+        if self.pos >= self.data.len() {
+            return Ok(None);
+        }
+        let offset = self.pos;
+        let kind = self.number(2)?;
+        let size = self.number(4)? as usize;
+        let data = self.data.get(self.pos..self.pos + size)
+            .ok_or_else(|| malformed(self.part, offset, "record is out of part"))?;
+        self.pos += size;
+        Ok(Some(Record { kind, offset, data }))
+    }
+}
+
+// sequential reading of record data
+struct RecordReader<'a> {
+    part: &'a str,
+    offset: usize,
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> RecordReader<'a> {
+    fn new(part: &'a str, record: &Record<'a>) -> RecordReader<'a> {
+        RecordReader { part, offset: record.offset, data: record.data, pos: 0 }
+    }
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], XlsxError> {
+        let bytes = self.data.get(self.pos..self.pos + count)
+            .ok_or_else(|| malformed(self.part, self.offset, "record is too short"))?;
+        self.pos += count;
+        Ok(bytes)
+    }
+    fn u8(&mut self) -> Result<u8, XlsxError> {
+        Ok(self.bytes(1)?[0])
+    }
+    fn u16(&mut self) -> Result<u16, XlsxError> {
+        let b = self.bytes(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+    fn u32(&mut self) -> Result<u32, XlsxError> {
+        let b = self.bytes(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+    fn f64(&mut self) -> Result<f64, XlsxError> {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(self.bytes(8)?);
+        Ok(f64::from_le_bytes(bytes))
+    }
+    fn skip(&mut self, count: usize) -> Result<(), XlsxError> {
+        self.bytes(count).map(|_| ())
+    }
+    fn chars(&mut self) -> Result<Option<Vec<u16>>, XlsxError> {
+        let count = self.u32()?;
+        if count == 0xFFFFFFFF {
+            return Ok(None);
+        }
+        let bytes = self.bytes(count as usize * 2)?;
+        Ok(Some(bytes.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect()))
+    }
+    // XLWideString, null strings are read as empty
+    fn string(&mut self) -> Result<String, XlsxError> {
+        Ok(String::from_utf16_lossy(&self.chars()?.unwrap_or_default()))
+    }
+    // RichStr, text and formatting runs
+    fn rich_string(&mut self) -> Result<(Vec<u16>, Runs), XlsxError> {
+        let flags = self.u8()?;
+        let chars = self.chars()?.unwrap_or_default();
+        let mut runs = vec!();
+        if flags & 0x01 > 0 {
+            let count = self.u32()?;
+            for _ in 0..count {
+                runs.push((self.u16()?, self.u16()?));
+            }
+        }
+        Ok((chars, runs))
+    }
+    // BrtColor: type, index, tint and rgb
+    fn color(&mut self, theme: &Theme) -> Result<Option<String>, XlsxError> {
+        let kind = self.u8()? >> 1;
+        let index = self.u8()?;
+        let tint = i16::from_le_bytes([self.u8()?, self.u8()?]) as f64 / 32767.0;
+        let (red, green, blue) = (self.u8()?, self.u8()?, self.u8()?);
+        self.skip(1)?;
+        Ok(match kind {
+            1 => Some(theme.indexed_color(&index.to_string())),
+            2 => get_xlsx_rgb(&format!("{:02X}{:02X}{:02X}", red, green, blue)),
+            3 => theme.color(index as usize, tint),
+            _ => None,
+        })
+    }
+    // Cell structure at the start of cell records: column and style
+    fn cell(&mut self) -> Result<(u32, u32), XlsxError> {
+        let col = self.u32()?;
+        let style = self.u32()? & 0x00FFFFFF;
+        Ok((col, style))
+    }
+}
+
+struct Font {
+    style: Dict,
+    script: u16,
+}
+
+struct Xf {
+    font: usize,
+    fill: usize,
+    border: usize,
+    format: u16,
+    align: u8,
+    vertical: u8,
+    wrap: bool,
+}
+
+pub(crate) struct Xlsb {
+    zip: Archive,
+    // descriptor and path of each sheet
+    sheets: Vec<(SheetDescriptor, String)>,
+    strings: Vec<(String, Option<Vec<TextRun>>)>,
+    fonts: Vec<Font>,
+    fills: Vec<Dict>,
+    borders: Vec<Vec<Border>>,
+    xfs: Vec<Xf>,
+    formats: HashMap<u16, String>,
+    date1904: bool,
+    theme: Theme,
+}
+
+impl Xlsb {
+    pub fn from_zip(zip: Archive) -> Result<Xlsb, XlsxError> {
+        let mut xlsb = Xlsb {
+            zip,
+            sheets: vec!(),
+            strings: vec!(),
+            fonts: vec!(),
+            fills: vec!(),
+            borders: vec!(),
+            xfs: vec!(),
+            formats: HashMap::new(),
+            date1904: false,
+            theme: Theme::default(),
+        };
+
+        let part = "xl/_rels/workbook.bin.rels";
+        let rels = match read_typed_relationships(&mut xlsb.zip, part)? {
+            Some(rels) => rels,
+            None => return Err(XlsxError::MissingPart { part: String::from(part) }),
+        };
+        xlsb.read_workbook(&rels)?;
+        let part = "xl/theme/theme1.xml";
+        if let Some(xml) = xml_reader(&mut xlsb.zip, part) {
+            xlsb.theme = Theme::read(&mut xml?, part)?;
+        }
+        xlsb.read_tab_colors()?;
+        xlsb.read_styles()?;
+        xlsb.read_shared_strings()?;
+        Ok(xlsb)
+    }
+    pub fn sheet_names(&self) -> Vec<String> {
+        self.sheets.iter().map(|(sheet, _)| sheet.name.clone()).collect()
+    }
+    pub fn sheets(&self) -> Vec<SheetDescriptor> {
+        self.sheets.iter().map(|(sheet, _)| sheet.clone()).collect()
+    }
+    fn read_workbook(&mut self, relationships: &TypedRelationships) -> Result<(), XlsxError> {
+        let part = "xl/workbook.bin";
+        let data = match read_part(&mut self.zip, part) {
+            None => return Err(XlsxError::MissingPart { part: String::from(part) }),
+            Some(data) => data?,
+        };
+        let mut records = Records::new(part, &data);
+        while let Some(record) = records.next()? {
+            let mut r = RecordReader::new(part, &record);
+            match record.kind {
+                WB_PROP => {
+                    self.date1904 = r.u32()? & 0x01 > 0;
+                },
+                BUNDLE_SH => {
+                    let state = match r.u32()? {
+                        1 => SheetState::Hidden,
+                        2 => SheetState::VeryHidden,
+                        _ => SheetState::Visible,
+                    };
+                    let sheet_id = r.u32()?;
+                    let id = r.string()?;
+                    let name = r.string()?;
+                    let (path, kind) = relationships.get(&id)
+                        .ok_or_else(|| malformed(part, record.offset, &format!("unknown relationship {}", id)))?;
+                    let sheet = SheetDescriptor { state, kind: sheet_kind(kind), ..SheetDescriptor::new(name, sheet_id) };
+                    self.sheets.push((sheet, part_path(path)));
+                },
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+    fn read_styles(&mut self) -> Result<(), XlsxError> {
+        let part = "xl/styles.bin";
+        let data = match read_part(&mut self.zip, part) {
+            None => return Ok(()),
+            Some(data) => data?,
+        };
+        let mut records = Records::new(part, &data);
+        // cell style xfs are listed before cell xfs with the same record type
+        let mut cell_xfs = false;
+        while let Some(record) = records.next()? {
+            let mut r = RecordReader::new(part, &record);
+            match record.kind {
+                FMT => {
+                    let id = r.u16()?;
+                    let code = r.string()?;
+                    self.formats.insert(id, code);
+                },
+                FONT => {
+                    let font = self.read_font(&mut r)?;
+                    self.fonts.push(font);
+                },
+                FILL => {
+                    let fill = self.read_fill(&mut r)?;
+                    self.fills.push(fill.get_computed_style());
+                },
+                BORDER => {
+                    let flags = r.u8()?;
+                    let mut borders = vec!();
+                    for position in [BorderPosition::Top, BorderPosition::Bottom, BorderPosition::Left, BorderPosition::Right] {
+                        borders.push(self.read_border(&mut r, position)?);
+                    }
+                    // both diagonals share the same line
+                    let diagonal = self.read_border(&mut r, BorderPosition::DiagonalDown)?;
+                    if flags & 0x02 > 0 {
+                        let mut up = diagonal.clone();
+                        up.set_position(BorderPosition::DiagonalUp);
+                        borders.push(up);
+                    }
+                    if flags & 0x01 > 0 {
+                        borders.push(diagonal);
+                    }
+                    self.borders.push(borders);
+                },
+                BEGIN_CELL_XFS => cell_xfs = true,
+                END_CELL_XFS => cell_xfs = false,
+                XF if cell_xfs => {
+                    r.skip(2)?;
+                    let format = r.u16()?;
+                    let font = r.u16()? as usize;
+                    let fill = r.u16()? as usize;
+                    let border = r.u16()? as usize;
+                    r.skip(2)?;
+                    let alignment = r.u16()?;
+                    self.xfs.push(Xf {
+                        font,
+                        fill,
+                        border,
+                        format,
+                        align: (alignment & 0x07) as u8,
+                        vertical: ((alignment >> 3) & 0x07) as u8,
+                        wrap: alignment & 0x40 > 0,
+                    });
+                },
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+    fn read_font(&self, r: &mut RecordReader) -> Result<Font, XlsxError> {
+        let height = r.u16()?;
+        let flags = r.u16()?;
+        let weight = r.u16()?;
+        let script = r.u16()?;
+        let underline = r.u8()?;
+        r.skip(3)?;
+        let color = r.color(&self.theme)?;
+        r.skip(1)?;
+        let name = r.string()?;
+
+        let mut style = Dict::new();
+        let size = height as f32 / 20.0;
+        style.insert(String::from("fontSize"), JsonValue::String((size / PT_COEF).to_string() + "px"));
+        style.insert(String::from("fontFamily"), JsonValue::String(name));
+        if let Some(color) = color {
+            style.insert(String::from("color"), JsonValue::String(color));
+        }
+        if weight >= 700 {
+            style.insert(String::from("fontWeight"), JsonValue::String(String::from("bold")));
+        }
+        if flags & 0x02 > 0 {
+            style.insert(String::from("fontStyle"), JsonValue::String(String::from("italic")));
+        }
+        let decoration = match (underline > 0, flags & 0x08 > 0) {
+            (true, true) => Some("line-through underline"),
+            (true, false) => Some("underline"),
+            (false, true) => Some("line-through"),
+            (false, false) => None,
+        };
+        if let Some(decoration) = decoration {
+            style.insert(String::from("textDecoration"), JsonValue::String(String::from(decoration)));
+        }
+        Ok(Font { style, script })
+    }
+    fn read_fill(&self, r: &mut RecordReader) -> Result<Fill, XlsxError> {
+        let pattern = r.u32()?;
+        let fg_color = r.color(&self.theme)?;
+        let bg_color = r.color(&self.theme)?;
+        let mut fill = Fill::new();
+        if pattern == GRADIENT_FILL {
+            let mut gradient = Gradient::default();
+            gradient.path = r.u32()? == 1;
+            gradient.degree = r.f64()?;
+            gradient.left = r.f64()?;
+            gradient.right = r.f64()?;
+            gradient.top = r.f64()?;
+            gradient.bottom = r.f64()?;
+            fill.set_gradient(gradient);
+            let stops = r.u32()?;
+            for _ in 0..stops {
+                let color = r.color(&self.theme)?;
+                fill.add_stop(r.f64()?);
+                if let Some(color) = color {
+                    fill.set_stop_color(color);
+                }
+            }
+        } else if let Some(name) = pattern_name(pattern as u8) {
+            fill.set_pattern(String::from(name));
+            if let Some(color) = fg_color {
+                fill.set_fg_color(color);
+            }
+            if let Some(color) = bg_color {
+                fill.set_bg_color(color);
+            }
+        }
+        Ok(fill)
+    }
+    fn read_border(&self, r: &mut RecordReader, position: BorderPosition) -> Result<Border, XlsxError> {
+        let line = r.u8()?;
+        r.skip(1)?;
+        let color = r.color(&self.theme)?;
+        let mut border = Border::new(position);
+        if let Some(name) = border_name(line) {
+            border.set_style(String::from(name));
+            if let Some(color) = color {
+                border.set_color(color);
+            }
+        }
+        Ok(border)
+    }
+    fn read_shared_strings(&mut self) -> Result<(), XlsxError> {
+        let part = "xl/sharedStrings.bin";
+        let data = match read_part(&mut self.zip, part) {
+            None => return Ok(()),
+            Some(data) => data?,
+        };
+        let mut records = Records::new(part, &data);
+        while let Some(record) = records.next()? {
+            if record.kind == SST_ITEM {
+                let (chars, runs) = RecordReader::new(part, &record).rich_string()?;
+                let runs = self.text_runs(&chars, &runs);
+                self.strings.push((String::from_utf16_lossy(&chars), runs));
+            }
+        }
+        Ok(())
+    }
+    fn text_runs(&self, chars: &[u16], runs: &[(u16, u16)]) -> Option<Vec<TextRun>> {
+        text_runs(chars, runs, |index| {
+            let font = match self.fonts.get(index as usize) {
+                Some(font) => font,
+                None => return Dict::new(),
+            };
+            let mut style = font.style.clone();
+            match font.script {
+                1 => { style.insert(String::from("verticalAlign"), JsonValue::String(String::from("super"))); },
+                2 => { style.insert(String::from("verticalAlign"), JsonValue::String(String::from("sub"))); },
+                _ => (),
+            }
+            style
+        })
+    }
+    // number format of each cell style
+    fn cell_formats(&self) -> Vec<String> {
+        self.xfs.iter().map(|xf| self.format(xf.format)).collect()
+    }
+    fn format(&self, id: u16) -> String {
+        match self.formats.get(&id) {
+            Some(format) => format.clone(),
+            None => get_format(&id.to_string()).unwrap_or_else(|| String::from("General")),
+        }
+    }
+    pub fn styles_with(&self, flags: u32) -> Vec<Style> {
+        self.xfs.iter().map(|xf| self.style(xf, flags)).collect()
+    }
+    fn style(&self, xf: &Xf, flags: u32) -> Style {
+        let mut style = Style::new();
+        if let Some(font) = self.fonts.get(xf.font) {
+            style.extend(font.style.clone());
+        }
+        if let Some(borders) = self.borders.get(xf.border) {
+            let mut details: HashMap<String, BorderInfo> = HashMap::new();
+            for border in borders.iter().cloned() {
+                if flags & WITH_BORDER_DETAILS > 0 {
+                    if let Some(info) = border.get_info() {
+                        details.insert(border.get_name(), info);
+                    }
+                }
+                let (key, value) = border.get_computed_style();
+                if !value.is_empty() {
+                    style.insert(key, JsonValue::String(value));
+                }
+            }
+            if !details.is_empty() {
+                style.insert(String::from("borders"), serde_json::to_value(details).unwrap_or_default());
+            }
+        }
+        if let Some(fill) = self.fills.get(xf.fill) {
+            style.extend(fill.clone());
+        }
+
+        style.insert(String::from("format"), JsonValue::String(self.format(xf.format)));
+        if let Some(align) = align_name(xf.align) {
+            style.insert(String::from("align"), JsonValue::String(String::from(align)));
+        }
+        if let Some(vertical) = vertical_name(xf.vertical) {
+            style.insert(String::from("verticalAlign"), JsonValue::String(String::from(vertical)));
+        }
+        if xf.wrap {
+            style.insert(String::from("wrapText"), JsonValue::Bool(true));
+        }
+        style
+    }
+    // sheet properties are the second record of the sheet, so only the start of the part is read
+    fn read_tab_colors(&mut self) -> Result<(), XlsxError> {
+        for (sheet, path) in self.sheets.iter_mut() {
+            let mut data = vec!();
+            match self.zip.by_name(path) {
+                Ok(file) => file.take(1024).read_to_end(&mut data).map_err(|e| XlsxError::BadZip { reason: e.to_string() })?,
+                Err(_) => continue,
+            };
+            let mut records = Records::new(path, &data);
+            // the last record can be cut
+            while let Ok(Some(record)) = records.next() {
+                if record.kind == WS_PROP {
+                    let mut r = RecordReader::new(path, &record);
+                    // fShowAutoBreaks, two reserved bits and fPublish are before fDialog
+                    if r.u8()? & 0x10 > 0 {
+                        sheet.kind = SheetKind::Dialogsheet;
+                    }
+                    r.skip(2)?;
+                    sheet.tab_color = r.color(&self.theme)?;
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+    pub fn sheet_with(&mut self, sheet_name: &str, flags: u32) -> Result<SheetData, XlsxError> {
+        Ok(self.read_sheet(sheet_name, flags)?.into_sheet(flags))
+    }
+    // binary sheet is read at once, like xls; formulas are not decompiled, cached values are used
+    pub fn read_sheet(&mut self, sheet_name: &str, flags: u32) -> Result<ParsedSheet, XlsxError> {
+        let (name, path) = match self.sheets.iter().find(|(sheet, _)| sheet.name == sheet_name) {
+            Some((sheet, path)) => (sheet.name.clone(), path.clone()),
+            None => return Err(XlsxError::UnknownSheet { name: sheet_name.to_string() }),
+        };
+        let links = read_relationships(&mut self.zip, &rels_path(&path))?.unwrap_or_default();
+        let data = match read_part(&mut self.zip, &path) {
+            None => return Err(XlsxError::MissingPart { part: path }),
+            Some(data) => data?,
+        };
+        let part = path.as_str();
+        let cell_formats = self.cell_formats();
+        let options = CellOptions::new(flags, self.date1904, &cell_formats, &self.theme);
+
+        let mut sheet = ParsedSheet::new(name);
+        // hyperlinks are listed after cells
+        let mut hyperlinks: HashMap<(u32, u32), String> = HashMap::new();
+        let mut row = 0;
+
+        let mut records = Records::new(part, &data);
+        while let Some(record) = records.next()? {
+            let mut r = RecordReader::new(part, &record);
+            match record.kind {
+                ROW_HDR => {
+                    row = r.u32()?;
+                    r.skip(4)?;
+                    let height = r.u16()? as f32 / 20.0 / HEIGHT_COEF;
+                    let hidden = if r.u16()? & 0x1000 > 0 { Some(true) } else { None };
+                    sheet.rows.insert(row, Row { index: row, height, hidden, cells: vec!() });
+                },
+                CELL_BLANK..=FMLA_ERROR | CELL_RSTRING => {
+                    let (col, style) = r.cell()?;
+                    let mut cell = Cell { s: style, ..Cell::new() };
+                    let value = match record.kind {
+                        CELL_RK => Some((number_text(rk_number(r.u32()?)), CellType::Number)),
+                        CELL_REAL | FMLA_NUM => Some((number_text(r.f64()?), CellType::Number)),
+                        CELL_BOOL | FMLA_BOOL => Some((r.u8()?.to_string(), CellType::Boolean)),
+                        CELL_ERROR | FMLA_ERROR => Some((error_text(r.u8()?), CellType::Error)),
+                        CELL_ISST => {
+                            let index = r.u32()? as usize;
+                            let (text, runs) = self.strings.get(index)
+                                .ok_or_else(|| malformed(part, record.offset, &format!("invalid shared string index {}", index)))?;
+                            options.set_text(&mut cell, text.clone(), runs.clone(), &cell_formats);
+                            None
+                        },
+                        CELL_ST | FMLA_STRING => {
+                            options.set_text(&mut cell, r.string()?, None, &cell_formats);
+                            None
+                        },
+                        CELL_RSTRING => {
+                            let (chars, runs) = r.rich_string()?;
+                            let runs = self.text_runs(&chars, &runs);
+                            options.set_text(&mut cell, String::from_utf16_lossy(&chars), runs, &cell_formats);
+                            None
+                        },
+                        _ => None,
+                    };
+                    let cell_type = match value {
+                        Some((value, cell_type)) => options.set_value(&mut cell, value, cell_type, &cell_formats),
+                        None => CellType::String,
+                    };
+                    set_type(&mut cell, cell_type);
+                    sheet.cells.push(SparseCell { r: row, c: col, cell });
+                },
+                WS_FMT_INFO => {
+                    let width = r.u32()?;
+                    if width != 0xFFFFFFFF {
+                        sheet.default_col_width = width as f32 / 256.0 * WIDTH_COEF;
+                    }
+                    r.skip(2)?;
+                    sheet.default_row_height = r.u16()? as f32 / 20.0 / HEIGHT_COEF;
+                },
+                COL_INFO => {
+                    let first = r.u32()? as usize;
+                    let last = r.u32()? as usize;
+                    let width = r.u32()? as f32 / 256.0 * WIDTH_COEF;
+                    r.skip(4)?;
+                    let hidden = if r.u16()? & 0x01 > 0 { Some(true) } else { None };
+                    for i in sheet.data.cols.len()..=last.min(16383) {
+                        if i >= first {
+                            sheet.data.cols.push(ColumnData { width, hidden, index: None });
+                        } else {
+                            sheet.data.cols.push(ColumnData { width: sheet.default_col_width, hidden: None, index: None });
+                        }
+                    }
+                },
+                MERGE_CELL => {
+                    let (first_row, last_row, first_col, last_col) = (r.u32()?, r.u32()?, r.u32()?, r.u32()?);
+                    sheet.data.merged.push(MergedCell {
+                        from: CellCoords { column: first_col, row: first_row },
+                        to: CellCoords { column: last_col, row: last_row },
+                    });
+                },
+                PANE => {
+                    let (cols, rows) = (r.f64()?, r.f64()?);
+                    r.skip(12)?;
+                    if r.u8()? & 0x01 > 0 {
+                        if cols > 0.0 {
+                            sheet.data.frozen_cols = Some(cols as u32);
+                        }
+                        if rows > 0.0 {
+                            sheet.data.frozen_rows = Some(rows as u32);
+                        }
+                    }
+                },
+                HLINK => {
+                    let (first_row, _, first_col, _) = (r.u32()?, r.u32()?, r.u32()?, r.u32()?);
+                    if let Some(link) = r.chars()?.and_then(|id| links.get(&String::from_utf16_lossy(&id))) {
+                        hyperlinks.insert((first_row, first_col), link.clone());
+                    }
+                },
+                _ => (),
+            }
+        }
+
+        for cell in sheet.cells.iter_mut() {
+            if let Some(link) = hyperlinks.remove(&(cell.r, cell.c)) {
+                cell.cell.hyperlink = Some(link);
+            }
+        }
+        Ok(sheet)
+    }
+}
+
+// BIFF12 record, type and size are written as 7 bit numbers
+#[cfg(test)]
+fn bin_record(kind: u32, data: &[u8]) -> Vec<u8> {
+    let mut result = vec!();
+    for (mut value, max) in [(kind, 2), (data.len() as u32, 4)] {
+        for _ in 0..max {
+            let byte = (value & 0x7F) as u8;
+            value >>= 7;
+            if value == 0 {
+                result.push(byte);
+                break;
+            }
+            result.push(byte | 0x80);
+        }
+    }
+    result.extend_from_slice(data);
+    result
+}
+
+#[cfg(test)]
+fn wide_string(text: &str) -> Vec<u8> {
+    let chars: Vec<u16> = text.encode_utf16().collect();
+    let mut result = (chars.len() as u32).to_le_bytes().to_vec();
+    result.extend(chars.iter().flat_map(|c| c.to_le_bytes()));
+    result
+}
+
+#[cfg(test)]
+fn bin_cell(kind: u32, col: u32, style: u32, value: &[u8]) -> Vec<u8> {
+    bin_record(kind, &[&col.to_le_bytes()[..], &style.to_le_bytes(), value].concat())
+}
+
+// xlsb workbook with a single sheet "Data"
+#[cfg(test)]
+fn build_xlsb() -> Vec<u8> {
+    use std::io::Write;
+
+    let rgb = |r: u8, g: u8, b: u8| vec![0x05, 0, 0, 0, r, g, b, 0xFF];
+    let font = |weight: u16, color: Vec<u8>, name: &str| {
+        let data = [&220u16.to_le_bytes()[..], &[0, 0], &weight.to_le_bytes(), &[0, 0, 0, 0, 0, 0], &color, &[0], &wide_string(name)].concat();
+        bin_record(43, &data)
+    };
+    let blxf = |line: u8| [&[line, 0][..], &rgb(0, 0, 0)].concat();
+    let xf = |format: u16, font: u16, fill: u16, border: u16, alignment: u16| {
+        let data = [0u16, format, font, fill, border, 0, alignment, 0].iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<u8>>();
+        bin_record(47, &data)
+    };
+    let styles = [
+        bin_record(44, &[&164u16.to_le_bytes()[..], &wide_string("0.0%")].concat()),
+        font(400, vec![0; 8], "Calibri"),
+        font(700, rgb(255, 0, 0), "Arial"),
+        bin_record(45, &[&0u32.to_le_bytes()[..], &[0; 16]].concat()),
+        bin_record(45, &[&1u32.to_le_bytes()[..], &rgb(255, 255, 0), &rgb(0, 0, 0)].concat()),
+        bin_record(46, &[vec![0], blxf(0), blxf(0), blxf(0), blxf(0), blxf(0)].concat()),
+        bin_record(46, &[vec![0], blxf(0), blxf(0), blxf(1), blxf(0), blxf(0)].concat()),
+        // cell style xf is not a cell format
+        xf(0, 1, 1, 1, 0),
+        bin_record(617, &[1, 0, 0, 0]),
+        xf(0, 0, 0, 0, 0),
+        xf(164, 1, 1, 1, 0x0042),
+        bin_record(618, &[]),
+    ].concat();
+
+    let strings = [
+        bin_record(19, &[&[0][..], &wide_string("hello")].concat()),
+        bin_record(19, &[&[1][..], &wide_string("bold text"), &1u32.to_le_bytes(), &[5, 0, 1, 0]].concat()),
+    ].concat();
+
+    let row = |index: u32, height: u16| bin_record(0, &[&index.to_le_bytes()[..], &[0, 0, 0, 0], &height.to_le_bytes(), &[0, 0]].concat());
+    let sheet = [
+        // published worksheet
+        bin_record(147, &[&[0x08u8, 0, 0][..], &rgb(0, 128, 0), &[0; 12]].concat()),
+        bin_record(485, &[&0xFFFFFFFFu32.to_le_bytes()[..], &[8, 0], &300u16.to_le_bytes()].concat()),
+        bin_record(60, &[0u32, 1, 20 * 256, 0].iter().flat_map(|v| v.to_le_bytes()).chain([0, 0]).collect::<Vec<u8>>()),
+        row(0, 600),
+        bin_cell(7, 0, 0, &0u32.to_le_bytes()),
+        bin_cell(5, 1, 1, &0.25f64.to_le_bytes()),
+        row(1, 300),
+        bin_cell(7, 0, 0, &1u32.to_le_bytes()),
+        row(2, 300),
+        bin_cell(2, 0, 0, &((42u32 << 2) | 2).to_le_bytes()),
+        bin_cell(8, 1, 0, &[&wide_string("formula!")[..], &[0, 0]].concat()),
+        bin_cell(4, 2, 0, &[1]),
+        bin_cell(3, 3, 0, &[0x07]),
+        bin_record(176, &[2u32, 2, 1, 3].iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<u8>>()),
+        bin_record(494, &[&[0u8; 16][..], &wide_string("rId1"), &wide_string(""), &wide_string(""), &wide_string("")].concat()),
+    ].concat();
+
+    let workbook = [
+        bin_record(153, &[0; 12]),
+        bin_record(156, &[&[2u8, 0, 0, 0, 1, 0, 0, 0][..], &wide_string("rId1"), &wide_string("Data")].concat()),
+        bin_record(156, &[&[0u8, 0, 0, 0, 2, 0, 0, 0][..], &wide_string("rId2"), &wide_string("Dialog")].concat()),
+    ].concat();
+
+    let parts: Vec<(&str, Vec<u8>)> = vec![
+        ("[Content_Types].xml", br#"<Types><Override PartName="/xl/workbook.bin" ContentType="application/vnd.ms-excel.sheet.binary.macroEnabled.main"/></Types>"#.to_vec()),
+        ("xl/_rels/workbook.bin.rels", br#"<Relationships><Relationship Id="rId1" Target="worksheets/sheet1.bin"/><Relationship Id="rId2" Target="worksheets/sheet2.bin"/></Relationships>"#.to_vec()),
+        ("xl/worksheets/_rels/sheet1.bin.rels", br#"<Relationships><Relationship Id="rId1" Target="https://dhtmlx.com" TargetMode="External"/></Relationships>"#.to_vec()),
+        ("xl/workbook.bin", workbook),
+        ("xl/styles.bin", styles),
+        ("xl/sharedStrings.bin", strings),
+        ("xl/worksheets/sheet1.bin", sheet),
+        ("xl/worksheets/sheet2.bin", bin_record(147, &[&[0x10u8, 0, 0][..], &[0; 20]].concat())),
+    ];
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(vec!()));
+    for (name, content) in parts.iter() {
+        zip.start_file(*name, zip::write::FileOptions::default()).unwrap();
+        zip.write_all(content).unwrap();
+    }
+    zip.finish().unwrap().into_inner()
+}
+
+#[test]
+fn test_xlsb_sheet() {
+    use crate::{CellValue, Workbook, WITH_RICH_TEXT};
+
+    let mut xlsb = Workbook::open(&build_xlsb()).unwrap();
+    assert_eq!(xlsb.sheet_names(), vec![String::from("Data"), String::from("Dialog")]);
+    let sheets = xlsb.sheets();
+    // published worksheet is not a dialog
+    assert_eq!((sheets[0].id, sheets[0].state, sheets[0].kind), (1, SheetState::VeryHidden, SheetKind::Worksheet));
+    assert_eq!(sheets[0].tab_color.as_deref(), Some("rgba(0,128,0,1)"));
+    assert_eq!((sheets[1].id, sheets[1].state, sheets[1].kind), (2, SheetState::Visible, SheetKind::Dialogsheet));
+    assert!(xlsb.sheet("Dialog").unwrap().cells.is_empty());
+
+    let data = xlsb.sheet_with("Data", WITH_RICH_TEXT).unwrap();
+    let value = |row: usize, col: usize| data.cells[row][col].as_ref().and_then(|c| c.v.clone());
+    assert_eq!(data.cells.len(), 3);
+    assert_eq!(value(0, 0), Some(CellValue::String(String::from("hello"))));
+    assert_eq!(data.cells[0][0].as_ref().unwrap().hyperlink.as_deref(), Some("https://dhtmlx.com"));
+    assert_eq!(value(0, 1), Some(CellValue::String(String::from("0.25"))));
+    assert_eq!(data.cells[0][1].as_ref().unwrap().s, 1);
+    assert_eq!(value(2, 0), Some(CellValue::String(String::from("42"))));
+    assert_eq!(value(2, 1), Some(CellValue::String(String::from("formula!"))));
+    assert_eq!(data.cells[2][1].as_ref().unwrap().t, Some(CellType::String));
+    assert_eq!(data.cells[2][2].as_ref().unwrap().t, Some(CellType::Boolean));
+    assert_eq!(value(2, 3), Some(CellValue::String(String::from("#DIV/0!"))));
+
+    let rich = data.cells[1][0].as_ref().unwrap().rich.as_ref().unwrap();
+    assert_eq!(rich.iter().map(|r| r.text.as_str()).collect::<Vec<&str>>(), vec!["bold ", "text"]);
+    assert_eq!(rich[1].style["fontWeight"], "bold");
+
+    assert_eq!(data.cols[0].width, 20.0 * crate::sheet::WIDTH_COEF);
+    assert_eq!(data.rows[0].height, 30.0 / crate::sheet::HEIGHT_COEF);
+    assert_eq!(data.rows[1].height, 15.0 / crate::sheet::HEIGHT_COEF);
+    assert_eq!(data.merged.len(), 1);
+    assert_eq!((data.merged[0].from.column, data.merged[0].to.column), (1, 3));
+
+    let styles = xlsb.styles().unwrap();
+    assert_eq!(styles.len(), 2);
+    assert_eq!(styles[1]["format"], "0.0%");
+    assert_eq!(styles[1]["align"], "center");
+    assert_eq!(styles[1]["wrapText"], true);
+    assert_eq!(styles[1]["fontFamily"], "Arial");
+    assert_eq!(styles[1]["color"], "rgba(255,0,0,1)");
+    assert_eq!(styles[1]["borderLeft"], "0.5px solid rgba(0,0,0,1)");
+    assert_eq!(styles[1]["background"], "rgba(255,255,0,1)");
+    assert!(!styles[0].contains_key("background"));
+
+    let rows: Vec<u32> = xlsb.rows("Data").unwrap().map(|row| row.unwrap().index).collect();
+    assert_eq!(rows, vec![0, 1, 2]);
+}